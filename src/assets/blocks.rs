@@ -1 +1,282 @@
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
 
+use bevy::asset::{io::Reader, AssetLoader, LoadContext, LoadState};
+use bevy::{log, platform::collections::HashMap, prelude::*};
+use derive_more::derive::{Display, Error, From};
+use serde::{Deserialize, Serialize};
+
+use crate::assets::prelude::*;
+use crate::identity::prelude::*;
+
+pub const BLOCK_DEFINITIONS_DIR: &str = "blocks";
+pub const MAX_LIGHT_LEVEL: u8 = 15;
+
+/// block definition, loaded from `namespace/blocks/name.json`
+/// the definition of `namespace/blocks/name.json` is keyed by `namespace:block/name`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Asset, TypePath)]
+#[serde(default)]
+pub struct BlockDefinition {
+    /// time factor to break the block, negative means unbreakable
+    pub hardness: f32,
+    /// whether entities collide with the block
+    pub solid: bool,
+    /// whether the block hides the faces behind it and blocks light
+    pub opaque: bool,
+    /// light level emitted by the block, 0..=15
+    pub light_emission: u8,
+    /// slipperiness of the top face
+    pub friction: f32,
+    pub sound_group: SoundGroup,
+    /// model id, default to the block id itself
+    pub model: Option<String>,
+    /// blockstate id, the variants are not resolved yet, so the block has no model while it is set
+    pub blockstate: Option<String>,
+    /// boxes in 1/16 of a block, default to the elements of the model
+    pub shape: Option<VoxelShape>,
+}
+
+impl Default for BlockDefinition {
+    fn default() -> Self {
+        Self {
+            hardness: 1.0,
+            solid: true,
+            opaque: true,
+            light_emission: 0,
+            friction: 0.6,
+            sound_group: SoundGroup::default(),
+            model: None,
+            blockstate: None,
+            shape: None,
+        }
+    }
+}
+
+impl BlockDefinition {
+    /// the model used to render the block
+    pub fn model_id(&self, block_id: &BlockId) -> Option<ModelId> {
+        if self.blockstate.is_some() {
+            return None;
+        }
+
+        match self.model {
+            Some(ref model) => ModelId::try_from(model.as_str())
+                .map_err(|err| log::error!("{}", err))
+                .ok(),
+            None => Some(block_id.clone().into()),
+        }
+    }
+
+    pub fn light_emission(&self) -> u8 {
+        self.light_emission.min(MAX_LIGHT_LEVEL)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum SoundGroup {
+    #[default]
+    Stone,
+    Wood,
+    Gravel,
+    Grass,
+    Sand,
+    Metal,
+    Glass,
+    Wool,
+}
+
+pub struct BlockDefinitionLoader;
+
+#[derive(Debug, Error, From, Display)]
+pub enum BlockDefinitionLoadError {
+    #[display("Failed to load block definition: {}", _0)]
+    Io(std::io::Error),
+    #[display("Block Definition Syntax Error: {}", _0)]
+    JsonError(serde_json::Error),
+}
+
+impl AssetLoader for BlockDefinitionLoader {
+    type Asset = BlockDefinition;
+    type Settings = ();
+    type Error = BlockDefinitionLoadError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::<u8>::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(serde_json::from_slice::<BlockDefinition>(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct BlockDefinitions {
+    definitions: HashMap<BlockId, BlockDefinition>,
+}
+
+impl Deref for BlockDefinitions {
+    type Target = HashMap<BlockId, BlockDefinition>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.definitions
+    }
+}
+
+impl DerefMut for BlockDefinitions {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.definitions
+    }
+}
+
+impl BlockDefinitions {
+    /// all the models used by the definitions
+    pub fn models(&self) -> Vec<ModelId> {
+        self.definitions
+            .iter()
+            .filter_map(|(block_id, definition)| definition.model_id(block_id))
+            .collect()
+    }
+
+    /// get the model of the block, fallback to the model with the same id
+    pub fn model_id(&self, block_id: &BlockId) -> Option<ModelId> {
+        match self.definitions.get(block_id) {
+            Some(definition) => definition.model_id(block_id),
+            None => Some(block_id.clone().into()),
+        }
+    }
+}
+
+/// `namespace/blocks/name.json` -> `namespace:block/name`
+pub fn definition_block_id(path: &str) -> Option<BlockId> {
+    let (ns, name) = path.split_once(&format!("/{}/", BLOCK_DEFINITIONS_DIR))?;
+    let name = name.strip_suffix(BlockId::EXTENSION)?.strip_suffix('.')?;
+    BlockId::try_from(format!("{}:block/{}", ns, name).as_str()).ok()
+}
+
+#[derive(Resource, Default)]
+pub struct BlockDefinitionHandles(HashMap<BlockId, Handle<BlockDefinition>>);
+
+/// run OnEnter AppLoadState::ModelLoading
+pub fn pre_block_load(
+    mut commands: Commands,
+    root: Res<AssetRoot>,
+    asset_server: Res<AssetServer>,
+    mut tracker: ResMut<LoadTracker>,
+) {
+    commands.insert_resource(BlockDefinitionHandles(load_definitions(
+        &root.0,
+        &asset_server,
+        &mut tracker,
+    )));
+}
+
+fn load_definitions(
+    root: &Path,
+    asset_server: &AssetServer,
+    tracker: &mut LoadTracker,
+) -> HashMap<BlockId, Handle<BlockDefinition>> {
+    discover_assets(root, BLOCK_DEFINITIONS_DIR, BlockId::EXTENSION)
+        .into_iter()
+        .filter_map(|path| match definition_block_id(&path) {
+            Some(block_id) => {
+                tracker.blocks.track(&path);
+                Some((block_id, asset_server.load(path)))
+            }
+            None => {
+                log::warn!("{} can not be convert to BlockId", path);
+                None
+            }
+        })
+        .collect()
+}
+
+/// run Update in AppLoadState::ModelLoaded, wait until every definition is loaded
+pub fn resolve_blocks(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    handles: Res<BlockDefinitionHandles>,
+    registry: Option<Res<BlockRegistry>>,
+    mut definition_assets: ResMut<Assets<BlockDefinition>>,
+) {
+    let loading = handles.0.values().any(|handle| {
+        !matches!(
+            asset_server.load_state(handle.id()),
+            LoadState::Loaded | LoadState::Failed(_)
+        )
+    });
+    if loading {
+        return;
+    }
+
+    let mut result = BlockDefinitions::default();
+    if let Some(registry) = registry {
+        result.extend(registry.definitions.clone());
+    }
+
+    for (block_id, handle) in &handles.0 {
+        match definition_assets.remove(handle.id()) {
+            Some(definition) => {
+                result.insert(block_id.clone(), definition);
+            }
+            None => log::error!("block definition {} load failed", block_id),
+        }
+    }
+
+    commands.remove_resource::<BlockDefinitionHandles>();
+    commands.insert_resource(result);
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_definition_parse() {
+        let definition = serde_json::from_value::<BlockDefinition>(json!({
+            "hardness": 0.5,
+            "sound_group": "gravel",
+            "light_emission": 20,
+        }))
+        .unwrap();
+
+        assert_eq!(definition.hardness, 0.5);
+        assert_eq!(definition.sound_group, SoundGroup::Gravel);
+        assert!(definition.solid && definition.opaque);
+        assert_eq!(definition.light_emission(), MAX_LIGHT_LEVEL);
+
+        let block_id = BlockId::from_static("bevy_craft:block/dirt");
+        assert_eq!(definition.model_id(&block_id), Some(block_id.into()));
+
+        let definition = serde_json::from_value::<BlockDefinition>(json!({
+            "model": "mymod:block/lamp_on",
+        }))
+        .unwrap();
+        assert_eq!(
+            definition.model_id(&BlockId::from_static("mymod:block/lamp")),
+            Some(ModelId::try_from("mymod:block/lamp_on").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_definition_block_id() {
+        assert_eq!(
+            definition_block_id("bevy_craft/blocks/dirt.json"),
+            Some(BlockId::from_static("bevy_craft:block/dirt"))
+        );
+        assert_eq!(
+            definition_block_id("mymod/blocks/ore/ruby.json"),
+            Some(BlockId::from_static("mymod:block/ore/ruby"))
+        );
+        assert_eq!(definition_block_id("mymod/models/block/ruby.json"), None);
+    }
+}
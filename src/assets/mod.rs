@@ -1,11 +1,19 @@
 pub(crate) mod blocks;
 pub(crate) mod models;
 pub(crate) mod plugin;
+pub(crate) mod progress;
+pub(crate) mod registry;
+pub(crate) mod shapes;
 pub(crate) mod textures;
+pub(crate) mod validate;
 
 pub mod prelude {
-    // pub use super::blocks::*;
+    pub use super::blocks::*;
     pub use super::models::prelude::*;
     pub use super::plugin::*;
+    pub use super::progress::*;
+    pub use super::registry::*;
+    pub use super::shapes::*;
     pub use super::textures::prelude::*;
+    pub use super::validate::*;
 }
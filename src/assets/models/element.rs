@@ -11,9 +11,48 @@ pub struct Element {
     pub to: [i8; 3],
     /// 立方体每个面的定义
     pub faces: HashMap<BlockFace, ElementFace>,
+    /// 是否使用平滑光照, false 时每个面使用单一光照
+    #[serde(default = "default_shade")]
+    pub shade: bool,
+}
+
+fn default_shade() -> bool {
+    true
 }
 
 impl Element {
+    pub fn new(from: [i8; 3], to: [i8; 3]) -> Self {
+        Self {
+            from,
+            to,
+            faces: HashMap::new(),
+            shade: true,
+        }
+    }
+
+    pub fn with_shade(mut self, shade: bool) -> Self {
+        self.shade = shade;
+        self
+    }
+
+    pub fn with_face(mut self, face: BlockFace, data: ElementFace) -> Self {
+        self.faces.insert(face, data);
+        self
+    }
+
+    /// add all six faces with the same texture, the faces on the block boundary are culled
+    pub fn with_all_faces(mut self, texture: impl Into<Texture>) -> Self {
+        let texture = texture.into();
+        for face in BlockFace::ALL {
+            let mut data = ElementFace::new(texture.clone());
+            if self.is_normal_face(face) {
+                data = data.with_cullface(face);
+            }
+            self.faces.insert(face, data);
+        }
+        self
+    }
+
     pub fn faces(&self, face: BlockFace) -> Option<Face<'_>> {
         self.faces.get(&face).map(|data| Face {
             from: self.from,
@@ -42,6 +81,35 @@ pub struct ElementFace {
     pub cullface: Option<BlockFace>,
     /// 材质标签
     pub texture: Texture,
+    /// 染色索引, 0 为草的颜色, 1 为树叶的颜色
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tintindex: Option<u8>,
+}
+
+impl ElementFace {
+    pub fn new(texture: impl Into<Texture>) -> Self {
+        Self {
+            uv: None,
+            cullface: None,
+            texture: texture.into(),
+            tintindex: None,
+        }
+    }
+
+    pub fn with_uv(mut self, uv: [i8; 4]) -> Self {
+        self.uv = Some(uv);
+        self
+    }
+
+    pub fn with_tintindex(mut self, tintindex: u8) -> Self {
+        self.tintindex = Some(tintindex);
+        self
+    }
+
+    pub fn with_cullface(mut self, cullface: BlockFace) -> Self {
+        self.cullface = Some(cullface);
+        self
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -65,6 +133,15 @@ pub enum Axis {
 }
 
 impl BlockFace {
+    pub const ALL: [BlockFace; 6] = [
+        BlockFace::Down,
+        BlockFace::Up,
+        BlockFace::North,
+        BlockFace::South,
+        BlockFace::West,
+        BlockFace::East,
+    ];
+
     #[rustfmt::skip]
     pub const fn normal(&self) -> [f32; 3] {
         match self {
@@ -1,13 +1,17 @@
+use std::path::Path;
+
 use crate::identity::prelude::*;
-use bevy::asset::{io::Reader, AssetLoader, LoadContext};
+use bevy::asset::{io::Reader, AssetLoader, LoadContext, LoadState};
 use bevy::log;
+use bevy::platform::collections::{HashMap, HashSet};
 use bevy::prelude::*;
-use bevy_asset_loader::asset_collection::AssetCollection;
+use bevy_asset_loader::prelude::*;
 use derive_more::derive::{Display, Error, From, IntoIterator};
 
 use crate::assets::prelude::*;
 
-pub const BLOCK_MODELS_PATH: &str = "bevy_craft/models/block";
+pub const BLOCK_MODELS_DIR: &str = "models/block";
+pub const ALL_BLOCK_MODELS_KEY: &str = "all_block_models";
 
 pub struct ModelLoader;
 
@@ -32,7 +36,7 @@ impl AssetLoader for ModelLoader {
     ) -> Result<Self::Asset, Self::Error> {
         let mut bytes = Vec::<u8>::new();
         reader.read_to_end(&mut bytes).await?;
-        Ok(serde_json::from_slice::<Model>(&bytes)?)
+        parse_model(&bytes)
     }
 
     fn extensions(&self) -> &[&str] {
@@ -40,32 +44,154 @@ impl AssetLoader for ModelLoader {
     }
 }
 
+pub fn parse_model(bytes: &[u8]) -> Result<Model, ModelLoadError> {
+    Ok(serde_json::from_slice::<Model>(bytes)?)
+}
+
 #[derive(Debug, AssetCollection, Resource, IntoIterator)]
 pub struct ModelAssets {
     #[into_iterator(owned, ref, ref_mut)]
-    #[asset(path = "bevy_craft/models/block", collection(typed))]
+    #[asset(key = "all_block_models", collection(typed))]
     models: Vec<Handle<Model>>,
 }
 
-pub fn resolve_models(
+/// models which are referenced by a parent or a block definition but not discovered on disk
+#[derive(Default)]
+pub struct PendingParents {
+    loading: HashMap<ModelId, Handle<Model>>,
+    failed: HashSet<ModelId>,
+}
+
+/// the model files of a dynamic collection, loaded as `Model` whatever other loader claims `json`
+#[derive(Debug)]
+pub struct ModelFiles {
+    paths: Vec<String>,
+}
+
+impl DynamicAsset for ModelFiles {
+    fn load(&self, asset_server: &AssetServer) -> Vec<UntypedHandle> {
+        self.paths
+            .iter()
+            .map(|path| asset_server.load::<Model>(path).untyped())
+            .collect()
+    }
+
+    fn build(&self, world: &mut World) -> Result<DynamicAssetType, anyhow::Error> {
+        let asset_server = world.resource::<AssetServer>();
+        Ok(DynamicAssetType::Collection(
+            self.paths
+                .iter()
+                .filter_map(|path| asset_server.get_handle::<Model>(path))
+                .map(Handle::untyped)
+                .collect(),
+        ))
+    }
+}
+
+/// collect the asset path of each block model, such as `mymod/models/block/foo.json`
+pub fn discover_models(root: &Path) -> Vec<String> {
+    discover_assets(root, BLOCK_MODELS_DIR, ModelId::EXTENSION)
+}
+
+/// run OnEnter AppLoadState::ModelLoading
+pub fn pre_model_load(
+    root: Res<AssetRoot>,
+    mut dynamic_assets: ResMut<DynamicAssets>,
+    mut tracker: ResMut<LoadTracker>,
+) {
+    let paths = discover_models(&root.0);
+    paths.iter().for_each(|path| tracker.models.track(path));
+    dynamic_assets.register_asset(ALL_BLOCK_MODELS_KEY, Box::new(ModelFiles { paths }));
+}
+
+/// run OnEnter AppLoadState::ModelLoaded
+pub fn collect_models(
     mut commands: Commands,
-    model_handles: ResMut<ModelAssets>,
+    model_handles: Res<ModelAssets>,
+    registry: Option<Res<BlockRegistry>>,
     mut models_assets: ResMut<Assets<Model>>,
-    mut next_state: ResMut<NextState<AppLoadState>>,
 ) {
     let mut result = ModelManager::default();
+    if let Some(registry) = registry {
+        result.extend(registry.models.clone());
+    }
+
     model_handles.into_iter().for_each(|handle| {
         if let Some(model) = models_assets.remove(handle) {
             // unwrap is safe
-            let block_id = BlockId::try_from(handle).unwrap();
-            result.insert(block_id, model);
+            let model_id = ModelId::try_from(handle).unwrap();
+            result.insert(model_id, model);
         } else {
             log::error!("{:?} not loaded yet", handle);
         }
     });
 
-    result.merge();
     commands.remove_resource::<ModelAssets>();
     commands.insert_resource(result);
-    next_state.set(AppLoadState::TextureLoading);
+}
+
+/// run Update in AppLoadState::ModelLoaded,
+/// load the parents and the models of block definitions across namespaces
+/// until nothing is missing, then merge
+pub fn resolve_models(
+    asset_server: Res<AssetServer>,
+    definitions: Res<BlockDefinitions>,
+    mut models: ResMut<ModelManager>,
+    mut models_assets: ResMut<Assets<Model>>,
+    mut pending: Local<PendingParents>,
+    mut tracker: ResMut<LoadTracker>,
+    mut next_state: ResMut<NextState<AppLoadState>>,
+) {
+    let PendingParents { loading, failed } = &mut *pending;
+
+    loading.retain(
+        |model_id, handle| match asset_server.load_state(handle.id()) {
+            LoadState::Loaded => {
+                if let Some(model) = models_assets.remove(handle.id()) {
+                    models.insert(model_id.clone(), model);
+                }
+                false
+            }
+            LoadState::Failed(err) => {
+                log::error!("parent {} load failed: {}", model_id, err);
+                failed.insert(model_id.clone());
+                false
+            }
+            _ => true,
+        },
+    );
+
+    let missing = definitions
+        .models()
+        .into_iter()
+        .filter(|model_id| !models.contains_key(model_id))
+        .chain(models.missing_parents());
+    for model_id in missing {
+        if !failed.contains(&model_id) && !loading.contains_key(&model_id) {
+            let handle = asset_server.load(model_id.path());
+            tracker.models.track(model_id.path());
+            loading.insert(model_id, handle);
+        }
+    }
+
+    if loading.is_empty() {
+        models.merge();
+        next_state.set(AppLoadState::TextureLoading);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_discover_models() {
+        let paths = discover_models(Path::new(ASSET_ROOT));
+        assert!(paths.contains(&"bevy_craft/models/block/cube.json".to_string()));
+        assert!(paths.contains(&"bevy_craft/models/block/cherry_stairs.json".to_string()));
+        assert!(paths
+            .iter()
+            .all(|path| ModelId::try_from(path.as_str()).is_ok()));
+        assert!(discover_models(Path::new("not_exists")).is_empty());
+    }
 }
@@ -1,18 +1,22 @@
 use std::ops::{Deref, DerefMut};
 
 use crate::identity::prelude::*;
-use bevy::{log, platform::collections::HashMap, prelude::*};
+use bevy::{
+    log,
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
 use topo_sort::TopoSort;
 
 use crate::assets::prelude::*;
 
 #[derive(Resource, Default)]
 pub struct ModelManager {
-    models: HashMap<BlockId, Model>,
+    models: HashMap<ModelId, Model>,
 }
 
 impl Deref for ModelManager {
-    type Target = HashMap<BlockId, Model>;
+    type Target = HashMap<ModelId, Model>;
 
     fn deref(&self) -> &Self::Target {
         &self.models
@@ -30,25 +34,25 @@ impl ModelManager {
         // topo sort
         let mut topo = TopoSort::with_capacity(self.models.len());
 
-        for (block_id, model) in &self.models {
+        for (model_id, model) in &self.models {
             topo.insert(
-                block_id.clone(),
+                model_id.clone(),
                 model
                     .parent
                     .as_ref()
-                    .and_then(|id| BlockId::try_from(id.as_str()).ok()),
+                    .and_then(|id| ModelId::try_from(id.as_str()).ok()),
             );
         }
 
         match topo.into_vec_nodes() {
-            topo_sort::SortResults::Full(block_ids) => {
-                for block_id in block_ids {
+            topo_sort::SortResults::Full(model_ids) => {
+                for model_id in model_ids {
                     let parent_model = self
                         .models
-                        .get(&block_id)
+                        .get(&model_id)
                         .and_then(|model| model.parent.as_ref())
                         .and_then(|parent| {
-                            BlockId::try_from(parent.as_str())
+                            ModelId::try_from(parent.as_str())
                                 .map_err(|e| log::error!("{}", e))
                                 .ok()
                                 .and_then(|key| self.models.get(&key))
@@ -57,7 +61,7 @@ impl ModelManager {
 
                     // unwrap is safe
                     if let Some(parent_model) = parent_model {
-                        let model = unsafe { self.models.get_mut(&block_id).unwrap_unchecked() };
+                        let model = unsafe { self.models.get_mut(&model_id).unwrap_unchecked() };
                         model.merge(parent_model);
                     }
                 }
@@ -66,6 +70,22 @@ impl ModelManager {
         }
     }
 
+    /// parents which are referenced but not present in the manager
+    pub fn missing_parents(&self) -> Vec<ModelId> {
+        self.models
+            .values()
+            .filter_map(|model| model.parent.as_ref())
+            .filter_map(|parent| {
+                ModelId::try_from(parent.as_str())
+                    .map_err(|err| log::error!("{}", err))
+                    .ok()
+            })
+            .filter(|model_id| !self.models.contains_key(model_id))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
     pub fn all_texture_path(&self) -> Vec<String> {
         self.models
             .values()
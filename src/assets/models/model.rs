@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::assets::prelude::*;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Asset, TypePath)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Asset, TypePath)]
 pub struct Model {
     pub parent: Option<String>,
     pub textures: Option<Textures>,
@@ -11,6 +11,23 @@ pub struct Model {
 }
 
 impl Model {
+    pub fn with_parent(mut self, parent: impl Into<String>) -> Self {
+        self.parent = Some(parent.into());
+        self
+    }
+
+    pub fn with_texture(mut self, key: impl Into<String>, texture: impl Into<Texture>) -> Self {
+        self.textures
+            .get_or_insert_default()
+            .insert(key.into(), texture.into());
+        self
+    }
+
+    pub fn with_element(mut self, element: Element) -> Self {
+        self.elements.get_or_insert_default().push(element);
+        self
+    }
+
     pub fn merge(&mut self, other: Self) {
         self.parent = other.parent;
 
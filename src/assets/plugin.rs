@@ -1,10 +1,21 @@
-use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+
+use bevy::asset::io::file::FileAssetReader;
+use bevy::{log, prelude::*};
 use bevy_asset_loader::prelude::*;
 
 use super::prelude::*;
+use crate::world::prelude::*;
+
+pub const ASSET_ROOT: &str = "assets";
 
 pub struct AppAssetPlugin;
 
+/// the directory of the default asset source, `AssetPlugin::file_path` under the base path,
+/// where the models, block definitions and biomes are discovered
+#[derive(Resource, Debug, Clone)]
+pub struct AssetRoot(pub PathBuf);
+
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
 pub enum AppLoadState {
     #[default]
@@ -17,9 +28,22 @@ pub enum AppLoadState {
 
 impl Plugin for AppAssetPlugin {
     fn build(&self, app: &mut App) {
-        app.init_asset::<Model>()
+        // `init_asset` needs `AssetPlugin`, so it is already added
+        let file_path = app
+            .get_added_plugins::<AssetPlugin>()
+            .first()
+            .map(|plugin| plugin.file_path.clone())
+            .unwrap_or_else(|| ASSET_ROOT.to_string());
+        app.insert_resource(AssetRoot(FileAssetReader::get_base_path().join(file_path)))
+            .init_asset::<Model>()
+            .init_asset::<BlockDefinition>()
+            .init_asset::<Biome>()
             .init_state::<AppLoadState>()
+            .init_resource::<LoadTracker>()
+            .init_resource::<LoadProgress>()
             .register_asset_loader(ModelLoader)
+            .register_asset_loader(BlockDefinitionLoader)
+            .register_asset_loader(BiomeLoader)
             .add_loading_state(
                 LoadingState::new(AppLoadState::ModelLoading)
                     .continue_to_state(AppLoadState::ModelLoaded)
@@ -30,8 +54,82 @@ impl Plugin for AppAssetPlugin {
                     .continue_to_state(AppLoadState::TextureLoaded)
                     .load_collection::<BlockTextures>(),
             )
-            .add_systems(OnEnter(AppLoadState::ModelLoaded), resolve_models)
-            .add_systems(OnEnter(AppLoadState::TextureLoading), pre_texture_load)
+            .add_systems(
+                OnEnter(AppLoadState::ModelLoading),
+                (pre_model_load, pre_block_load, pre_biome_load),
+            )
+            .add_systems(OnEnter(AppLoadState::ModelLoaded), collect_models)
+            .add_systems(
+                Update,
+                (
+                    resolve_blocks.run_if(resource_exists::<BlockDefinitionHandles>),
+                    collect_biomes.run_if(resource_exists::<BiomeHandles>),
+                    // the biomes are ready before the models finish the stage
+                    resolve_models
+                        .run_if(resource_exists::<BlockDefinitions>)
+                        .run_if(resource_exists::<Biomes>),
+                )
+                    .chain()
+                    .run_if(in_state(AppLoadState::ModelLoaded)),
+            )
+            .add_systems(
+                PreUpdate,
+                track_progress.run_if(not(in_state(AppLoadState::Next))),
+            )
+            .add_systems(
+                OnEnter(AppLoadState::TextureLoading),
+                (pre_texture_load, build_shapes),
+            )
             .add_systems(OnEnter(AppLoadState::TextureLoaded), build_atlas);
     }
 }
+
+/// scan every namespace directory under `root` and collect the asset path
+/// of each file in `<namespace>/<dir>` with the given extension
+pub fn discover_assets(root: &Path, dir: &str, extension: &str) -> Vec<String> {
+    let Ok(namespaces) = std::fs::read_dir(root) else {
+        log::error!("can not read asset root {:?}", root);
+        return Vec::new();
+    };
+
+    let mut paths = Vec::new();
+    for namespace in namespaces.flatten() {
+        if !namespace.path().is_dir() {
+            continue;
+        }
+
+        let Some(ns) = namespace.file_name().to_str().map(str::to_string) else {
+            log::warn!("skip namespace {:?}, not utf-8", namespace.path());
+            continue;
+        };
+
+        walk_assets(
+            &namespace.path().join(dir),
+            &format!("{}/{}", ns, dir),
+            extension,
+            &mut paths,
+        );
+    }
+
+    paths.sort();
+    paths
+}
+
+fn walk_assets(dir: &Path, prefix: &str, extension: &str, paths: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+
+        if path.is_dir() {
+            walk_assets(&path, &format!("{}/{}", prefix, name), extension, paths);
+        } else if path.extension().is_some_and(|ext| ext == extension) {
+            paths.push(format!("{}/{}", prefix, name));
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use bevy::asset::LoadState;
+use bevy::{platform::collections::HashMap, prelude::*};
+use derive_more::derive::Display;
+
+use crate::{assets::prelude::*, world::prelude::*};
+
+/// counts of one kind of asset
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Display)]
+#[display("{}/{} ({} failed)", loaded, total, failed)]
+pub struct StageProgress {
+    pub total: usize,
+    pub loaded: usize,
+    pub failed: usize,
+}
+
+impl StageProgress {
+    pub fn pending(&self) -> usize {
+        self.total - self.loaded - self.failed
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.pending() == 0
+    }
+
+    /// finished part in 0..=1, a stage with nothing to load is finished
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            (self.loaded + self.failed) as f32 / self.total as f32
+        }
+    }
+}
+
+/// progress of the assets loaded across `AppLoadState`, can be polled by any consumer
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub models: StageProgress,
+    pub blocks: StageProgress,
+    pub biomes: StageProgress,
+    pub textures: StageProgress,
+}
+
+impl LoadProgress {
+    /// the stages load one after the other, a stage counts as 0 until it or a later stage
+    /// has found something to load, so the fraction does not drop when a stage starts
+    pub fn fraction(&self) -> f32 {
+        let stages = [self.models, self.blocks, self.biomes, self.textures];
+        let started = stages.iter().rposition(|stage| stage.total > 0);
+        let sum = stages
+            .iter()
+            .enumerate()
+            .map(|(index, stage)| match started {
+                Some(last) if index <= last => stage.fraction(),
+                _ => 0.0,
+            })
+            .sum::<f32>();
+        sum / stages.len() as f32
+    }
+
+    pub fn failed(&self) -> usize {
+        self.models.failed + self.blocks.failed + self.biomes.failed + self.textures.failed
+    }
+}
+
+/// the load state of every tracked path,
+/// a path never leaves `Loaded` or `Failed` even if its handle is dropped later
+#[derive(Debug, Default)]
+pub struct PathTracker {
+    states: HashMap<String, LoadState>,
+}
+
+impl PathTracker {
+    pub fn track(&mut self, path: impl Into<String>) {
+        self.states
+            .entry(path.into())
+            .or_insert(LoadState::NotLoaded);
+    }
+
+    pub fn update<A: Asset>(&mut self, asset_server: &AssetServer) -> StageProgress {
+        let mut progress = StageProgress {
+            total: self.states.len(),
+            ..default()
+        };
+
+        for (path, state) in self.states.iter_mut() {
+            if !matches!(state, LoadState::Loaded | LoadState::Failed(_)) {
+                if let Some(handle) = asset_server.get_handle::<A>(path.as_str()) {
+                    *state = asset_server.load_state(handle.id());
+                }
+            }
+
+            match state {
+                LoadState::Loaded => progress.loaded += 1,
+                LoadState::Failed(_) => progress.failed += 1,
+                _ => {}
+            }
+        }
+
+        progress
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct LoadTracker {
+    pub models: PathTracker,
+    pub blocks: PathTracker,
+    pub biomes: PathTracker,
+    pub textures: PathTracker,
+}
+
+/// run PreUpdate until AppLoadState::Next,
+/// before the loaded handles are dropped in OnEnter
+pub fn track_progress(
+    asset_server: Res<AssetServer>,
+    mut tracker: ResMut<LoadTracker>,
+    mut progress: ResMut<LoadProgress>,
+) {
+    let current = LoadProgress {
+        models: tracker.models.update::<Model>(&asset_server),
+        blocks: tracker.blocks.update::<BlockDefinition>(&asset_server),
+        biomes: tracker.biomes.update::<Biome>(&asset_server),
+        textures: tracker.textures.update::<Image>(&asset_server),
+    };
+
+    if *progress != current {
+        *progress = current;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stage_progress() {
+        let progress = StageProgress {
+            total: 4,
+            loaded: 2,
+            failed: 1,
+        };
+        assert_eq!(progress.pending(), 1);
+        assert!(!progress.is_finished());
+        assert_eq!(progress.fraction(), 0.75);
+
+        assert!(StageProgress::default().is_finished());
+        assert_eq!(LoadProgress::default().fraction(), 0.0);
+    }
+
+    #[test]
+    fn test_monotonic_progress() {
+        let stage = |loaded, total| StageProgress {
+            total,
+            loaded,
+            failed: 0,
+        };
+        let (none, done) = (StageProgress::default(), stage(3, 3));
+        let steps = [
+            (none, none, none, none),
+            (stage(0, 2), none, none, none),
+            (stage(1, 2), none, none, none),
+            (stage(2, 2), none, none, none),
+            (stage(2, 2), stage(0, 3), none, none),
+            (stage(2, 2), done, none, none),
+            (stage(2, 2), done, stage(1, 2), none),
+            (stage(2, 2), done, stage(2, 2), none),
+            // no textures discovered yet
+            (stage(2, 2), done, stage(2, 2), stage(0, 4)),
+            (stage(2, 2), done, stage(2, 2), stage(4, 4)),
+        ];
+        let fractions = steps
+            .map(|(models, blocks, biomes, textures)| {
+                LoadProgress {
+                    models,
+                    blocks,
+                    biomes,
+                    textures,
+                }
+                .fraction()
+            })
+            .to_vec();
+        assert!(fractions.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(fractions[0], 0.0);
+        assert_eq!(fractions[fractions.len() - 1], 1.0);
+
+        // an empty stage before a started one is finished
+        let progress = LoadProgress {
+            models: done,
+            blocks: none,
+            biomes: none,
+            textures: stage(1, 2),
+        };
+        assert_eq!(progress.fraction(), 3.5 / 4.0);
+    }
+}
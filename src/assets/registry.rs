@@ -0,0 +1,100 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::assets::prelude::*;
+use crate::identity::prelude::*;
+
+/// blocks and models registered from code,
+/// they are merged with the models on disk before `ModelManager::merge`,
+/// and the files on disk take precedence with the same id
+#[derive(Resource, Default)]
+pub struct BlockRegistry {
+    pub models: HashMap<ModelId, Model>,
+    pub definitions: HashMap<BlockId, BlockDefinition>,
+}
+
+pub trait AppBlockExt {
+    /// register a model, such as a parent template shared by other models
+    fn register_model(&mut self, model_id: ModelId, model: Model) -> &mut Self;
+
+    /// register a block with the default definition and its model
+    fn register_block(&mut self, block_id: BlockId, model: Model) -> &mut Self {
+        self.register_block_with(block_id, BlockDefinition::default(), model)
+    }
+
+    /// register a block with the definition and its model
+    fn register_block_with(
+        &mut self,
+        block_id: BlockId,
+        definition: BlockDefinition,
+        model: Model,
+    ) -> &mut Self;
+}
+
+impl AppBlockExt for App {
+    fn register_model(&mut self, model_id: ModelId, model: Model) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_init::<BlockRegistry>()
+            .models
+            .insert(model_id, model);
+        self
+    }
+
+    fn register_block_with(
+        &mut self,
+        block_id: BlockId,
+        definition: BlockDefinition,
+        model: Model,
+    ) -> &mut Self {
+        let model_id = definition
+            .model_id(&block_id)
+            .unwrap_or_else(|| block_id.clone().into());
+        let mut registry = self.world_mut().get_resource_or_init::<BlockRegistry>();
+        registry.definitions.insert(block_id, definition);
+        registry.models.insert(model_id, model);
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_register_block() {
+        let mut app = App::new();
+        app.register_model(
+            ModelId::try_from("mymod:block/template").unwrap(),
+            Model::default()
+                .with_element(Element::new([0, 0, 0], [16, 8, 16]).with_all_faces("#all")),
+        )
+        .register_block(
+            BlockId::from_static("mymod:block/slab"),
+            Model::default()
+                .with_parent("mymod:block/template")
+                .with_texture("all", "mymod:block/slab"),
+        );
+
+        let registry = app.world().resource::<BlockRegistry>();
+        assert_eq!(registry.models.len(), 2);
+        assert!(registry
+            .definitions
+            .contains_key(&BlockId::from_static("mymod:block/slab")));
+
+        let mut models = ModelManager::default();
+        models.extend(registry.models.clone());
+        models.merge();
+
+        assert_eq!(models.all_texture_path(), ["mymod/textures/block/slab.png"]);
+
+        let slab = models
+            .get(&ModelId::try_from("mymod:block/slab").unwrap())
+            .unwrap();
+        let element = &slab.elements.as_ref().unwrap()[0];
+        assert_eq!(element.faces.len(), 6);
+        assert_eq!(
+            element.faces[&BlockFace::Down].cullface,
+            Some(BlockFace::Down)
+        );
+        assert_eq!(element.faces[&BlockFace::Up].cullface, None);
+    }
+}
@@ -0,0 +1,380 @@
+use std::ops::{Deref, DerefMut};
+
+use bevy::{
+    math::{bounding::Aabb3d, I8Vec2},
+    platform::collections::HashMap,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{assets::prelude::*, identity::prelude::*};
+
+/// an axis aligned box in 1/16 of a block, like the `from`/`to` of an `Element`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ShapeBox {
+    pub from: [i8; 3],
+    pub to: [i8; 3],
+}
+
+impl ShapeBox {
+    pub const FULL: ShapeBox = ShapeBox {
+        from: [0; 3],
+        to: [DEFAULT_ELEMENT_SIZE_I8; 3],
+    };
+
+    pub fn new(from: [i8; 3], to: [i8; 3]) -> Self {
+        Self {
+            from: [0, 1, 2].map(|i| from[i].min(to[i])),
+            to: [0, 1, 2].map(|i| from[i].max(to[i])),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..3).any(|i| self.from[i] >= self.to[i])
+    }
+
+    pub fn contains(&self, other: &ShapeBox) -> bool {
+        (0..3).all(|i| self.from[i] <= other.from[i] && other.to[i] <= self.to[i])
+    }
+
+    pub fn intersection(&self, other: &ShapeBox) -> Option<ShapeBox> {
+        let result = ShapeBox {
+            from: [0, 1, 2].map(|i| self.from[i].max(other.from[i])),
+            to: [0, 1, 2].map(|i| self.to[i].min(other.to[i])),
+        };
+        (!result.is_empty()).then_some(result)
+    }
+
+    /// the box in world space for the block at `pos`
+    pub fn aabb(&self, pos: IVec3) -> Aabb3d {
+        let offset = pos.as_vec3();
+        Aabb3d {
+            min: (self.min() + offset).into(),
+            max: (self.max() + offset).into(),
+        }
+    }
+}
+
+impl FaceAble for ShapeBox {
+    fn from(&self) -> [i8; 3] {
+        self.from
+    }
+
+    fn to(&self) -> [i8; 3] {
+        self.to
+    }
+}
+
+/// which of the 16x16 pixels of a block face are covered, one row per bit set
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FaceCoverage([u16; 16]);
+
+impl FaceCoverage {
+    pub const EMPTY: FaceCoverage = FaceCoverage([0; 16]);
+    pub const FULL: FaceCoverage = FaceCoverage([u16::MAX; 16]);
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::EMPTY
+    }
+
+    pub fn is_full(&self) -> bool {
+        *self == Self::FULL
+    }
+
+    pub fn union(mut self, other: FaceCoverage) -> FaceCoverage {
+        for (row, other) in self.0.iter_mut().zip(other.0) {
+            *row |= other;
+        }
+        self
+    }
+
+    /// whether every pixel covered by `other` is covered by `self`
+    pub fn contains(&self, other: &FaceCoverage) -> bool {
+        self.0
+            .iter()
+            .zip(other.0)
+            .all(|(row, other)| row & other == other)
+    }
+}
+
+impl From<I8Rect> for FaceCoverage {
+    fn from(rect: I8Rect) -> Self {
+        let min = rect
+            .min
+            .clamp(I8Vec2::ZERO, I8Vec2::splat(DEFAULT_ELEMENT_SIZE_I8));
+        let max = rect
+            .max
+            .clamp(I8Vec2::ZERO, I8Vec2::splat(DEFAULT_ELEMENT_SIZE_I8));
+
+        let mut result = Self::EMPTY;
+        let row = (min.x..max.x).fold(0u16, |row, x| row | (1 << x));
+        for y in min.y..max.y {
+            result.0[y as usize] = row;
+        }
+        result
+    }
+}
+
+/// shape of a block, a set of boxes in 1/16 of a block,
+/// used for collision, raycasting, the selection outline and face culling
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VoxelShape {
+    boxes: Vec<ShapeBox>,
+}
+
+impl VoxelShape {
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    pub fn full() -> Self {
+        Self {
+            boxes: vec![ShapeBox::FULL],
+        }
+    }
+
+    pub fn new(boxes: impl IntoIterator<Item = ShapeBox>) -> Self {
+        Self {
+            boxes: boxes.into_iter().filter(|b| !b.is_empty()).collect(),
+        }
+    }
+
+    pub fn from_elements(elements: &[Element]) -> Self {
+        Self::new(
+            elements
+                .iter()
+                .map(|element| ShapeBox::new(element.from, element.to)),
+        )
+    }
+
+    pub fn boxes(&self) -> &[ShapeBox] {
+        &self.boxes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boxes.is_empty()
+    }
+
+    /// the boxes in world space for the block at `pos`
+    pub fn aabbs(&self, pos: IVec3) -> Vec<Aabb3d> {
+        self.boxes.iter().map(|b| b.aabb(pos)).collect()
+    }
+
+    /// every box of both shapes, without the boxes inside another box
+    pub fn union(&self, other: &VoxelShape) -> VoxelShape {
+        let mut boxes = Vec::<ShapeBox>::new();
+        for b in self.boxes.iter().chain(&other.boxes) {
+            if boxes.iter().any(|kept| kept.contains(b)) {
+                continue;
+            }
+            boxes.retain(|kept| !b.contains(kept));
+            boxes.push(*b);
+        }
+        VoxelShape { boxes }
+    }
+
+    pub fn intersection(&self, other: &VoxelShape) -> VoxelShape {
+        let boxes = self
+            .boxes
+            .iter()
+            .flat_map(|a| other.boxes.iter().filter_map(|b| a.intersection(b)))
+            .collect::<Vec<_>>();
+        VoxelShape::empty().union(&VoxelShape { boxes })
+    }
+
+    pub fn intersects(&self, other: &VoxelShape) -> bool {
+        self.boxes
+            .iter()
+            .any(|a| other.boxes.iter().any(|b| a.intersection(b).is_some()))
+    }
+
+    /// the part of the block boundary on `face` covered by the shape
+    pub fn face(&self, face: BlockFace) -> FaceCoverage {
+        self.boxes
+            .iter()
+            .filter(|b| b.is_normal_face(face))
+            .fold(FaceCoverage::EMPTY, |coverage, b| {
+                coverage.union(b.rect(face).into())
+            })
+    }
+
+    /// whether `rect` on the boundary `face` of this block is hidden by this shape
+    pub fn occludes(&self, face: BlockFace, rect: I8Rect) -> bool {
+        self.face(face).contains(&rect.into())
+    }
+
+    /// whether the `face` of this shape is hidden by `neighbor`, the block next to that face
+    pub fn is_occluded_by(&self, face: BlockFace, neighbor: &VoxelShape) -> bool {
+        neighbor.face(face.opposite()).contains(&self.face(face))
+    }
+}
+
+impl Model {
+    pub fn shape(&self) -> VoxelShape {
+        self.elements
+            .as_deref()
+            .map(VoxelShape::from_elements)
+            .unwrap_or_default()
+    }
+}
+
+/// the shape of every block, from the `shape` of the definition or the elements of the model
+#[derive(Resource, Default)]
+pub struct VoxelShapes {
+    shapes: HashMap<BlockId, VoxelShape>,
+}
+
+impl Deref for VoxelShapes {
+    type Target = HashMap<BlockId, VoxelShape>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.shapes
+    }
+}
+
+impl DerefMut for VoxelShapes {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.shapes
+    }
+}
+
+impl VoxelShapes {
+    pub fn new(models: &ModelManager, definitions: Option<&BlockDefinitions>) -> Self {
+        let mut shapes = models
+            .iter()
+            .filter_map(|(model_id, model)| {
+                BlockId::try_from(model_id)
+                    .ok()
+                    .map(|block_id| (block_id, model.shape()))
+            })
+            .collect::<HashMap<_, _>>();
+
+        for (block_id, definition) in definitions.into_iter().flat_map(|d| d.iter()) {
+            let shape = definition.shape.clone().or_else(|| {
+                definition
+                    .model_id(block_id)
+                    .and_then(|model_id| models.get(&model_id))
+                    .map(Model::shape)
+            });
+            if let Some(shape) = shape {
+                shapes.insert(block_id.clone(), shape);
+            }
+        }
+
+        Self { shapes }
+    }
+}
+
+/// run OnEnter AppLoadState::TextureLoading, the models are merged
+pub fn build_shapes(
+    mut commands: Commands,
+    models: Res<ModelManager>,
+    definitions: Option<Res<BlockDefinitions>>,
+) {
+    commands.insert_resource(VoxelShapes::new(&models, definitions.as_deref()));
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn slab() -> VoxelShape {
+        VoxelShape::new([ShapeBox::new([0, 0, 0], [16, 8, 16])])
+    }
+
+    fn stairs() -> VoxelShape {
+        VoxelShape::new([
+            ShapeBox::new([0, 0, 0], [16, 8, 16]),
+            ShapeBox::new([8, 8, 0], [16, 16, 16]),
+        ])
+    }
+
+    #[test]
+    fn test_face_coverage() {
+        let full = VoxelShape::full();
+        assert!(BlockFace::ALL.iter().all(|face| full.face(*face).is_full()));
+
+        let slab = slab();
+        assert!(slab.face(BlockFace::Down).is_full());
+        assert!(slab.face(BlockFace::Up).is_empty());
+        assert!(!slab.face(BlockFace::North).is_full());
+        assert!(slab.occludes(BlockFace::North, I8Rect::new(0, 0, 16, 8)));
+        assert!(!slab.occludes(BlockFace::North, I8Rect::new(0, 0, 16, 9)));
+
+        let stairs = stairs();
+        assert!(stairs.face(BlockFace::East).is_full());
+        assert!(!stairs.face(BlockFace::West).is_full());
+        assert!(stairs.occludes(BlockFace::Up, I8Rect::new(8, 0, 16, 16)));
+
+        // a slab hides the top of the block below it, but not the bottom of the block above it
+        assert!(full.is_occluded_by(BlockFace::Up, &slab));
+        assert!(!full.is_occluded_by(BlockFace::Down, &slab));
+        assert!(slab.is_occluded_by(BlockFace::Down, &full));
+        assert!(stairs.is_occluded_by(BlockFace::West, &full));
+        assert!(!full.is_occluded_by(BlockFace::East, &stairs));
+        assert!(full.is_occluded_by(BlockFace::West, &stairs));
+    }
+
+    #[test]
+    fn test_union_intersection() {
+        let slab = slab();
+        let stairs = stairs();
+
+        assert_eq!(slab.union(&stairs), stairs);
+        assert_eq!(stairs.union(&VoxelShape::full()), VoxelShape::full());
+        assert_eq!(stairs.intersection(&slab), slab);
+        assert_eq!(
+            stairs.intersection(&VoxelShape::new([ShapeBox::new([0, 8, 0], [16, 16, 16])])),
+            VoxelShape::new([ShapeBox::new([8, 8, 0], [16, 16, 16])])
+        );
+
+        let top = VoxelShape::new([ShapeBox::new([0, 8, 0], [16, 16, 16])]);
+        assert!(!slab.intersects(&top));
+        assert!(stairs.intersects(&top));
+        assert!(slab.intersection(&top).is_empty());
+    }
+
+    #[test]
+    fn test_voxel_shapes() {
+        let slab_id = BlockId::from_static("bevy_craft:block/slab");
+        let lamp_id = BlockId::from_static("bevy_craft:block/lamp");
+        let torch_id = BlockId::from_static("bevy_craft:block/torch");
+
+        let mut models = ModelManager::default();
+        models.insert(
+            slab_id.clone().into(),
+            Model::default().with_element(Element::new([0, 0, 0], [16, 8, 16])),
+        );
+
+        let mut definitions = BlockDefinitions::default();
+        definitions.insert(
+            lamp_id.clone(),
+            serde_json::from_value(json!({ "model": "bevy_craft:block/slab" })).unwrap(),
+        );
+        definitions.insert(
+            torch_id.clone(),
+            serde_json::from_value(json!({
+                "shape": [{ "from": [7, 0, 7], "to": [9, 10, 9] }],
+            }))
+            .unwrap(),
+        );
+
+        let shapes = VoxelShapes::new(&models, Some(&definitions));
+        assert_eq!(shapes[&slab_id], slab());
+        assert_eq!(shapes[&lamp_id], slab());
+        assert_eq!(
+            shapes[&torch_id],
+            VoxelShape::new([ShapeBox::new([7, 0, 7], [9, 10, 9])])
+        );
+        assert_eq!(
+            shapes[&torch_id].aabbs(IVec3::new(1, 0, 0)),
+            vec![Aabb3d {
+                min: Vec3A::new(1.4375, 0.0, 0.4375),
+                max: Vec3A::new(1.5625, 0.625, 0.5625),
+            }]
+        );
+    }
+}
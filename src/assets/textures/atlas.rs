@@ -1,9 +1,11 @@
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
 use std::ops::Index;
 
 use bevy::image::TextureAtlasBuilderError;
 use bevy::platform::collections::HashMap;
 use bevy::{image::ImageSampler, prelude::*};
+use serde::Serialize;
 
 use crate::assets::prelude::*;
 use crate::identity::prelude::*;
@@ -40,6 +42,20 @@ impl<T: Identity> AppTextureAtlas<T> {
             .map(|idx| *self.layout.textures.index(*idx))
     }
 
+    /// where each texture landed, keyed by `TextureId`
+    pub fn entries(&self) -> BTreeMap<String, AtlasEntry> {
+        self.texture_map
+            .keys()
+            .filter_map(|texture_id| {
+                let entry = AtlasEntry {
+                    rect: self._uv(texture_id.clone())?,
+                    uv: self.uv(texture_id.clone())?,
+                };
+                Some((texture_id.to_string(), entry))
+            })
+            .collect()
+    }
+
     pub fn size(&self) -> UVec2 {
         self.layout.size
     }
@@ -49,6 +65,53 @@ impl<T: Identity> AppTextureAtlas<T> {
     }
 }
 
+impl AppTextureAtlas<TextureId> {
+    /// pack the textures, the atlas grows until every texture fits
+    pub fn build(
+        textures: &mut Assets<Image>,
+        entries: impl IntoIterator<Item = (TextureId, AssetId<Image>)>,
+    ) -> Result<Self, TextureAtlasBuilderError> {
+        let mut builder = TextureAtlasBuilder::default();
+        builder.padding(UVec2::splat(2));
+
+        let mut texture_map = HashMap::<TextureId, AssetId<Image>>::default();
+        for (texture_id, id) in entries {
+            let Some(texture) = textures.get(id) else {
+                continue;
+            };
+            builder.add_texture(Some(id), texture);
+            texture_map.insert(texture_id, id);
+        }
+
+        let mut max_size = DEFAULT_ATLAS_MAX_SIZE;
+        loop {
+            builder.max_size(max_size);
+            match builder.build() {
+                Ok((layout, source, mut atlas_image)) => {
+                    atlas_image.sampler = ImageSampler::nearest();
+                    return Ok(AppTextureAtlas {
+                        atlas: textures.add(atlas_image),
+                        layout,
+                        source,
+                        texture_map,
+                        _marker: PhantomData,
+                    });
+                }
+                Err(TextureAtlasBuilderError::NotEnoughSpace) => max_size *= 2,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AtlasEntry {
+    /// pixel rect in the atlas
+    pub rect: URect,
+    /// normalized uv rect in the atlas
+    pub uv: Rect,
+}
+
 /// run OnEnter AppLoadeStaet::TextureLoaded
 pub fn build_atlas(
     mut commands: Commands,
@@ -56,56 +119,26 @@ pub fn build_atlas(
     block_texturues: Res<BlockTextures>,
     mut app_state: ResMut<NextState<AppLoadState>>,
 ) {
-    let mut builder = TextureAtlasBuilder::default();
-    builder.padding(UVec2::splat(2));
-
-    let mut texture_map: HashMap<TextureId, AssetId<Image>> =
-        HashMap::with_capacity(block_texturues.len());
-
+    let mut entries = Vec::with_capacity(block_texturues.len());
     for handler in block_texturues.iter() {
-        let Some(texture) = textures.get(handler) else {
+        if !textures.contains(handler) {
             bevy::log::warn!("{:?} did not loaded yet", handler.path());
             continue;
-        };
+        }
 
         if let Ok(texture_id) = TextureId::try_from(handler) {
-            let id = handler.id();
-            builder.add_texture(Some(id), texture);
-            texture_map.insert(texture_id, id);
+            entries.push((texture_id, handler.id()));
         } else {
             bevy::log::warn!("{:?} can not be convert to TextureId, amz", handler.path());
         }
     }
 
-    let mut max_size = DEFAULT_ATLAS_MAX_SIZE;
-    loop {
-        builder.max_size(max_size);
-        match builder.build() {
-            Ok((layout, source, mut atlas_image)) => {
-                atlas_image.sampler = ImageSampler::nearest();
-
-                commands.insert_resource(AppTextureAtlas {
-                    atlas: textures.add(atlas_image),
-                    layout,
-                    source,
-                    texture_map,
-                    _marker: PhantomData::<TextureId>,
-                });
-
-                app_state.set(AppLoadState::Next);
-                break;
-            }
-
-            Err(err) => match err {
-                TextureAtlasBuilderError::NotEnoughSpace => {
-                    max_size *= 2;
-                    continue;
-                }
-                _ => {
-                    // NOTE: maybe only log and break?
-                    panic!("{}", err);
-                }
-            },
-        };
+    match AppTextureAtlas::build(&mut textures, entries) {
+        Ok(atlas) => {
+            commands.insert_resource(atlas);
+            app_state.set(AppLoadState::Next);
+        }
+        // NOTE: maybe only log and break?
+        Err(err) => panic!("{}", err),
     }
 }
@@ -25,8 +25,15 @@ impl DerefMut for BlockTextures {
     }
 }
 
-pub fn pre_texture_load(models: Res<ModelManager>, mut dynamic_assets: ResMut<DynamicAssets>) {
+pub fn pre_texture_load(
+    models: Res<ModelManager>,
+    mut dynamic_assets: ResMut<DynamicAssets>,
+    mut tracker: ResMut<LoadTracker>,
+) {
     let all_block_texture = models.all_texture_path();
+    all_block_texture
+        .iter()
+        .for_each(|path| tracker.textures.track(path));
     dynamic_assets.register_asset(
         "all_block_textures",
         Box::new(StandardDynamicAsset::Files {
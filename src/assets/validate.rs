@@ -0,0 +1,426 @@
+use std::path::{Path, PathBuf};
+
+use bevy::platform::collections::{HashMap, HashSet};
+use derive_more::derive::Display;
+use serde_json::Value;
+
+use crate::assets::prelude::*;
+use crate::identity::prelude::*;
+
+/// a problem found in a resource pack
+#[derive(Debug, Display)]
+pub enum Problem {
+    #[display("{}: {}", _0, _1)]
+    Malformed(String, ModelLoadError),
+    #[display("{}: {}", _0, _1)]
+    MalformedDefinition(String, BlockDefinitionLoadError),
+    #[display("{}: element {} has unknown face `{}`", model, element, face)]
+    UnknownFace {
+        model: ModelId,
+        element: usize,
+        face: String,
+    },
+    #[display("parent cycle {}", _0.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(" -> "))]
+    ParentCycle(Vec<ModelId>),
+    #[display("{}: parent `{}` not found", model, parent)]
+    MissingParent { model: ModelId, parent: String },
+    #[display("{}: model `{}` of the block definition not found", block, model)]
+    MissingModel { block: BlockId, model: ModelId },
+    #[display("{}: element {} {:?} is out of 0..=16", model, element, position)]
+    OutOfRange {
+        model: ModelId,
+        element: usize,
+        position: [i8; 3],
+    },
+    #[display("{}: element {} from {:?} > to {:?}", model, element, from, to)]
+    Inverted {
+        model: ModelId,
+        element: usize,
+        from: [i8; 3],
+        to: [i8; 3],
+    },
+    #[display("{}: {:?} face texture `{}` is not resolved", model, face, texture)]
+    UnresolvedTexture {
+        model: ModelId,
+        face: BlockFace,
+        texture: String,
+    },
+    #[display("{}: texture {} not found at {}", model, texture, path.display())]
+    MissingTexture {
+        model: ModelId,
+        texture: TextureId,
+        path: PathBuf,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    pub models: usize,
+    pub definitions: usize,
+    pub problems: Vec<Problem>,
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// check every model and block definition under the asset root without loading a window,
+/// the biomes are checked by `validate_biomes`
+pub fn validate_pack(root: &Path) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let models = read_models(root, &mut report);
+    let definitions = read_definitions(root, &mut report);
+
+    check_elements(&models, &mut report);
+    let cycles = check_parents(&models, &mut report);
+
+    for (block, definition) in &definitions {
+        if let Some(model) = definition.model_id(block) {
+            if !models.contains_key(&model) {
+                report.problems.push(Problem::MissingModel {
+                    block: block.clone(),
+                    model,
+                });
+            }
+        }
+    }
+
+    // a model which is no one's parent is rendered, so its textures must be resolved
+    let parents = models
+        .values()
+        .filter_map(|model| model.parent.as_deref())
+        .filter_map(|parent| ModelId::try_from(parent).ok())
+        .collect::<HashSet<_>>();
+    let mut leaves = models
+        .keys()
+        .filter(|model_id| !parents.contains(*model_id) && !cycles.contains(*model_id))
+        .collect::<Vec<_>>();
+    leaves.sort_by_key(|model_id| model_id.to_string());
+
+    for model_id in leaves {
+        check_textures(root, model_id, &flatten(model_id, &models), &mut report);
+    }
+
+    report
+}
+
+/// every model directory is read, a block model may have a parent in `models/item`
+fn read_models(root: &Path, report: &mut ValidationReport) -> HashMap<ModelId, Model> {
+    let mut models = HashMap::new();
+    for path in discover_assets(root, ModelId::DIR, ModelId::EXTENSION) {
+        let Ok(model_id) = ModelId::try_from(path.as_str()) else {
+            continue;
+        };
+        report.models += 1;
+
+        let bytes = match std::fs::read(root.join(&path)) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                report.problems.push(Problem::Malformed(path, err.into()));
+                continue;
+            }
+        };
+
+        let unknown_faces = unknown_faces(&bytes);
+        if !unknown_faces.is_empty() {
+            report
+                .problems
+                .extend(
+                    unknown_faces
+                        .into_iter()
+                        .map(|(element, face)| Problem::UnknownFace {
+                            model: model_id.clone(),
+                            element,
+                            face,
+                        }),
+                );
+            continue;
+        }
+
+        match parse_model(&bytes) {
+            Ok(model) => {
+                models.insert(model_id, model);
+            }
+            Err(err) => report.problems.push(Problem::Malformed(path, err)),
+        }
+    }
+    models
+}
+
+fn read_definitions(root: &Path, report: &mut ValidationReport) -> Vec<(BlockId, BlockDefinition)> {
+    let mut definitions = Vec::new();
+    for path in discover_assets(root, BLOCK_DEFINITIONS_DIR, BlockId::EXTENSION) {
+        let Some(block_id) = definition_block_id(&path) else {
+            continue;
+        };
+        report.definitions += 1;
+
+        let result = std::fs::read(root.join(&path))
+            .map_err(BlockDefinitionLoadError::from)
+            .and_then(|bytes| Ok(serde_json::from_slice::<BlockDefinition>(&bytes)?));
+        match result {
+            Ok(definition) => definitions.push((block_id, definition)),
+            Err(err) => report
+                .problems
+                .push(Problem::MalformedDefinition(path, err)),
+        }
+    }
+    definitions
+}
+
+/// face names which are not a `BlockFace`, keyed by the index of the element
+fn unknown_faces(bytes: &[u8]) -> Vec<(usize, String)> {
+    let Ok(value) = serde_json::from_slice::<Value>(bytes) else {
+        return Vec::new();
+    };
+
+    let Some(elements) = value.get("elements").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+
+    elements
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, element)| Some((idx, element.get("faces")?.as_object()?)))
+        .flat_map(|(idx, faces)| {
+            faces
+                .keys()
+                .filter(|face| {
+                    serde_json::from_value::<BlockFace>(Value::from(face.as_str())).is_err()
+                })
+                .map(move |face| (idx, face.clone()))
+        })
+        .collect()
+}
+
+fn check_elements(models: &HashMap<ModelId, Model>, report: &mut ValidationReport) {
+    for (model_id, model) in models {
+        for (idx, element) in model.elements.iter().flatten().enumerate() {
+            for position in [element.from, element.to] {
+                if position
+                    .iter()
+                    .any(|value| !(0..=DEFAULT_ELEMENT_SIZE_I8).contains(value))
+                {
+                    report.problems.push(Problem::OutOfRange {
+                        model: model_id.clone(),
+                        element: idx,
+                        position,
+                    });
+                }
+            }
+
+            if element
+                .from
+                .iter()
+                .zip(element.to)
+                .any(|(from, to)| *from > to)
+            {
+                report.problems.push(Problem::Inverted {
+                    model: model_id.clone(),
+                    element: idx,
+                    from: element.from,
+                    to: element.to,
+                });
+            }
+        }
+    }
+}
+
+/// report missing parents and cycles, return the models inside a cycle
+fn check_parents(
+    models: &HashMap<ModelId, Model>,
+    report: &mut ValidationReport,
+) -> HashSet<ModelId> {
+    let mut in_cycle = HashSet::new();
+    let mut model_ids = models.keys().collect::<Vec<_>>();
+    model_ids.sort_by_key(|model_id| model_id.to_string());
+
+    for model_id in model_ids {
+        if let Some(parent) = models[model_id].parent.as_ref() {
+            let found = ModelId::try_from(parent.as_str())
+                .is_ok_and(|parent_id| models.contains_key(&parent_id));
+            if !found {
+                report.problems.push(Problem::MissingParent {
+                    model: model_id.clone(),
+                    parent: parent.clone(),
+                });
+            }
+        }
+
+        if in_cycle.contains(model_id) {
+            continue;
+        }
+
+        let mut chain = vec![model_id.clone()];
+        while let Some(parent) = chain
+            .last()
+            .and_then(|current| models.get(current))
+            .and_then(|model| model.parent.as_ref())
+            .and_then(|parent| ModelId::try_from(parent.as_str()).ok())
+            .filter(|parent| models.contains_key(parent))
+        {
+            if let Some(start) = chain.iter().position(|id| *id == parent) {
+                let mut cycle = chain.split_off(start);
+                if cycle[0] == *model_id {
+                    in_cycle.extend(cycle.iter().cloned());
+                    cycle.push(parent);
+                    report.problems.push(Problem::ParentCycle(cycle));
+                }
+                break;
+            }
+            chain.push(parent);
+        }
+    }
+
+    in_cycle
+}
+
+/// merge the parents into the model like `ModelManager::merge`
+fn flatten(model_id: &ModelId, models: &HashMap<ModelId, Model>) -> Model {
+    let mut model = models[model_id].clone();
+    let mut visited = HashSet::from([model_id.clone()]);
+    while let Some(parent_id) = model
+        .parent
+        .as_ref()
+        .and_then(|parent| ModelId::try_from(parent.as_str()).ok())
+    {
+        let Some(parent) = models.get(&parent_id) else {
+            break;
+        };
+        if !visited.insert(parent_id) {
+            break;
+        }
+        model.merge(parent.clone());
+    }
+    model
+}
+
+fn check_textures(root: &Path, model_id: &ModelId, model: &Model, report: &mut ValidationReport) {
+    let mut checked = HashSet::new();
+    for element in model.elements.iter().flatten() {
+        let mut faces = element.faces.iter().collect::<Vec<_>>();
+        faces.sort_by_key(|(face, _)| **face as u8);
+
+        for (face, face_data) in faces {
+            let texture = match face_data.texture.reference() {
+                Some(key) => model
+                    .textures
+                    .as_ref()
+                    .and_then(|textures| textures.texture_path(key)),
+                None => Some(&face_data.texture),
+            };
+
+            let Some(texture_id) = texture.and_then(|texture| TextureId::try_from(texture).ok())
+            else {
+                report.problems.push(Problem::UnresolvedTexture {
+                    model: model_id.clone(),
+                    face: *face,
+                    texture: face_data.texture.0.clone(),
+                });
+                continue;
+            };
+
+            let path = root.join(texture_id.path());
+            if checked.insert(texture_id.clone()) && !path.is_file() {
+                report.problems.push(Problem::MissingTexture {
+                    model: model_id.clone(),
+                    texture: texture_id,
+                    path,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_validate_assets() {
+        let report = validate_pack(Path::new(ASSET_ROOT));
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert!(report.models > 0);
+    }
+
+    #[test]
+    fn test_validate_problems() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        write(root, "pack/models/block/broken.json", "{ \"parent\": ");
+        write(
+            root,
+            "pack/models/block/a.json",
+            r#"{ "parent": "pack:block/b" }"#,
+        );
+        write(
+            root,
+            "pack/models/block/b.json",
+            r#"{ "parent": "pack:block/a" }"#,
+        );
+        write(
+            root,
+            "pack/models/block/orphan.json",
+            r#"{ "parent": "pack:block/none" }"#,
+        );
+        write(
+            root,
+            "pack/models/block/bad.json",
+            r##"{
+                "textures": { "all": "pack:block/missing" },
+                "elements": [
+                    { "from": [0, 0, 0], "to": [16, 17, 16], "faces": { "up": { "texture": "#all" } } },
+                    { "from": [8, 0, 0], "to": [4, 16, 16], "faces": { "down": { "texture": "#none" } } }
+                ]
+            }"##,
+        );
+        write(
+            root,
+            "pack/models/block/face.json",
+            r##"{ "elements": [{ "from": [0, 0, 0], "to": [16, 16, 16], "faces": { "top": { "texture": "#all" } } }] }"##,
+        );
+        // the parent is in another model directory
+        write(
+            root,
+            "pack/models/item/handheld.json",
+            r#"{ "textures": { "all": "pack:block/handheld" } }"#,
+        );
+        write(
+            root,
+            "pack/models/block/torch.json",
+            r#"{ "parent": "pack:item/handheld" }"#,
+        );
+        write(root, "pack/textures/block/handheld.png", "");
+        write(root, "pack/blocks/ghost.json", "{}");
+
+        let report = validate_pack(root);
+        let has = |f: fn(&Problem) -> bool| report.problems.iter().filter(|p| f(p)).count();
+
+        assert_eq!(report.models, 8);
+        assert_eq!(has(|p| matches!(p, Problem::Malformed(..))), 1);
+        assert_eq!(
+            has(|p| matches!(p, Problem::ParentCycle(cycle) if cycle.len() == 3)),
+            1
+        );
+        assert_eq!(has(|p| matches!(p, Problem::MissingParent { .. })), 1);
+        assert_eq!(has(|p| matches!(p, Problem::OutOfRange { .. })), 1);
+        assert_eq!(has(|p| matches!(p, Problem::Inverted { .. })), 1);
+        assert_eq!(has(|p| matches!(p, Problem::UnresolvedTexture { .. })), 1);
+        assert_eq!(has(|p| matches!(p, Problem::MissingTexture { .. })), 1);
+        assert_eq!(
+            has(|p| matches!(p, Problem::UnknownFace { face, .. } if face == "top")),
+            1
+        );
+        assert_eq!(has(|p| matches!(p, Problem::MissingModel { .. })), 1);
+        assert_eq!(report.problems.len(), 9);
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+use bevy_craft::{assets::prelude::*, world::prelude::*};
+
+/// validate a resource pack, exit with 1 if any problem is found
+///
+/// usage: bevy_craft-validate [asset root, default `assets`]
+fn main() {
+    let root = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(ASSET_ROOT));
+
+    if !root.is_dir() {
+        eprintln!("error: asset root {} is not a directory", root.display());
+        std::process::exit(2);
+    }
+
+    let report = validate_pack(&root);
+    for problem in &report.problems {
+        eprintln!("error: {}", problem);
+    }
+    let (biomes, biome_errors) = validate_biomes(&root);
+    for err in &biome_errors {
+        eprintln!("error: {}", err);
+    }
+
+    println!(
+        "checked {} models, {} block definitions and {} biomes in {}, {} problems",
+        report.models,
+        report.definitions,
+        biomes,
+        root.display(),
+        report.problems.len() + biome_errors.len()
+    );
+
+    if !report.is_ok() || !biome_errors.is_empty() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,392 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{
+    input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll},
+    prelude::*,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+
+use crate::chunks::prelude::*;
+
+#[derive(Resource, Debug, Clone)]
+pub struct DebugCameraSettings {
+    pub toggle_key: KeyCode,
+    /// switch between free-fly and orbit
+    pub mode_key: KeyCode,
+    /// orbit around the chunk in front of the camera
+    pub focus_key: KeyCode,
+    /// radians per pixel of mouse motion
+    pub sensitivity: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// speed factor of one scroll step
+    pub scroll_factor: f32,
+    /// how far the focused chunk is searched
+    pub focus_distance: f32,
+}
+
+impl Default for DebugCameraSettings {
+    fn default() -> Self {
+        Self {
+            toggle_key: KeyCode::F5,
+            mode_key: KeyCode::Tab,
+            focus_key: KeyCode::KeyC,
+            sensitivity: 0.003,
+            min_speed: 1.0,
+            max_speed: 200.0,
+            scroll_factor: 1.2,
+            focus_distance: 256.0,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DebugCameraMode {
+    /// WASD moves along the view, Space and Shift move up and down, scroll changes the speed
+    #[default]
+    FreeFly,
+    /// rotate around `focus`, WASD moves the focus, scroll changes the distance
+    Orbit,
+}
+
+#[derive(Component, Debug, Clone, PartialEq)]
+#[require(Camera3d)]
+pub struct DebugCamera {
+    pub mode: DebugCameraMode,
+    /// blocks per second in free-fly
+    pub speed: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub focus: Vec3,
+    /// distance to `focus` in orbit
+    pub distance: f32,
+    /// the camera which was active before, it gets the view back
+    pub previous: Option<Entity>,
+}
+
+impl Default for DebugCamera {
+    fn default() -> Self {
+        Self {
+            mode: DebugCameraMode::default(),
+            speed: 10.0,
+            yaw: 0.0,
+            pitch: 0.0,
+            focus: Vec3::ZERO,
+            distance: 24.0,
+            previous: None,
+        }
+    }
+}
+
+impl DebugCamera {
+    pub fn rotation(&self) -> Quat {
+        Quat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, 0.0)
+    }
+
+    pub fn look(&mut self, delta: Vec2, settings: &DebugCameraSettings) {
+        self.yaw -= delta.x * settings.sensitivity;
+        self.pitch = (self.pitch - delta.y * settings.sensitivity)
+            .clamp(-FRAC_PI_2 + 0.01, FRAC_PI_2 - 0.01);
+    }
+
+    /// scroll up speeds up in free-fly and moves closer in orbit
+    pub fn scroll(&mut self, steps: f32, settings: &DebugCameraSettings) {
+        let factor = settings.scroll_factor.powf(steps);
+        match self.mode {
+            DebugCameraMode::FreeFly => {
+                self.speed = (self.speed * factor).clamp(settings.min_speed, settings.max_speed)
+            }
+            DebugCameraMode::Orbit => self.distance = (self.distance / factor).max(1.0),
+        }
+    }
+
+    /// follow the angles of `transform`
+    pub fn copy_rotation(&mut self, transform: &Transform) {
+        let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        self.yaw = yaw;
+        self.pitch = pitch;
+    }
+
+    /// look at `focus` from `distance` away along the current angles
+    pub fn orbit_transform(&self) -> Transform {
+        let rotation = self.rotation();
+        Transform::from_translation(self.focus + rotation * Vec3::Z * self.distance)
+            .with_rotation(rotation)
+    }
+}
+
+/// center of the bounding box of the blocks of a chunk
+pub fn chunk_center(chunk: &Chunk) -> Option<Vec3> {
+    let min = chunk.data.keys().copied().reduce(IVec3::min)?;
+    let max = chunk.data.keys().copied().reduce(IVec3::max)?;
+    Some((min.as_vec3() + max.as_vec3() + Vec3::ONE) / 2.0)
+}
+
+pub fn spawn_debug_camera(mut commands: Commands) {
+    commands.spawn((
+        DebugCamera::default(),
+        Camera {
+            is_active: false,
+            ..default()
+        },
+    ));
+}
+
+pub fn debug_camera_active(camera: Query<&Camera, With<DebugCamera>>) -> bool {
+    camera.iter().any(|camera| camera.is_active)
+}
+
+type OtherCameras<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static mut Camera, &'static GlobalTransform),
+    (With<Camera3d>, Without<DebugCamera>),
+>;
+
+/// swap the debug camera with the active 3d camera, keeping the point of view
+pub fn toggle_debug_camera(
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<DebugCameraSettings>,
+    mut debug: Single<(&mut Camera, &mut Transform, &mut DebugCamera)>,
+    mut others: OtherCameras,
+) {
+    if !keys.just_pressed(settings.toggle_key) {
+        return;
+    }
+
+    let (camera, transform, state) = &mut *debug;
+    if camera.is_active {
+        camera.is_active = false;
+        // give the view back to the camera it was taken from, the first one if it is gone
+        let previous = state
+            .previous
+            .take()
+            .filter(|entity| others.contains(*entity))
+            .or_else(|| others.iter().next().map(|(entity, ..)| entity));
+        if let Some((_, mut other, _)) = previous.and_then(|entity| others.get_mut(entity).ok()) {
+            other.is_active = true;
+        }
+        return;
+    }
+
+    for (entity, mut other, global) in &mut others {
+        if other.is_active {
+            **transform = global.compute_transform();
+            other.is_active = false;
+            state.previous.get_or_insert(entity);
+        }
+    }
+    state.copy_rotation(transform);
+    state.mode = DebugCameraMode::FreeFly;
+    camera.is_active = true;
+}
+
+pub fn switch_debug_mode(
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<DebugCameraSettings>,
+    mut debug: Single<(&Transform, &mut DebugCamera)>,
+) {
+    if !keys.just_pressed(settings.mode_key) {
+        return;
+    }
+
+    let (transform, state) = &mut *debug;
+    state.mode = match state.mode {
+        DebugCameraMode::FreeFly => {
+            state.focus = transform.translation + transform.forward() * state.distance;
+            DebugCameraMode::Orbit
+        }
+        DebugCameraMode::Orbit => DebugCameraMode::FreeFly,
+    };
+}
+
+/// orbit around the chunk hit by the view, or the chunk which contains the camera
+pub fn focus_chunk(
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<DebugCameraSettings>,
+    chunks: Query<&Chunk>,
+    mut debug: Single<(&Transform, &mut DebugCamera)>,
+) {
+    if !keys.just_pressed(settings.focus_key) {
+        return;
+    }
+
+    let (transform, state) = &mut *debug;
+    let view = chunks.iter().collect::<ChunkView>();
+    let block = raycast(
+        &view,
+        transform.translation,
+        transform.forward(),
+        settings.focus_distance,
+        None,
+    )
+    .map(|hit| hit.position)
+    .unwrap_or(transform.translation.floor().as_ivec3());
+
+    let target = chunk_position(block);
+    let Some(center) = chunks
+        .iter()
+        .find(|chunk| chunk.position() == Some(target))
+        .and_then(chunk_center)
+    else {
+        return;
+    };
+
+    state.focus = center;
+    state.distance = CHUNK_SIZE as f32 * 1.5;
+    state.mode = DebugCameraMode::Orbit;
+}
+
+/// look around while the cursor is grabbed, or while the right button is held
+pub fn debug_camera_look(
+    mouse: Res<ButtonInput<MouseButton>>,
+    motion: Res<AccumulatedMouseMotion>,
+    scroll: Res<AccumulatedMouseScroll>,
+    settings: Res<DebugCameraSettings>,
+    window: Option<Single<&Window, With<PrimaryWindow>>>,
+    mut debug: Single<&mut DebugCamera>,
+) {
+    let grabbed =
+        window.is_some_and(|window| window.cursor_options.grab_mode != CursorGrabMode::None);
+    if grabbed || mouse.pressed(MouseButton::Right) {
+        debug.look(motion.delta, &settings);
+    }
+    if scroll.delta.y != 0.0 {
+        debug.scroll(scroll.delta.y.signum(), &settings);
+    }
+}
+
+pub fn debug_camera_move(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut debug: Single<(&mut Transform, &mut DebugCamera)>,
+) {
+    let (transform, state) = &mut *debug;
+    let axis = |negative, positive| keys.pressed(positive) as i32 - keys.pressed(negative) as i32;
+    let input = Vec3::new(
+        axis(KeyCode::KeyA, KeyCode::KeyD) as f32,
+        axis(KeyCode::ShiftLeft, KeyCode::Space) as f32,
+        axis(KeyCode::KeyW, KeyCode::KeyS) as f32,
+    );
+
+    match state.mode {
+        DebugCameraMode::FreeFly => {
+            let rotation = state.rotation();
+            let direction = rotation * input.with_y(0.0) + Vec3::Y * input.y;
+            transform.translation +=
+                direction.normalize_or_zero() * state.speed * time.delta_secs();
+            transform.rotation = rotation;
+        }
+        DebugCameraMode::Orbit => {
+            // move the focus on the ground plane
+            let rotation = Quat::from_rotation_y(state.yaw);
+            let direction = rotation * input.with_y(0.0) + Vec3::Y * input.y;
+            let offset = direction.normalize_or_zero() * state.speed * time.delta_secs();
+            state.focus += offset;
+            **transform = state.orbit_transform();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::identity::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_orbit_transform() {
+        let camera = DebugCamera {
+            mode: DebugCameraMode::Orbit,
+            focus: Vec3::new(8.0, 0.0, 8.0),
+            distance: 10.0,
+            yaw: FRAC_PI_2,
+            ..default()
+        };
+
+        let transform = camera.orbit_transform();
+        assert!(transform
+            .translation
+            .abs_diff_eq(Vec3::new(18.0, 0.0, 8.0), 1e-4));
+        assert!(transform.forward().abs_diff_eq(Vec3::NEG_X, 1e-4));
+
+        let mut copy = DebugCamera::default();
+        copy.copy_rotation(&transform);
+        assert!((copy.yaw - FRAC_PI_2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_scroll() {
+        let settings = DebugCameraSettings::default();
+        let mut camera = DebugCamera::default();
+        camera.scroll(1.0, &settings);
+        assert!((camera.speed - 12.0).abs() < 1e-4);
+        camera.scroll(-100.0, &settings);
+        assert_eq!(camera.speed, settings.min_speed);
+
+        camera.mode = DebugCameraMode::Orbit;
+        camera.scroll(1.0, &settings);
+        assert!((camera.distance - 20.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_chunk_center() {
+        let mut chunk = Chunk::default();
+        assert_eq!(chunk_center(&chunk), None);
+
+        for pos in [IVec3::new(16, -1, 16), IVec3::new(31, 4, 20)] {
+            chunk.data.insert(
+                pos,
+                BlockData {
+                    id: BlockId::from_static("bevy_craft:block/stone"),
+                },
+            );
+        }
+        assert_eq!(chunk_center(&chunk), Some(Vec3::new(24.0, 2.0, 18.5)));
+    }
+
+    #[test]
+    fn test_toggle_debug_camera() {
+        let mut app = App::new();
+        app.init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<DebugCameraSettings>()
+            .add_systems(Startup, spawn_debug_camera)
+            .add_systems(Update, toggle_debug_camera);
+        // an inactive camera before the player, such as an export camera
+        let export = app
+            .world_mut()
+            .spawn((
+                Camera3d::default(),
+                Camera {
+                    is_active: false,
+                    ..default()
+                },
+            ))
+            .id();
+        let player = app
+            .world_mut()
+            .spawn((Camera3d::default(), Transform::from_xyz(1.0, 2.0, 3.0)))
+            .id();
+        let toggle = |app: &mut App| {
+            let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            keys.release_all();
+            keys.clear();
+            keys.press(KeyCode::F5);
+            app.update();
+        };
+        let active =
+            |app: &App, entity: Entity| app.world().get::<Camera>(entity).unwrap().is_active;
+
+        toggle(&mut app);
+        let mut debug = app.world_mut().query::<(Entity, &DebugCamera)>();
+        let (debug_entity, state) = debug.single(app.world()).unwrap();
+        assert_eq!(state.previous, Some(player));
+        assert!(active(&app, debug_entity));
+        assert!(!active(&app, player) && !active(&app, export));
+
+        toggle(&mut app);
+        assert!(!active(&app, debug_entity));
+        assert!(active(&app, player));
+        assert!(!active(&app, export));
+    }
+}
@@ -0,0 +1,7 @@
+pub(crate) mod debug;
+pub(crate) mod plugin;
+
+pub mod prelude {
+    pub use super::debug::*;
+    pub use super::plugin::*;
+}
@@ -0,0 +1,29 @@
+use bevy::prelude::*;
+
+use crate::camera::prelude::*;
+
+/// a free-fly/orbit camera to inspect the world, toggled with F5,
+/// it replaces the active 3d camera while it is enabled
+pub struct DebugCameraPlugin;
+
+impl Plugin for DebugCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugCameraSettings>()
+            .add_systems(Startup, spawn_debug_camera)
+            .add_systems(
+                Update,
+                (
+                    toggle_debug_camera,
+                    (
+                        switch_debug_mode,
+                        focus_chunk,
+                        debug_camera_look,
+                        debug_camera_move,
+                    )
+                        .chain()
+                        .run_if(debug_camera_active),
+                )
+                    .chain(),
+            );
+    }
+}
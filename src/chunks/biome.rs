@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+
+use crate::{chunks::prelude::*, identity::prelude::*};
+
+/// colors multiplied with the faces which have a `tintindex`,
+/// 0 is the grass color and 1 is the foliage color
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiomeTints {
+    pub grass: [f32; 3],
+    pub foliage: [f32; 3],
+}
+
+impl Default for BiomeTints {
+    /// the tints of plains, used when the biome is not known
+    fn default() -> Self {
+        Self {
+            grass: [0.57, 0.74, 0.35],
+            foliage: [0.47, 0.67, 0.18],
+        }
+    }
+}
+
+impl BiomeTints {
+    pub fn get(&self, tintindex: Option<u8>) -> [f32; 3] {
+        match tintindex {
+            Some(0) => self.grass,
+            Some(1) => self.foliage,
+            _ => [1.0; 3],
+        }
+    }
+}
+
+/// the biome of every column of a chunk
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkBiomes {
+    palette: Vec<BiomeId>,
+    /// index into `palette`, ordered by z then x
+    columns: Vec<u8>,
+    /// tints of every palette entry, empty until `resolve` is called
+    tints: Vec<BiomeTints>,
+}
+
+impl ChunkBiomes {
+    /// the biome of every column from its world x and z
+    pub fn new(chunk_pos: IVec2, mut biome: impl FnMut(IVec2) -> BiomeId) -> Self {
+        let origin = chunk_pos * CHUNK_SIZE;
+        let mut palette = Vec::<BiomeId>::new();
+        let mut columns = Vec::with_capacity((CHUNK_SIZE * CHUNK_SIZE) as usize);
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let id = biome(origin + IVec2::new(x, z));
+                let index = match palette.iter().position(|known| *known == id) {
+                    Some(index) => index,
+                    None => {
+                        palette.push(id);
+                        palette.len() - 1
+                    }
+                };
+                columns.push(index as u8);
+            }
+        }
+        Self {
+            palette,
+            columns,
+            tints: Vec::new(),
+        }
+    }
+
+    /// `None` if a column refers outside of the palette
+    pub fn from_palette(palette: Vec<BiomeId>, columns: Vec<u8>) -> Option<Self> {
+        let valid = columns.len() == (CHUNK_SIZE * CHUNK_SIZE) as usize
+            && columns
+                .iter()
+                .all(|index| (*index as usize) < palette.len());
+        valid.then_some(Self {
+            palette,
+            columns,
+            tints: Vec::new(),
+        })
+    }
+
+    pub fn palette(&self) -> &[BiomeId] {
+        &self.palette
+    }
+
+    pub fn columns(&self) -> &[u8] {
+        &self.columns
+    }
+
+    fn column(&self, pos: IVec3) -> usize {
+        let local = pos.xz().rem_euclid(IVec2::splat(CHUNK_SIZE));
+        self.columns[(local.y * CHUNK_SIZE + local.x) as usize] as usize
+    }
+
+    /// the biome of the column which contains `pos`
+    pub fn get(&self, pos: IVec3) -> &BiomeId {
+        &self.palette[self.column(pos)]
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        self.tints.len() == self.palette.len()
+    }
+
+    /// look up the tints of every biome in the palette
+    pub fn resolve(&mut self, mut tints: impl FnMut(&BiomeId) -> BiomeTints) {
+        self.tints = self.palette.iter().map(&mut tints).collect();
+    }
+
+    pub fn tints(&self, pos: IVec3) -> BiomeTints {
+        self.tints
+            .get(self.column(pos))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+impl Chunk {
+    pub fn biome(&self, pos: IVec3) -> Option<&BiomeId> {
+        self.biomes.as_ref().map(|biomes| biomes.get(pos))
+    }
+
+    /// the color multiplied with a face of the block at `pos`
+    pub fn tint(&self, pos: IVec3, tintindex: Option<u8>) -> [f32; 3] {
+        self.biomes
+            .as_ref()
+            .map(|biomes| biomes.tints(pos))
+            .unwrap_or_default()
+            .get(tintindex)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_chunk_biomes() {
+        let desert = BiomeId::try_from("bevy_craft:desert").unwrap();
+        let plains = BiomeId::try_from("bevy_craft:plains").unwrap();
+        let chunk_pos = IVec2::new(-1, 2);
+        let mut biomes = ChunkBiomes::new(chunk_pos, |column| {
+            if column.x < -8 {
+                desert.clone()
+            } else {
+                plains.clone()
+            }
+        });
+        assert_eq!(biomes.palette().len(), 2);
+        assert_eq!(biomes.get(IVec3::new(-16, 5, 32)), &desert);
+        assert_eq!(biomes.get(IVec3::new(-1, -5, 47)), &plains);
+
+        assert!(!biomes.is_resolved());
+        assert_eq!(biomes.tints(IVec3::new(-16, 0, 32)), BiomeTints::default());
+        let sand = BiomeTints {
+            grass: [1.0, 0.5, 0.0],
+            foliage: [0.0; 3],
+        };
+        biomes.resolve(|id| {
+            if *id == desert {
+                sand
+            } else {
+                BiomeTints::default()
+            }
+        });
+        assert!(biomes.is_resolved());
+
+        let chunk = Chunk {
+            biomes: Some(biomes.clone()),
+            ..default()
+        };
+        assert_eq!(chunk.biome(IVec3::new(-10, 0, 40)), Some(&desert));
+        assert_eq!(chunk.tint(IVec3::new(-10, 0, 40), Some(0)), sand.grass);
+        assert_eq!(chunk.tint(IVec3::new(-10, 0, 40), None), [1.0; 3]);
+        assert_eq!(
+            Chunk::default().tint(IVec3::ZERO, Some(1)),
+            BiomeTints::default().foliage
+        );
+
+        let copy = ChunkBiomes::from_palette(biomes.palette().to_vec(), biomes.columns().to_vec())
+            .unwrap();
+        assert_eq!(copy.get(IVec3::new(-16, 0, 32)), &desert);
+        assert!(ChunkBiomes::from_palette(vec![plains], vec![1; 256]).is_none());
+    }
+}
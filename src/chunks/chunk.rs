@@ -1,43 +1,54 @@
 use bevy::{platform::collections::HashMap, prelude::*};
 
-use crate::{assets::prelude::*, identity::prelude::*};
+use crate::{assets::prelude::*, chunks::prelude::*, identity::prelude::*, render::prelude::*};
 
 pub const CHUNK_SIZE: i32 = 16;
 
+/// position of the chunk which contains the block at `block_pos`
+pub fn chunk_position(block_pos: IVec3) -> IVec2 {
+    block_pos.xz().div_euclid(IVec2::splat(CHUNK_SIZE))
+}
+
 #[derive(Component, Default, Debug)]
 pub struct Chunk {
     pub data: HashMap<IVec3, BlockData>,
+    /// computed by `compute_light`, the chunk is fully lit while it is `None`
+    pub light: Option<ChunkLight>,
+    /// the faces with a `tintindex` use the default tints while it is `None`
+    pub biomes: Option<ChunkBiomes>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BlockData {
     pub id: BlockId,
 }
 
 impl Chunk {
-    pub fn mesh(&self, atlas: &AppTextureAtlas<TextureId>, models: &ModelManager) -> Mesh {
-        let vertex = self
-            .data
+    pub fn mesh(&self, context: &MeshContext) -> Mesh {
+        Mesh::from(self.vertex(context))
+    }
+
+    pub fn vertex(&self, context: &MeshContext) -> Vertex {
+        self.data
             .iter()
             .flat_map(|(pos, block_data)| {
-                models
-                    .get(&block_data.id)
-                    .and_then(|model| model.vertex(*pos, atlas, self, models))
+                context
+                    .models
+                    .get(block_data.id.location())
+                    .and_then(|model| model.vertex(*pos, self, context))
             })
             .reduce(|mut first, second| {
                 first.merge(second);
                 first
             })
-            .unwrap_or_default();
-
-        Mesh::from(vertex)
+            .unwrap_or_default()
     }
 
     pub fn position(&self) -> Option<IVec2> {
         self.data
             .iter()
             .next()
-            .map(|(block_pos, _)| block_pos.xz().div_euclid(IVec2::splat(CHUNK_SIZE)))
+            .map(|(block_pos, _)| chunk_position(*block_pos))
     }
 
     pub fn opposite(&self, pos: IVec3, face: BlockFace) -> Option<&BlockData> {
@@ -55,7 +66,7 @@ mod test {
         chunk.data.insert(
             IVec3::new(0, 0, 0),
             BlockData {
-                id: BlockId(String::new()),
+                id: BlockId::from_static("bevy_craft:block/stone"),
             },
         );
         assert_eq!(chunk.position(), Some(IVec2::new(0, 0)));
@@ -64,7 +75,7 @@ mod test {
         chunk.data.insert(
             IVec3::new(1, 0, 15),
             BlockData {
-                id: BlockId(String::new()),
+                id: BlockId::from_static("bevy_craft:block/stone"),
             },
         );
         assert_eq!(chunk.position(), Some(IVec2::new(0, 0)));
@@ -73,7 +84,7 @@ mod test {
         chunk.data.insert(
             IVec3::new(16, 0, 16),
             BlockData {
-                id: BlockId(String::new()),
+                id: BlockId::from_static("bevy_craft:block/stone"),
             },
         );
         assert_eq!(chunk.position(), Some(IVec2::new(1, 1)));
@@ -82,7 +93,7 @@ mod test {
         chunk.data.insert(
             IVec3::new(9, 0, 10),
             BlockData {
-                id: BlockId(String::new()),
+                id: BlockId::from_static("bevy_craft:block/stone"),
             },
         );
         assert_eq!(chunk.position(), Some(IVec2::new(0, 0)));
@@ -91,7 +102,7 @@ mod test {
         chunk.data.insert(
             IVec3::new(-9, 0, 10),
             BlockData {
-                id: BlockId(String::new()),
+                id: BlockId::from_static("bevy_craft:block/stone"),
             },
         );
         assert_eq!(chunk.position(), Some(IVec2::new(-1, 0)));
@@ -100,7 +111,7 @@ mod test {
         chunk.data.insert(
             IVec3::new(-16, 0, -10),
             BlockData {
-                id: BlockId(String::new()),
+                id: BlockId::from_static("bevy_craft:block/stone"),
             },
         );
         assert_eq!(chunk.position(), Some(IVec2::new(-1, -1)));
@@ -109,7 +120,7 @@ mod test {
         chunk.data.insert(
             IVec3::new(-17, 0, -17),
             BlockData {
-                id: BlockId(String::new()),
+                id: BlockId::from_static("bevy_craft:block/stone"),
             },
         );
         assert_eq!(chunk.position(), Some(IVec2::new(-2, -2)));
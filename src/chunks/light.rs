@@ -0,0 +1,616 @@
+use std::collections::VecDeque;
+
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::{assets::prelude::*, chunks::prelude::*};
+
+/// the darkest a lit face is rendered, so that caves are not pitch black
+pub const MIN_BRIGHTNESS: f32 = 0.05;
+
+/// light of a block, sky light in the high nibble and block light in the low nibble
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Light(u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LightChannel {
+    Sky,
+    Block,
+}
+
+impl Light {
+    pub const DARK: Light = Light(0);
+    pub const SKY: Light = Light(MAX_LIGHT_LEVEL << 4);
+
+    pub fn new(sky: u8, block: u8) -> Self {
+        Self((sky.min(MAX_LIGHT_LEVEL) << 4) | block.min(MAX_LIGHT_LEVEL))
+    }
+
+    pub fn sky(&self) -> u8 {
+        self.0 >> 4
+    }
+
+    pub fn block(&self) -> u8 {
+        self.0 & 0x0f
+    }
+
+    pub fn get(&self, channel: LightChannel) -> u8 {
+        match channel {
+            LightChannel::Sky => self.sky(),
+            LightChannel::Block => self.block(),
+        }
+    }
+
+    pub fn with(self, channel: LightChannel, level: u8) -> Self {
+        match channel {
+            LightChannel::Sky => Self::new(level, self.block()),
+            LightChannel::Block => Self::new(self.sky(), level),
+        }
+    }
+
+    /// sky and block light in 0..=1
+    pub fn levels(&self) -> [f32; 2] {
+        [
+            self.sky() as f32 / MAX_LIGHT_LEVEL as f32,
+            self.block() as f32 / MAX_LIGHT_LEVEL as f32,
+        ]
+    }
+}
+
+/// rendered brightness of a light level in 0..=1
+pub fn brightness(level: f32) -> f32 {
+    let level = level.clamp(0.0, 1.0);
+    (level / (4.0 - 3.0 * level)).max(MIN_BRIGHTNESS)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct BorderColumn {
+    min_y: i32,
+    values: Vec<Light>,
+}
+
+/// the light of the columns around a chunk, copied from its lit neighbors,
+/// it spreads into the chunk like the light of a source
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BorderLight {
+    columns: HashMap<IVec2, BorderColumn>,
+}
+
+impl BorderLight {
+    /// the columns around the chunk at `chunk_pos` which are in a neighbor with light
+    pub fn new<'a>(chunk_pos: IVec2, neighbor: impl Fn(IVec2) -> Option<&'a Chunk>) -> Self {
+        let origin = chunk_pos * CHUNK_SIZE;
+        let mut columns = HashMap::default();
+        for x in -1..=CHUNK_SIZE {
+            for z in -1..=CHUNK_SIZE {
+                if (0..CHUNK_SIZE).contains(&x) && (0..CHUNK_SIZE).contains(&z) {
+                    continue;
+                }
+                let column = origin + IVec2::new(x, z);
+                let light = neighbor(chunk_position(IVec3::new(column.x, 0, column.y)))
+                    .and_then(|chunk| chunk.light.as_ref());
+                if let Some(light) = light {
+                    columns.insert(column, light.column(column));
+                }
+            }
+        }
+        Self { columns }
+    }
+
+    /// the light at `pos`, `None` if its column is not known
+    fn get(&self, pos: IVec3) -> Option<Light> {
+        let column = self.columns.get(&pos.xz())?;
+        let Ok(index) = usize::try_from(pos.y - column.min_y) else {
+            return Some(Light::DARK);
+        };
+        Some(column.values.get(index).copied().unwrap_or(Light::SKY))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+}
+
+/// the light of a chunk, from one block below its lowest block up to its highest block,
+/// everything above is open sky
+#[derive(Debug, Default, Clone)]
+pub struct ChunkLight {
+    min: IVec3,
+    height: i32,
+    values: Vec<Light>,
+    border: BorderLight,
+}
+
+impl ChunkLight {
+    fn new(chunk_pos: IVec2, min_y: i32, max_y: i32, border: BorderLight) -> Self {
+        let height = max_y - min_y + 1;
+        Self {
+            min: IVec3::new(chunk_pos.x * CHUNK_SIZE, min_y, chunk_pos.y * CHUNK_SIZE),
+            height,
+            values: vec![Light::DARK; (CHUNK_SIZE * CHUNK_SIZE * height) as usize],
+            border,
+        }
+    }
+
+    fn index(&self, pos: IVec3) -> Option<usize> {
+        let local = pos - self.min;
+        let inside = (0..CHUNK_SIZE).contains(&local.x)
+            && (0..CHUNK_SIZE).contains(&local.z)
+            && (0..self.height).contains(&local.y);
+        inside.then(|| ((local.y * CHUNK_SIZE + local.z) * CHUNK_SIZE + local.x) as usize)
+    }
+
+    pub fn contains(&self, pos: IVec3) -> bool {
+        self.index(pos).is_some()
+    }
+
+    /// the top of the chunk is open sky, and so are the sides
+    /// which are not in `BorderLight`
+    pub fn get(&self, pos: IVec3) -> Light {
+        match self.index(pos) {
+            Some(index) => self.values[index],
+            None if pos.y < self.min.y => Light::DARK,
+            None => self.border.get(pos).unwrap_or(Light::SKY),
+        }
+    }
+
+    pub fn border(&self) -> &BorderLight {
+        &self.border
+    }
+
+    /// the light of the column, from the bottom of the chunk up to its top
+    fn column(&self, column: IVec2) -> BorderColumn {
+        BorderColumn {
+            min_y: self.min.y,
+            values: (self.min.y..=self.top())
+                .map(|y| self.get(IVec3::new(column.x, y, column.y)))
+                .collect(),
+        }
+    }
+
+    /// the light of the neighbors in the chunk volume which spreads into the chunk
+    fn border_sources(&self, channel: LightChannel) -> VecDeque<IVec3> {
+        let mut queue = VecDeque::new();
+        for column in self.border.columns.keys() {
+            for y in self.min.y..=self.top() {
+                let pos = IVec3::new(column.x, y, column.y);
+                if self.level(pos, channel) > 0 {
+                    queue.push_back(pos);
+                }
+            }
+        }
+        queue
+    }
+
+    fn level(&self, pos: IVec3, channel: LightChannel) -> u8 {
+        self.get(pos).get(channel)
+    }
+
+    fn set(&mut self, pos: IVec3, channel: LightChannel, level: u8) {
+        if let Some(index) = self.index(pos) {
+            self.values[index] = self.values[index].with(channel, level);
+        }
+    }
+
+    fn top(&self) -> i32 {
+        self.min.y + self.height - 1
+    }
+}
+
+/// whether the block hides the faces behind it and blocks light,
+/// a block without a definition is opaque
+pub fn is_opaque(block: Option<&BlockData>, definitions: Option<&BlockDefinitions>) -> bool {
+    light_properties(block, definitions).0
+}
+
+/// whether the block hides light, and the light it emits
+fn light_properties(
+    block: Option<&BlockData>,
+    definitions: Option<&BlockDefinitions>,
+) -> (bool, u8) {
+    let Some(block) = block else {
+        return (false, 0);
+    };
+    definitions
+        .and_then(|definitions| definitions.get(&block.id))
+        .map(|definition| (definition.opaque, definition.light_emission()))
+        .unwrap_or((true, 0))
+}
+
+/// light level after travelling one block toward `face`,
+/// full sky light goes straight down without fading
+fn spread(channel: LightChannel, face: BlockFace, level: u8) -> u8 {
+    if channel == LightChannel::Sky && face == BlockFace::Down && level == MAX_LIGHT_LEVEL {
+        level
+    } else {
+        level.saturating_sub(1)
+    }
+}
+
+/// flood fill from every position in `queue`
+fn propagate(
+    data: &HashMap<IVec3, BlockData>,
+    definitions: Option<&BlockDefinitions>,
+    light: &mut ChunkLight,
+    channel: LightChannel,
+    mut queue: VecDeque<IVec3>,
+) {
+    while let Some(pos) = queue.pop_front() {
+        let level = light.level(pos, channel);
+        for face in BlockFace::ALL {
+            let neighbor = pos + IVec3::from(face);
+            if !light.contains(neighbor) || light_properties(data.get(&neighbor), definitions).0 {
+                continue;
+            }
+
+            let next = spread(channel, face, level);
+            if next > light.level(neighbor, channel) {
+                light.set(neighbor, channel, next);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+}
+
+/// remove the light which came through `pos`, then refill it from the remaining sources
+fn relight(
+    data: &HashMap<IVec3, BlockData>,
+    definitions: Option<&BlockDefinitions>,
+    light: &mut ChunkLight,
+    channel: LightChannel,
+    pos: IVec3,
+) {
+    let mut removal = VecDeque::from([(pos, light.level(pos, channel))]);
+    let mut refill = VecDeque::new();
+    light.set(pos, channel, 0);
+
+    while let Some((current, level)) = removal.pop_front() {
+        for face in BlockFace::ALL {
+            let neighbor = current + IVec3::from(face);
+            if !light.contains(neighbor) {
+                // the light of the neighbor chunks is not removed, it fills the chunk again
+                if light
+                    .border
+                    .get(neighbor)
+                    .is_some_and(|border| border.get(channel) > 0)
+                {
+                    refill.push_back(neighbor);
+                }
+                continue;
+            }
+
+            let neighbor_level = light.level(neighbor, channel);
+            if neighbor_level == 0 {
+                continue;
+            }
+            if neighbor_level < level || spread(channel, face, level) == neighbor_level {
+                light.set(neighbor, channel, 0);
+                removal.push_back((neighbor, neighbor_level));
+            } else {
+                refill.push_back(neighbor);
+            }
+        }
+    }
+
+    let (opaque, emission) = light_properties(data.get(&pos), definitions);
+    let mut level = match channel {
+        LightChannel::Block => emission,
+        LightChannel::Sky => 0,
+    };
+    if !opaque {
+        // pull from the neighbors, the sky above the chunk is not in the queue
+        for face in BlockFace::ALL {
+            let neighbor = pos + IVec3::from(face);
+            let known = light.border.get(neighbor).is_some() || neighbor.y > light.top();
+            if !light.contains(neighbor) && !known {
+                continue;
+            }
+            level = level.max(spread(
+                channel,
+                face.opposite(),
+                light.level(neighbor, channel),
+            ));
+        }
+    }
+    light.set(pos, channel, level);
+    refill.push_back(pos);
+
+    propagate(data, definitions, light, channel, refill);
+}
+
+impl Chunk {
+    /// light at `pos`, full sky light if the light of the chunk is not computed
+    pub fn light(&self, pos: IVec3) -> Light {
+        self.light
+            .as_ref()
+            .map(|light| light.get(pos))
+            .unwrap_or(Light::SKY)
+    }
+
+    /// compute the sky light and the block light of the whole chunk,
+    /// the `BorderLight` of the previous light is kept
+    pub fn compute_light(&mut self, definitions: Option<&BlockDefinitions>) {
+        let border = self
+            .light
+            .take()
+            .map(|light| light.border)
+            .unwrap_or_default();
+        self.compute_light_with(definitions, border);
+    }
+
+    /// compute the light of the whole chunk with the light of its neighbors
+    pub fn compute_light_with(
+        &mut self,
+        definitions: Option<&BlockDefinitions>,
+        border: BorderLight,
+    ) {
+        let Some(chunk_pos) = self.position() else {
+            self.light = None;
+            return;
+        };
+        let min_y = self.data.keys().map(|pos| pos.y).min().unwrap_or_default();
+        let max_y = self.data.keys().map(|pos| pos.y).max().unwrap_or_default();
+        let mut light = ChunkLight::new(chunk_pos, min_y - 1, max_y, border);
+
+        // sky light falls down each column until an opaque block
+        let mut queue = light.border_sources(LightChannel::Sky);
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for y in (light.min.y..=max_y).rev() {
+                    let pos = light.min.with_y(y) + IVec3::new(x, 0, z);
+                    if light_properties(self.data.get(&pos), definitions).0 {
+                        break;
+                    }
+                    light.set(pos, LightChannel::Sky, MAX_LIGHT_LEVEL);
+                    queue.push_back(pos);
+                }
+            }
+        }
+        propagate(
+            &self.data,
+            definitions,
+            &mut light,
+            LightChannel::Sky,
+            queue,
+        );
+
+        let mut queue = light.border_sources(LightChannel::Block);
+        for (pos, block) in &self.data {
+            let emission = light_properties(Some(block), definitions).1;
+            if emission > 0 {
+                light.set(*pos, LightChannel::Block, emission);
+                queue.push_back(*pos);
+            }
+        }
+        propagate(
+            &self.data,
+            definitions,
+            &mut light,
+            LightChannel::Block,
+            queue,
+        );
+
+        self.light = Some(light);
+    }
+
+    /// update the light after the block at `pos` is placed or removed
+    pub fn update_light(&mut self, pos: IVec3, definitions: Option<&BlockDefinitions>) {
+        let Some(light) = self.light.as_mut() else {
+            self.compute_light(definitions);
+            return;
+        };
+
+        // the block is outside of the light volume, or on its bottom layer which must stay empty
+        let outside = !light.contains(pos) || pos.y > light.top() || pos.y == light.min.y;
+        if outside && self.data.contains_key(&pos) {
+            self.compute_light(definitions);
+            return;
+        }
+        if !light.contains(pos) {
+            return;
+        }
+
+        relight(&self.data, definitions, light, LightChannel::Sky, pos);
+        relight(&self.data, definitions, light, LightChannel::Block, pos);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::identity::prelude::*;
+
+    use super::*;
+
+    fn stone() -> BlockData {
+        BlockData {
+            id: BlockId::from_static("bevy_craft:block/stone"),
+        }
+    }
+
+    fn torch() -> BlockData {
+        BlockData {
+            id: BlockId::from_static("bevy_craft:block/torch"),
+        }
+    }
+
+    fn definitions() -> BlockDefinitions {
+        let mut definitions = BlockDefinitions::default();
+        definitions.insert(
+            torch().id,
+            BlockDefinition {
+                opaque: false,
+                light_emission: 14,
+                ..default()
+            },
+        );
+        definitions
+    }
+
+    /// a stone floor at y = 0, and a closed 3x3x3 room with stone walls at (4..7, 1..4, 4..7)
+    fn room() -> Chunk {
+        let mut chunk = Chunk::default();
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                chunk.data.insert(IVec3::new(x, 0, z), stone());
+            }
+        }
+        for x in 3..8 {
+            for y in 1..6 {
+                for z in 3..8 {
+                    let inside = (4..7).contains(&x) && (1..4).contains(&y) && (4..7).contains(&z);
+                    if !inside && y != 5 {
+                        chunk.data.insert(IVec3::new(x, y, z), stone());
+                    }
+                }
+            }
+        }
+        for x in 3..8 {
+            for z in 3..8 {
+                chunk.data.insert(IVec3::new(x, 4, z), stone());
+            }
+        }
+        chunk
+    }
+
+    #[test]
+    fn test_light_nibbles() {
+        let light = Light::new(12, 7);
+        assert_eq!((light.sky(), light.block()), (12, 7));
+        assert_eq!(light.with(LightChannel::Sky, 3), Light::new(3, 7));
+        assert_eq!(Light::new(20, 20), Light::new(15, 15));
+        assert_eq!(brightness(1.0), 1.0);
+        assert_eq!(brightness(0.0), MIN_BRIGHTNESS);
+    }
+
+    #[test]
+    fn test_sky_light() {
+        let definitions = definitions();
+        let mut chunk = room();
+        chunk.compute_light(Some(&definitions));
+
+        assert_eq!(chunk.light(IVec3::new(0, 1, 0)).sky(), MAX_LIGHT_LEVEL);
+        assert_eq!(chunk.light(IVec3::new(0, 10, 0)).sky(), MAX_LIGHT_LEVEL);
+        assert_eq!(chunk.light(IVec3::new(5, 2, 5)), Light::DARK);
+        assert_eq!(chunk.light(IVec3::new(5, 0, 5)), Light::DARK);
+
+        // open a hole in the roof
+        chunk.data.remove(&IVec3::new(5, 4, 5));
+        chunk.update_light(IVec3::new(5, 4, 5), Some(&definitions));
+        assert_eq!(chunk.light(IVec3::new(5, 1, 5)).sky(), MAX_LIGHT_LEVEL);
+        assert_eq!(chunk.light(IVec3::new(4, 1, 4)).sky(), MAX_LIGHT_LEVEL - 2);
+
+        // and close it again
+        chunk.data.insert(IVec3::new(5, 4, 5), stone());
+        chunk.update_light(IVec3::new(5, 4, 5), Some(&definitions));
+        assert_eq!(chunk.light(IVec3::new(5, 1, 5)), Light::DARK);
+        assert_eq!(chunk.light(IVec3::new(4, 1, 4)), Light::DARK);
+    }
+
+    #[test]
+    fn test_block_light() {
+        let definitions = definitions();
+        let mut chunk = room();
+        chunk.data.insert(IVec3::new(4, 1, 4), torch());
+        chunk.compute_light(Some(&definitions));
+
+        assert_eq!(chunk.light(IVec3::new(4, 1, 4)).block(), 14);
+        assert_eq!(chunk.light(IVec3::new(5, 1, 4)).block(), 13);
+        assert_eq!(chunk.light(IVec3::new(6, 3, 6)).block(), 8);
+        // the walls block the light
+        assert_eq!(chunk.light(IVec3::new(2, 1, 4)).block(), 0);
+
+        chunk.data.remove(&IVec3::new(4, 1, 4));
+        chunk.update_light(IVec3::new(4, 1, 4), Some(&definitions));
+        assert_eq!(chunk.light(IVec3::new(4, 1, 4)).block(), 0);
+        assert_eq!(chunk.light(IVec3::new(6, 3, 6)).block(), 0);
+    }
+
+    /// a dark layer at y = 1 between a stone floor and a stone roof
+    fn slab(chunk_pos: IVec2) -> Chunk {
+        let mut chunk = Chunk::default();
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let pos = (chunk_pos * CHUNK_SIZE + IVec2::new(x, z)).extend(0).xzy();
+                chunk.data.insert(pos, stone());
+                chunk.data.insert(pos.with_y(2), stone());
+            }
+        }
+        chunk
+    }
+
+    #[test]
+    fn test_border_light() {
+        let definitions = definitions();
+        let torch_pos = IVec3::new(15, 1, 5);
+        let mut chunks = [slab(IVec2::ZERO), slab(IVec2::X)];
+        chunks[0].data.insert(torch_pos, torch());
+        chunks[0].compute_light(Some(&definitions));
+        // the sides are open sky until the neighbor is known
+        assert_eq!(chunks[0].light(IVec3::new(16, 1, 5)), Light::SKY);
+
+        // the light of the torch crosses into the neighbor
+        let border = BorderLight::new(IVec2::X, |pos| (pos == IVec2::ZERO).then_some(&chunks[0]));
+        chunks[1].compute_light_with(Some(&definitions), border);
+        assert_eq!(chunks[1].light(IVec3::new(16, 1, 5)), Light::new(0, 13));
+        assert_eq!(chunks[1].light(IVec3::new(18, 1, 5)).block(), 11);
+        assert_eq!(chunks[1].light(IVec3::new(17, 1, 7)).block(), 10);
+
+        let border = BorderLight::new(IVec2::ZERO, |pos| (pos == IVec2::X).then_some(&chunks[1]));
+        chunks[0].compute_light_with(Some(&definitions), border);
+        assert_eq!(chunks[0].light(IVec3::new(16, 1, 5)), Light::new(0, 13));
+        assert_eq!(chunks[0].light(torch_pos).block(), 14);
+
+        // the light left in the neighbors fades out as the borders are copied back and forth
+        chunks[0].data.remove(&torch_pos);
+        chunks[0].update_light(torch_pos, Some(&definitions));
+        for _ in 0..MAX_LIGHT_LEVEL {
+            let border =
+                BorderLight::new(IVec2::X, |pos| (pos == IVec2::ZERO).then_some(&chunks[0]));
+            chunks[1].compute_light_with(Some(&definitions), border);
+            let border =
+                BorderLight::new(IVec2::ZERO, |pos| (pos == IVec2::X).then_some(&chunks[1]));
+            chunks[0].compute_light_with(Some(&definitions), border);
+        }
+        assert_eq!(chunks[0].light(torch_pos), Light::DARK);
+        assert_eq!(chunks[1].light(IVec3::new(16, 1, 5)), Light::DARK);
+    }
+
+    #[test]
+    fn test_update_matches_compute() {
+        let definitions = definitions();
+        let mut chunk = room();
+        chunk.compute_light(Some(&definitions));
+
+        let edits = [
+            (IVec3::new(5, 2, 5), Some(torch())),
+            (IVec3::new(5, 4, 5), None),
+            (IVec3::new(3, 2, 5), None),
+            (IVec3::new(10, 3, 10), Some(stone())),
+            (IVec3::new(10, 2, 11), Some(torch())),
+            (IVec3::new(5, 4, 5), Some(stone())),
+            (IVec3::new(5, 2, 5), None),
+            (IVec3::new(9, 1, 9), Some(stone())),
+            (IVec3::new(3, 2, 5), Some(stone())),
+        ];
+        for (pos, block) in edits {
+            match block {
+                Some(block) => chunk.data.insert(pos, block),
+                None => chunk.data.remove(&pos),
+            };
+            chunk.update_light(pos, Some(&definitions));
+
+            let mut expected = Chunk {
+                data: chunk.data.clone(),
+                ..default()
+            };
+            expected.compute_light(Some(&definitions));
+            for x in 0..CHUNK_SIZE {
+                for y in -1..8 {
+                    for z in 0..CHUNK_SIZE {
+                        let pos = IVec3::new(x, y, z);
+                        assert_eq!(chunk.light(pos), expected.light(pos), "{:?}", pos);
+                    }
+                }
+            }
+        }
+    }
+}
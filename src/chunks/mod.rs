@@ -1,5 +1,11 @@
+pub(crate) mod biome;
 pub(crate) mod chunk;
+pub(crate) mod light;
+pub(crate) mod raycast;
 
 pub mod prelude {
+    pub use super::biome::*;
     pub use super::chunk::*;
+    pub use super::light::*;
+    pub use super::raycast::*;
 }
@@ -0,0 +1,290 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::{assets::prelude::*, chunks::prelude::*, identity::prelude::*};
+
+/// anything which can be queried block by block, such as a `Chunk` or the world
+pub trait BlockGetter {
+    fn get_block(&self, pos: IVec3) -> Option<&BlockData>;
+}
+
+impl BlockGetter for Chunk {
+    fn get_block(&self, pos: IVec3) -> Option<&BlockData> {
+        self.data.get(&pos)
+    }
+}
+
+/// the spawned chunks keyed by `Chunk::position`
+#[derive(Default)]
+pub struct ChunkView<'a>(HashMap<IVec2, &'a Chunk>);
+
+impl<'a> FromIterator<&'a Chunk> for ChunkView<'a> {
+    fn from_iter<T: IntoIterator<Item = &'a Chunk>>(iter: T) -> Self {
+        Self(
+            iter.into_iter()
+                .filter_map(|chunk| chunk.position().map(|pos| (pos, chunk)))
+                .collect(),
+        )
+    }
+}
+
+impl ChunkView<'_> {
+    /// whether the chunk at `chunk_pos` is in the view
+    pub fn contains(&self, chunk_pos: IVec2) -> bool {
+        self.0.contains_key(&chunk_pos)
+    }
+}
+
+impl BlockGetter for ChunkView<'_> {
+    fn get_block(&self, pos: IVec3) -> Option<&BlockData> {
+        self.0
+            .get(&chunk_position(pos))
+            .and_then(|chunk| chunk.data.get(&pos))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RaycastHit {
+    /// position of the hit block
+    pub position: IVec3,
+    /// face of the block which the ray enters
+    pub face: BlockFace,
+    /// distance from the origin to `point`
+    pub distance: f32,
+    pub point: Vec3,
+    pub block_id: BlockId,
+}
+
+impl RaycastHit {
+    /// the position in front of the hit face, such as where a block is placed
+    pub fn adjacent(&self) -> IVec3 {
+        self.position + IVec3::from(self.face)
+    }
+}
+
+/// DDA voxel traversal from `origin` along `direction` up to `max_distance`,
+/// if `shapes` is given, the ray is tested against the `VoxelShape` of the block,
+/// otherwise every block is a unit cube
+pub fn raycast(
+    world: &impl BlockGetter,
+    origin: Vec3,
+    direction: Dir3,
+    max_distance: f32,
+    shapes: Option<&VoxelShapes>,
+) -> Option<RaycastHit> {
+    let direction = direction.as_vec3();
+    let mut voxel = origin.floor().as_ivec3();
+    let step = direction.signum().as_ivec3();
+
+    // distance along the ray to cross one voxel on each axis
+    let delta = direction.recip().abs();
+    // distance along the ray to the first boundary on each axis
+    let mut next = Vec3::select(
+        direction.cmpgt(Vec3::ZERO),
+        (voxel.as_vec3() + Vec3::ONE - origin) * delta,
+        (origin - voxel.as_vec3()) * delta,
+    );
+    // avoid 0 * inf when the ray is parallel to an axis
+    next = Vec3::select(direction.cmpeq(Vec3::ZERO), Vec3::INFINITY, next);
+    let mut distance = 0.0;
+    let mut face = entry_face(direction, None);
+
+    while distance <= max_distance {
+        if let Some(block_data) = world.get_block(voxel) {
+            let shape = shapes.and_then(|shapes| shapes.get(&block_data.id));
+            let hit = match shape {
+                Some(shape) => {
+                    hit_shape(shape, voxel, origin, direction).filter(|(t, _)| *t <= max_distance)
+                }
+                None => Some((distance, face)),
+            };
+
+            if let Some((t, face)) = hit {
+                return Some(RaycastHit {
+                    position: voxel,
+                    face,
+                    distance: t,
+                    point: origin + direction * t,
+                    block_id: block_data.id.clone(),
+                });
+            }
+        }
+
+        let axis = if next.x < next.y && next.x < next.z {
+            0
+        } else if next.y < next.z {
+            1
+        } else {
+            2
+        };
+        distance = next[axis];
+        next[axis] += delta[axis];
+        voxel[axis] += step[axis];
+        face = entry_face(direction, Some(axis));
+    }
+
+    None
+}
+
+/// the face which is entered when the ray crosses a boundary on `axis`,
+/// or the face against the main direction if the ray starts inside
+fn entry_face(direction: Vec3, axis: Option<usize>) -> BlockFace {
+    let axis = axis.unwrap_or_else(|| major_axis(direction));
+    let positive = direction[axis] > 0.0;
+    match (axis, positive) {
+        (0, true) => BlockFace::West,
+        (0, false) => BlockFace::East,
+        (1, true) => BlockFace::Down,
+        (1, false) => BlockFace::Up,
+        (_, true) => BlockFace::North,
+        (_, false) => BlockFace::South,
+    }
+}
+
+fn major_axis(direction: Vec3) -> usize {
+    let abs = direction.abs();
+    if abs.x >= abs.y && abs.x >= abs.z {
+        0
+    } else if abs.y >= abs.z {
+        1
+    } else {
+        2
+    }
+}
+
+/// nearest hit of the ray against the shape of the block at `pos`
+fn hit_shape(
+    shape: &VoxelShape,
+    pos: IVec3,
+    origin: Vec3,
+    direction: Vec3,
+) -> Option<(f32, BlockFace)> {
+    shape
+        .aabbs(pos)
+        .iter()
+        .filter_map(|aabb| ray_aabb(origin, direction, aabb.min.into(), aabb.max.into()))
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+/// slab test, returns the distance and the face where the ray enters the box
+pub fn ray_aabb(origin: Vec3, direction: Vec3, min: Vec3, max: Vec3) -> Option<(f32, BlockFace)> {
+    let inv = direction.recip();
+    let t1 = (min - origin) * inv;
+    let t2 = (max - origin) * inv;
+    let near = t1.min(t2);
+    let far = t1.max(t2);
+
+    // NaN comes from 0 * inf when the ray is parallel and on the slab,
+    // it is ignored by max_element/min_element
+    let t_near = near.max_element();
+    let t_far = far.min_element();
+    if t_near > t_far || t_far < 0.0 {
+        return None;
+    }
+
+    if t_near < 0.0 {
+        return Some((0.0, entry_face(direction, None)));
+    }
+
+    let axis = (0..3)
+        .find(|axis| near[*axis] == t_near)
+        .unwrap_or_else(|| major_axis(direction));
+    Some((t_near, entry_face(direction, Some(axis))))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn block(id: &str) -> BlockData {
+        BlockData {
+            id: BlockId::try_from(id).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_raycast_cube() {
+        let mut chunk = Chunk::default();
+        chunk
+            .data
+            .insert(IVec3::new(3, 0, 0), block("bevy_craft:block/dirt"));
+
+        let hit = raycast(&chunk, Vec3::new(0.5, 0.5, 0.5), Dir3::X, 10.0, None).unwrap();
+        assert_eq!(hit.position, IVec3::new(3, 0, 0));
+        assert_eq!(hit.face, BlockFace::West);
+        assert_eq!(hit.distance, 2.5);
+        assert_eq!(hit.adjacent(), IVec3::new(2, 0, 0));
+        assert_eq!(hit.block_id, BlockId::from_static("bevy_craft:block/dirt"));
+
+        assert!(raycast(&chunk, Vec3::new(0.5, 0.5, 0.5), Dir3::X, 2.0, None).is_none());
+        assert!(raycast(&chunk, Vec3::new(0.5, 0.5, 0.5), Dir3::NEG_X, 10.0, None).is_none());
+
+        chunk
+            .data
+            .insert(IVec3::new(-1, -3, -1), block("bevy_craft:block/stone"));
+        let direction = Dir3::new(Vec3::new(-1.0, -2.0, -1.0)).unwrap();
+        let hit = raycast(&chunk, Vec3::new(0.5, 0.9, 0.5), direction, 10.0, None).unwrap();
+        assert_eq!(hit.position, IVec3::new(-1, -3, -1));
+        assert_eq!(hit.face, BlockFace::Up);
+    }
+
+    #[test]
+    fn test_raycast_shape() {
+        let slab = BlockId::from_static("bevy_craft:block/slab");
+        let mut shapes = VoxelShapes::default();
+        shapes.insert(
+            slab.clone(),
+            VoxelShape::new([ShapeBox::new([0, 0, 0], [16, 8, 16])]),
+        );
+
+        let mut chunk = Chunk::default();
+        chunk
+            .data
+            .insert(IVec3::new(0, 0, 0), block(&slab.to_string()));
+
+        // pass over the slab
+        let origin = Vec3::new(-1.0, 0.75, 0.5);
+        assert!(raycast(&chunk, origin, Dir3::X, 10.0, None).is_some());
+        assert!(raycast(&chunk, origin, Dir3::X, 10.0, Some(&shapes)).is_none());
+
+        // hit the top of the slab
+        let hit = raycast(
+            &chunk,
+            Vec3::new(0.5, 3.0, 0.5),
+            Dir3::NEG_Y,
+            10.0,
+            Some(&shapes),
+        )
+        .unwrap();
+        assert_eq!(hit.face, BlockFace::Up);
+        assert_eq!(hit.distance, 2.5);
+        assert_eq!(hit.point, Vec3::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn test_chunk_view() {
+        let mut first = Chunk::default();
+        first.data.insert(
+            IVec3::new(1, 2, 3),
+            BlockData {
+                id: BlockId::from_static("bevy_craft:block/stone"),
+            },
+        );
+        let mut second = Chunk::default();
+        second.data.insert(
+            IVec3::new(-1, 2, 3),
+            BlockData {
+                id: BlockId::from_static("bevy_craft:block/dirt"),
+            },
+        );
+
+        let view = [&first, &second].into_iter().collect::<ChunkView>();
+        assert!(view.get_block(IVec3::new(1, 2, 3)).is_some());
+        assert!(view.get_block(IVec3::new(-1, 2, 3)).is_some());
+        assert!(view.get_block(IVec3::new(-2, 2, 3)).is_none());
+        assert!(view.contains(IVec2::new(-1, 0)));
+        assert!(!view.contains(IVec2::new(1, 0)));
+
+        let hit = raycast(&view, Vec3::new(3.5, 2.5, 3.5), Dir3::NEG_X, 10.0, None).unwrap();
+        assert_eq!(hit.position, IVec3::new(1, 2, 3));
+    }
+}
@@ -0,0 +1,5 @@
+pub(crate) mod plugin;
+
+pub mod prelude {
+    pub use super::plugin::*;
+}
@@ -0,0 +1,142 @@
+use std::time::{Duration, Instant};
+
+use bevy::{
+    app::{PluginGroupBuilder, PluginsState},
+    log::LogPlugin,
+    prelude::*,
+    render::{mesh::MeshPlugin, texture::ImagePlugin},
+    state::app::StatesPlugin,
+};
+use derive_more::derive::{Display, Error};
+
+use crate::assets::prelude::*;
+
+pub const DEFAULT_LOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// load assets, resolve models, build the atlas and mesh chunks without a window or a renderer
+pub struct HeadlessPlugins;
+
+impl PluginGroup for HeadlessPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add_group(MinimalPlugins)
+            .add(LogPlugin::default())
+            .add(AssetPlugin::default())
+            .add(ImagePlugin::default_nearest())
+            .add(MeshPlugin)
+            .add(StatesPlugin)
+            .add(AppAssetPlugin)
+    }
+}
+
+#[derive(Debug, Error, Display, PartialEq, Eq)]
+pub enum HeadlessError {
+    #[display("assets are not loaded after {:?}, {:?}", _0, _1)]
+    Timeout(#[error(not(source))] Duration, LoadProgress),
+}
+
+/// drive an app built with `HeadlessPlugins` until AppLoadState::Next
+pub fn run_until_loaded(app: &mut App, timeout: Duration) -> Result<(), HeadlessError> {
+    if app.plugins_state() == PluginsState::Ready {
+        app.finish();
+        app.cleanup();
+    }
+
+    let start = Instant::now();
+    loop {
+        app.update();
+
+        let state = app.world().resource::<State<AppLoadState>>();
+        if *state.get() == AppLoadState::Next {
+            return Ok(());
+        }
+
+        if start.elapsed() > timeout {
+            let progress = app.world().resource::<LoadProgress>().clone();
+            return Err(HeadlessError::Timeout(timeout, progress));
+        }
+
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{chunks::prelude::*, identity::prelude::*, render::prelude::*, world::prelude::*};
+
+    use super::*;
+
+    #[test]
+    fn test_headless_pipeline() {
+        let mut app = App::new();
+        app.add_plugins(HeadlessPlugins.build().disable::<LogPlugin>());
+        run_until_loaded(&mut app, DEFAULT_LOAD_TIMEOUT).unwrap();
+
+        let progress = app.world().resource::<LoadProgress>();
+        assert!(progress.models.is_finished() && progress.models.total > 0);
+        assert!(progress.textures.is_finished() && progress.textures.total > 0);
+        assert!(progress.biomes.is_finished() && progress.biomes.total > 0);
+        assert_eq!(
+            app.world().resource::<Biomes>().len(),
+            progress.biomes.total
+        );
+        assert_eq!(progress.failed(), 0);
+
+        let stairs = BlockId::from_static("bevy_craft:block/cherry_stairs");
+        let models = app.world().resource::<ModelManager>();
+        assert!(models[stairs.location()].elements.is_some());
+        assert!(app.world().contains_resource::<BlockDefinitions>());
+        assert_eq!(
+            app.world().resource::<VoxelShapes>()[&stairs].boxes().len(),
+            models[stairs.location()].elements.as_ref().unwrap().len()
+        );
+
+        let atlas = app.world().resource::<AppTextureAtlas<TextureId>>();
+        assert!(atlas
+            .uv(TextureId::try_from("bevy_craft:block/cherry_planks").unwrap())
+            .is_some());
+        let entries = atlas.entries();
+        assert_eq!(entries.len(), progress.textures.total);
+        assert_eq!(
+            entries["bevy_craft:block/cherry_planks"].rect.size(),
+            UVec2::splat(16)
+        );
+
+        let mut chunk = Chunk::default();
+        chunk.data.insert(IVec3::ZERO, BlockData { id: stairs });
+        let mesh = chunk.mesh(&MeshContext {
+            atlas,
+            models,
+            shapes: app.world().resource::<VoxelShapes>(),
+            definitions: app.world().get_resource::<BlockDefinitions>(),
+        });
+        assert!(mesh.count_vertices() > 0);
+    }
+
+    #[test]
+    fn test_custom_asset_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let models = dir.path().join("mymod/models/block");
+        std::fs::create_dir_all(&models).unwrap();
+        std::fs::write(models.join("plain.json"), "{}").unwrap();
+
+        let mut app = App::new();
+        app.add_plugins(
+            HeadlessPlugins
+                .build()
+                .disable::<LogPlugin>()
+                .set(AssetPlugin {
+                    file_path: dir.path().display().to_string(),
+                    ..default()
+                }),
+        );
+        run_until_loaded(&mut app, DEFAULT_LOAD_TIMEOUT).unwrap();
+
+        assert_eq!(app.world().resource::<AssetRoot>().0, dir.path());
+        let progress = app.world().resource::<LoadProgress>();
+        assert_eq!(progress.models.total, 1);
+        assert_eq!(progress.failed(), 0);
+        let plain = ModelId::try_from("mymod:block/plain").unwrap();
+        assert!(app.world().resource::<ModelManager>().contains_key(&plain));
+    }
+}
@@ -0,0 +1,76 @@
+use std::{path::Path, str::FromStr};
+
+use derive_more::derive::Display;
+
+use crate::identity::prelude::*;
+use crate::identity::*;
+
+/// biome id, such as bevy_craft:plains
+/// a biome_id can be Into a path namespace/biomes/name.json
+#[derive(Display, Debug, Hash, PartialEq, Eq, Clone, PartialOrd, Ord)]
+#[display("{}", _0)]
+pub struct BiomeId(ResourceLocation);
+
+impl Identity for BiomeId {
+    const DIR: &str = "biomes";
+
+    const EXTENSION: &str = "json";
+
+    fn location(&self) -> &ResourceLocation {
+        &self.0
+    }
+}
+
+impl IdentityExtra for BiomeId {
+    const _DIR: &str = "/biomes/";
+
+    const _EXTENSION: &str = ".json";
+}
+
+impl FromStr for BiomeId {
+    type Err = IdentityError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::parse_location(value)
+            .map(Self)
+            .map_err(|_| IdentityError::BiomeIdError(value.to_string()))
+    }
+}
+
+impl TryFrom<&Path> for BiomeId {
+    type Error = IdentityError;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let path = path.to_str().ok_or(IdentityError::BiomeIdError(
+            path.to_string_lossy().to_string(),
+        ))?;
+
+        path.parse()
+    }
+}
+
+impl TryFrom<&str> for BiomeId {
+    type Error = IdentityError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let biome_id = "bevy_craft/biomes/plains.json".parse::<BiomeId>();
+        assert_eq!(biome_id, Ok(BiomeId("bevy_craft:plains".parse().unwrap())));
+
+        let biome_id = "plains".parse::<BiomeId>();
+        assert_eq!(biome_id, Ok(BiomeId("bevy_craft:plains".parse().unwrap())));
+
+        let biome_id = BiomeId("mymod:frozen/peaks".parse().unwrap());
+        assert_eq!(biome_id.path(), "mymod/biomes/frozen/peaks.json");
+        assert_eq!(biome_id.path().parse::<BiomeId>(), Ok(biome_id));
+    }
+}
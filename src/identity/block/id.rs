@@ -1,24 +1,31 @@
 use std::{path::Path, str::FromStr};
 
-use bevy::prelude::*;
-use derive_more::derive::{Display, From};
+use derive_more::derive::Display;
 
-use crate::assets::prelude::*;
+use crate::identity::prelude::*;
 use crate::identity::*;
 
 /// block id
 /// a block_id must be namespace:block/name
-/// a block_id can be Into a path namespace/models/block/name.json
-#[derive(Display, Debug, Hash, PartialEq, Eq, Clone, From)]
+/// a block_id can be Into a path namespace/models/block/name.json, the model of the same id
+#[derive(Display, Debug, Hash, PartialEq, Eq, Clone)]
 #[display("{}", _0)]
-pub struct BlockId(pub String);
+pub struct BlockId(ResourceLocation);
+
+impl BlockId {
+    /// the id of a literal known to be valid, such as `bevy_craft:block/dirt`,
+    /// panic if it is not a block id
+    pub fn from_static(value: &'static str) -> Self {
+        value.parse().unwrap_or_else(|err| panic!("{}", err))
+    }
+}
 
 impl Identity for BlockId {
     const DIR: &str = "models";
 
     const EXTENSION: &str = "json";
 
-    fn id(&self) -> &str {
+    fn location(&self) -> &ResourceLocation {
         &self.0
     }
 }
@@ -27,42 +34,17 @@ impl IdentityExtra for BlockId {
     const _DIR: &str = "/models/";
 
     const _EXTENSION: &str = ".json";
+
+    const CATEGORY: Option<&str> = Some("block");
 }
 
 impl FromStr for BlockId {
     type Err = IdentityError;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
-        let (ns, remainder) = if let Some((ns, value)) = value.split_once(IDENTITY_DELIMITER) {
-            (ns, value)
-        } else if let Some((ns, value)) = value.split_once(Self::_DIR) {
-            (ns, value)
-        } else {
-            (DEFAULT_NAMESPACE, value)
-        };
-
-        if !remainder.starts_with("block") {
-            return Err(IdentityError::BlockIdError(value.to_string()));
-        }
-
-        let value = remainder
-            .strip_suffix(Self::_EXTENSION)
-            .unwrap_or(remainder);
-
-        Ok(Self(format!("{}:{}", ns, value)))
-    }
-}
-
-impl TryFrom<&Handle<Model>> for BlockId {
-    type Error = IdentityError;
-
-    fn try_from(handle: &Handle<Model>) -> Result<Self, Self::Error> {
-        let path = handle
-            .path()
-            .ok_or(IdentityError::BlockIdError(handle.id().to_string()))?
-            .path();
-
-        BlockId::try_from(path)
+        Self::parse_location(value)
+            .map(Self)
+            .map_err(|_| IdentityError::BlockIdError(value.to_string()))
     }
 }
 
@@ -86,45 +68,91 @@ impl TryFrom<&str> for BlockId {
     }
 }
 
+impl TryFrom<&ModelId> for BlockId {
+    type Error = IdentityError;
+
+    fn try_from(model_id: &ModelId) -> Result<Self, Self::Error> {
+        match model_id.category() {
+            "block" => Ok(Self(model_id.location().clone())),
+            _ => Err(IdentityError::BlockIdError(model_id.to_string())),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
 
     use super::*;
 
+    fn block(value: &str) -> BlockId {
+        BlockId(value.parse().unwrap())
+    }
+
     #[test]
     fn test_parse() {
         let block_id = "bevy_craft/models/block/cube.json".parse::<BlockId>();
-        assert!(block_id.is_ok());
-        assert_eq!(
-            block_id.unwrap(),
-            BlockId(String::from("bevy_craft:block/cube"))
-        );
+        assert_eq!(block_id, Ok(block("bevy_craft:block/cube")));
 
         let block_id = "bevy_craft:block/cube".parse::<BlockId>();
-        assert!(block_id.is_ok());
-        assert_eq!(
-            block_id.unwrap(),
-            BlockId(String::from("bevy_craft:block/cube"))
-        );
+        assert_eq!(block_id, Ok(block("bevy_craft:block/cube")));
 
         let block_id = "block/cube".parse::<BlockId>();
-        assert!(block_id.is_ok());
-        assert_eq!(
-            block_id.unwrap(),
-            BlockId(String::from("bevy_craft:block/cube"))
-        );
+        assert_eq!(block_id, Ok(block("bevy_craft:block/cube")));
 
         let block_id = "bevy_craft:models/block/cube.json".parse::<BlockId>();
-        assert!(block_id.is_err());
         assert_eq!(
-            block_id.unwrap_err(),
-            IdentityError::BlockIdError(String::from("bevy_craft:models/block/cube.json"))
+            block_id,
+            Err(IdentityError::BlockIdError(String::from(
+                "bevy_craft:models/block/cube.json"
+            )))
         );
     }
 
     #[test]
     fn test_path() {
-        let block_id = BlockId("bevy_craft:block/cube".to_string());
+        let block_id = block("bevy_craft:block/cube");
         assert_eq!(block_id.path(), "bevy_craft/models/block/cube.json")
     }
+
+    #[test]
+    fn test_category() {
+        for value in ["mymod/models/item/sword.json", "mymod:entity/pig", "stick"] {
+            assert_eq!(
+                value.parse::<BlockId>(),
+                Err(IdentityError::BlockIdError(value.to_string()))
+            );
+        }
+
+        let model_id = "mymod:item/sword".parse::<ModelId>().unwrap();
+        assert!(BlockId::try_from(&model_id).is_err());
+        let model_id = "mymod:block/slab".parse::<ModelId>().unwrap();
+        assert_eq!(BlockId::try_from(&model_id), Ok(block("mymod:block/slab")));
+        assert_eq!(ModelId::from(block("mymod:block/slab")), model_id);
+    }
+
+    #[test]
+    fn test_from_static() {
+        assert_eq!(
+            BlockId::from_static("bevy_craft:block/dirt"),
+            block("bevy_craft:block/dirt")
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_static_item() {
+        BlockId::from_static("bevy_craft:item/stick");
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_path_round_trip(
+            ns in "[a-z0-9_-]{1,16}",
+            name in "block(/[a-z0-9_-]{1,8}){1,3}",
+        ) {
+            let block_id = block(&format!("{}:{}", ns, name));
+            proptest::prop_assert_eq!(block_id.path().parse::<BlockId>(), Ok(block_id.clone()));
+            proptest::prop_assert_eq!(block_id.to_string().parse::<BlockId>(), Ok(block_id));
+        }
+    }
 }
@@ -0,0 +1,132 @@
+use std::str::FromStr;
+
+use derive_more::derive::Display;
+
+use crate::identity::*;
+
+/// a validated `namespace:path` pair
+/// namespace only contains `[a-z0-9_.-]`
+/// path only contains `[a-z0-9_.-/]`, and every segment split by `/` is not empty
+#[derive(Display, Debug, Hash, PartialEq, Eq, Clone, PartialOrd, Ord)]
+#[display("{}{}{}", namespace, IDENTITY_DELIMITER, path)]
+pub struct ResourceLocation {
+    namespace: String,
+    path: String,
+}
+
+impl ResourceLocation {
+    pub fn new(
+        namespace: impl Into<String>,
+        path: impl Into<String>,
+    ) -> Result<Self, IdentityError> {
+        let namespace = namespace.into();
+        let path = path.into();
+
+        if !Self::is_valid_namespace(&namespace) {
+            return Err(IdentityError::NamespaceError(namespace));
+        }
+
+        if !Self::is_valid_path(&path) {
+            return Err(IdentityError::PathError(path));
+        }
+
+        Ok(Self { namespace, path })
+    }
+
+    pub fn is_valid_namespace(namespace: &str) -> bool {
+        !namespace.is_empty()
+            && namespace
+                .chars()
+                .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_' | '.' | '-'))
+    }
+
+    pub fn is_valid_path(path: &str) -> bool {
+        !path.is_empty()
+            && path.split('/').all(|segment| !segment.is_empty())
+            && path
+                .chars()
+                .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_' | '.' | '-' | '/'))
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// the first segment of the path, such as `block`, `item` or `entity`
+    pub fn category(&self) -> &str {
+        self.path
+            .split_once('/')
+            .map(|(category, _)| category)
+            .unwrap_or(&self.path)
+    }
+}
+
+impl FromStr for ResourceLocation {
+    type Err = IdentityError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once(IDENTITY_DELIMITER) {
+            Some((ns, path)) => Self::new(ns, path),
+            None => Self::new(DEFAULT_NAMESPACE, value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let location = "mymod:item/sword".parse::<ResourceLocation>().unwrap();
+        assert_eq!(location.namespace(), "mymod");
+        assert_eq!(location.path(), "item/sword");
+        assert_eq!(location.category(), "item");
+
+        let location = "environment/sun".parse::<ResourceLocation>().unwrap();
+        assert_eq!(location.namespace(), DEFAULT_NAMESPACE);
+        assert_eq!(location.to_string(), "bevy_craft:environment/sun");
+
+        assert_eq!(
+            "MyMod:block/a".parse::<ResourceLocation>(),
+            Err(IdentityError::NamespaceError("MyMod".to_string()))
+        );
+        assert_eq!(
+            ":block/a".parse::<ResourceLocation>(),
+            Err(IdentityError::NamespaceError(String::new()))
+        );
+        assert_eq!(
+            "block//a".parse::<ResourceLocation>(),
+            Err(IdentityError::PathError("block//a".to_string()))
+        );
+        assert_eq!(
+            "#all".parse::<ResourceLocation>(),
+            Err(IdentityError::PathError("#all".to_string()))
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(
+            ns in "[a-z0-9_.-]{1,16}",
+            path in "[a-z0-9_.-]{1,8}(/[a-z0-9_.-]{1,8}){0,3}",
+        ) {
+            let location = ResourceLocation::new(ns.as_str(), path.as_str()).unwrap();
+            let parsed = location.to_string().parse::<ResourceLocation>().unwrap();
+            prop_assert_eq!(parsed.namespace(), ns.as_str());
+            prop_assert_eq!(parsed.path(), path.as_str());
+            prop_assert_eq!(parsed, location);
+        }
+
+        #[test]
+        fn test_reject_invalid(ns in "[a-z]*[A-Z:#/ ][a-z]*", path in "[a-z]{1,8}") {
+            prop_assert!(ResourceLocation::new(ns, path).is_err());
+        }
+    }
+}
@@ -2,9 +2,14 @@ use std::hash::Hash;
 
 use derive_more::derive::{Display, Error};
 
+pub(crate) mod biome_id;
 pub(crate) mod block;
+pub(crate) mod location;
+pub(crate) mod model_id;
 pub(crate) mod texture_id;
 
+use location::ResourceLocation;
+
 pub const IDENTITY_DELIMITER: char = ':';
 pub const DEFAULT_NAMESPACE: &str = "bevy_craft";
 
@@ -12,23 +17,22 @@ pub trait Identity: Eq + Hash {
     const DIR: &str;
     const EXTENSION: &str;
 
-    /// get the id such namespace:name
-    fn id(&self) -> &str;
+    /// get the validated namespace:name
+    fn location(&self) -> &ResourceLocation;
 
     /// get the namespace
     fn ns(&self) -> &str {
-        self.id()
-            .find(IDENTITY_DELIMITER)
-            .map(|pos| &self.id()[..pos])
-            .unwrap_or(DEFAULT_NAMESPACE)
+        self.location().namespace()
     }
 
     /// get name
     fn name(&self) -> &str {
-        self.id()
-            .find(IDENTITY_DELIMITER)
-            .map(|pos| &self.id()[pos + 1..])
-            .unwrap_or(self.id())
+        self.location().path()
+    }
+
+    /// get the first segment of name, such as `block`, `item` or `entity`
+    fn category(&self) -> &str {
+        self.location().category()
     }
 
     fn path(&self) -> String {
@@ -45,18 +49,57 @@ pub trait Identity: Eq + Hash {
 pub(crate) trait IdentityExtra: Identity {
     const _DIR: &str;
     const _EXTENSION: &str;
+    /// the category every name starts with, any category if `None`
+    const CATEGORY: Option<&str> = None;
+
+    /// parse both `namespace:name` and `namespace/DIR/name.EXTENSION`
+    fn parse_location(value: &str) -> Result<ResourceLocation, IdentityError> {
+        let (ns, remainder) = if let Some((ns, value)) = value.split_once(IDENTITY_DELIMITER) {
+            (ns, value)
+        } else if let Some((ns, value)) = value.split_once(Self::_DIR) {
+            (ns, value)
+        } else {
+            (DEFAULT_NAMESPACE, value)
+        };
+
+        let path = remainder
+            .strip_suffix(Self::_EXTENSION)
+            .unwrap_or(remainder);
+
+        let location = ResourceLocation::new(ns, path)?;
+        // `namespace:DIR/name` mixes the id with the file path,
+        // and `DIR` as the category would not survive `path` and parse back
+        if location.category() == Self::DIR
+            || Self::CATEGORY.is_some_and(|category| category != location.category())
+        {
+            return Err(IdentityError::PathError(value.to_string()));
+        }
+
+        Ok(location)
+    }
 }
 
 #[derive(Debug, Error, Display, PartialEq, Eq)]
 pub enum IdentityError {
     #[display("parse BlockId{{ {} }} error", _0)]
     BlockIdError(#[error(not(source))] String),
+    #[display("parse ModelId{{ {} }} error", _0)]
+    ModelIdError(#[error(not(source))] String),
     #[display("parse TextureId{{ {} }} error", _0)]
     TextureIdError(#[error(not(source))] String),
+    #[display("parse BiomeId{{ {} }} error", _0)]
+    BiomeIdError(#[error(not(source))] String),
+    #[display("invalid namespace {{ {} }}", _0)]
+    NamespaceError(#[error(not(source))] String),
+    #[display("invalid path {{ {} }}", _0)]
+    PathError(#[error(not(source))] String),
 }
 
 pub mod prelude {
+    pub use super::biome_id::*;
     pub use super::block::prelude::*;
+    pub use super::location::*;
+    pub use super::model_id::*;
     pub use super::texture_id::*;
     pub use super::{Identity, IdentityError};
 }
@@ -0,0 +1,130 @@
+use std::{borrow::Borrow, path::Path, str::FromStr};
+
+use bevy::prelude::*;
+use derive_more::derive::Display;
+
+use crate::assets::prelude::*;
+use crate::identity::prelude::*;
+use crate::identity::*;
+
+/// model id
+/// a model_id must be namespace:category/name, such as bevy_craft:block/dirt or bevy_craft:item/stick
+/// a model_id can be Into a path namespace/models/category/name.json
+#[derive(Display, Debug, Hash, PartialEq, Eq, Clone)]
+#[display("{}", _0)]
+pub struct ModelId(ResourceLocation);
+
+impl Identity for ModelId {
+    const DIR: &str = "models";
+
+    const EXTENSION: &str = "json";
+
+    fn location(&self) -> &ResourceLocation {
+        &self.0
+    }
+}
+
+impl IdentityExtra for ModelId {
+    const _DIR: &str = "/models/";
+
+    const _EXTENSION: &str = ".json";
+}
+
+/// look up the models by the location of a `BlockId`
+impl Borrow<ResourceLocation> for ModelId {
+    fn borrow(&self) -> &ResourceLocation {
+        &self.0
+    }
+}
+
+impl FromStr for ModelId {
+    type Err = IdentityError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::parse_location(value)
+            .map(Self)
+            .map_err(|_| IdentityError::ModelIdError(value.to_string()))
+    }
+}
+
+impl TryFrom<&Handle<Model>> for ModelId {
+    type Error = IdentityError;
+
+    fn try_from(handle: &Handle<Model>) -> Result<Self, Self::Error> {
+        let path = handle
+            .path()
+            .ok_or(IdentityError::ModelIdError(handle.id().to_string()))?
+            .path();
+
+        ModelId::try_from(path)
+    }
+}
+
+impl TryFrom<&Path> for ModelId {
+    type Error = IdentityError;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let path = path.to_str().ok_or(IdentityError::ModelIdError(
+            path.to_string_lossy().to_string(),
+        ))?;
+
+        path.parse()
+    }
+}
+
+impl TryFrom<&str> for ModelId {
+    type Error = IdentityError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<BlockId> for ModelId {
+    fn from(block_id: BlockId) -> Self {
+        Self(block_id.location().clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_category() {
+        let model_id = "mymod/models/item/sword.json".parse::<ModelId>().unwrap();
+        assert_eq!(model_id.to_string(), "mymod:item/sword");
+        assert_eq!(model_id.ns(), "mymod");
+        assert_eq!(model_id.category(), "item");
+        assert_eq!(model_id.path(), "mymod/models/item/sword.json");
+
+        assert_eq!(
+            "bevy_craft:models/block/cube.json".parse::<ModelId>(),
+            Err(IdentityError::ModelIdError(
+                "bevy_craft:models/block/cube.json".to_string()
+            ))
+        );
+        assert!("bevy_craft/models/models/cube.json"
+            .parse::<ModelId>()
+            .is_err());
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_path_round_trip(
+            ns in "[a-z0-9_-]{1,16}",
+            name in "(models|[a-z0-9_-]{1,8})(/[a-z0-9_-]{1,8}){0,3}",
+        ) {
+            let value = format!("{}:{}", ns, name);
+            if name.split('/').next() == Some(ModelId::DIR) {
+                proptest::prop_assert_eq!(value.parse::<ModelId>(), Err(IdentityError::ModelIdError(value.clone())));
+                let path = format!("{}/models/{}.json", ns, name);
+                proptest::prop_assert!(path.parse::<ModelId>().is_err());
+            } else {
+                let model_id = value.parse::<ModelId>().unwrap();
+                proptest::prop_assert_eq!(model_id.path().parse::<ModelId>(), Ok(model_id.clone()));
+                proptest::prop_assert_eq!(model_id.to_string().parse::<ModelId>(), Ok(model_id));
+            }
+        }
+    }
+}
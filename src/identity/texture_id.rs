@@ -1,21 +1,22 @@
 use std::{path::Path, str::FromStr};
 
 use bevy::prelude::*;
-use derive_more::derive::{Display, From};
+use derive_more::derive::Display;
 
 use crate::assets::prelude::*;
+use crate::identity::prelude::*;
 use crate::identity::*;
 
-#[derive(Display, Debug, Hash, PartialEq, Eq, Clone, From)]
+#[derive(Display, Debug, Hash, PartialEq, Eq, Clone)]
 #[display("{}", _0)]
-pub struct TextureId(pub String);
+pub struct TextureId(ResourceLocation);
 
 impl Identity for TextureId {
     const DIR: &str = "textures";
 
     const EXTENSION: &str = "png";
 
-    fn id(&self) -> &str {
+    fn location(&self) -> &ResourceLocation {
         &self.0
     }
 }
@@ -30,23 +31,9 @@ impl FromStr for TextureId {
     type Err = IdentityError;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
-        let (ns, remainder) = if let Some((ns, value)) = value.split_once(IDENTITY_DELIMITER) {
-            (ns, value)
-        } else if let Some((ns, value)) = value.split_once(Self::_DIR) {
-            (ns, value)
-        } else {
-            (DEFAULT_NAMESPACE, value)
-        };
-
-        if !remainder.starts_with("block") {
-            return Err(IdentityError::TextureIdError(value.to_string()));
-        }
-
-        let value = remainder
-            .strip_suffix(Self::_EXTENSION)
-            .unwrap_or(remainder);
-
-        Ok(Self(format!("{}:{}", ns, value)))
+        Self::parse_location(value)
+            .map(Self)
+            .map_err(|_| IdentityError::TextureIdError(value.to_string()))
     }
 }
 
@@ -105,21 +92,21 @@ mod test {
         assert!(block_id.is_ok());
         assert_eq!(
             block_id.unwrap(),
-            TextureId(String::from("bevy_craft:block/cube"))
+            TextureId("bevy_craft:block/cube".parse().unwrap())
         );
 
         let block_id = "bevy_craft:block/cube".parse::<TextureId>();
         assert!(block_id.is_ok());
         assert_eq!(
             block_id.unwrap(),
-            TextureId(String::from("bevy_craft:block/cube"))
+            TextureId("bevy_craft:block/cube".parse().unwrap())
         );
 
         let block_id = "block/cube".parse::<TextureId>();
         assert!(block_id.is_ok());
         assert_eq!(
             block_id.unwrap(),
-            TextureId(String::from("bevy_craft:block/cube"))
+            TextureId("bevy_craft:block/cube".parse().unwrap())
         );
 
         let block_id = "bevy_craft:textures/block/cube.png".parse::<TextureId>();
@@ -133,7 +120,7 @@ mod test {
         assert!(block_id.is_ok());
         assert_eq!(
             block_id.unwrap(),
-            TextureId(String::from("bevy_craft:block/dirt"))
+            TextureId("bevy_craft:block/dirt".parse().unwrap())
         );
     }
 
@@ -144,7 +131,7 @@ mod test {
         assert!(texture_id.is_ok());
         assert_eq!(
             texture_id.unwrap(),
-            TextureId("bevy_craft:block/dirt".to_string())
+            TextureId("bevy_craft:block/dirt".parse().unwrap())
         );
 
         let texture = Texture("#all".to_string());
@@ -166,7 +153,49 @@ mod test {
 
     #[test]
     fn test_path() {
-        let block_id = TextureId("bevy_craft:block/cube".to_string());
+        let block_id = TextureId("bevy_craft:block/cube".parse().unwrap());
         assert_eq!(block_id.path(), "bevy_craft/textures/block/cube.png")
     }
+
+    #[test]
+    fn test_category() {
+        for (value, expected, category) in [
+            ("item/stick", "bevy_craft:item/stick", "item"),
+            ("mymod:entity/pig/cold", "mymod:entity/pig/cold", "entity"),
+            (
+                "bevy_craft/textures/environment/sun.png",
+                "bevy_craft:environment/sun",
+                "environment",
+            ),
+        ] {
+            let texture_id = value.parse::<TextureId>().unwrap();
+            assert_eq!(texture_id, TextureId(expected.parse().unwrap()));
+            assert_eq!(texture_id.category(), category);
+        }
+
+        assert_eq!(
+            "Bad:block/dirt".parse::<TextureId>(),
+            Err(IdentityError::TextureIdError("Bad:block/dirt".to_string()))
+        );
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_path_round_trip(
+            ns in "[a-z0-9_-]{1,16}",
+            name in "(textures|[a-z0-9_-]{1,8})(/[a-z0-9_-]{1,8}){0,3}",
+        ) {
+            let value = format!("{}:{}", ns, name);
+            if name.split('/').next() == Some(TextureId::DIR) {
+                // `ns:textures/..` is the file path mixed in the id, so reject it in both forms
+                proptest::prop_assert_eq!(value.parse::<TextureId>(), Err(IdentityError::TextureIdError(value.clone())));
+                let path = format!("{}/textures/{}.png", ns, name);
+                proptest::prop_assert!(path.parse::<TextureId>().is_err());
+            } else {
+                let texture_id = value.parse::<TextureId>().unwrap();
+                proptest::prop_assert_eq!(texture_id.path().parse::<TextureId>(), Ok(texture_id.clone()));
+                proptest::prop_assert_eq!(texture_id.to_string().parse::<TextureId>(), Ok(texture_id));
+            }
+        }
+    }
 }
@@ -0,0 +1,345 @@
+use bevy::{ecs::system::SystemParam, math::bounding::Aabb3d, prelude::*, window::PrimaryWindow};
+
+use crate::{
+    assets::prelude::*, chunks::prelude::*, identity::prelude::*, player::prelude::*,
+    render::prelude::*, world::prelude::*,
+};
+
+/// how far away blocks can be broken or placed
+pub const REACH_DISTANCE: f32 = 5.0;
+
+/// the block under the cursor, updated every frame
+#[derive(Resource, Default, Debug)]
+pub struct BlockTarget {
+    pub hit: Option<RaycastHit>,
+}
+
+/// the block placed by the right click
+#[derive(Resource, Debug)]
+pub struct HeldBlock(pub BlockId);
+
+impl Default for HeldBlock {
+    fn default() -> Self {
+        Self(BlockId::from_static("bevy_craft:block/dirt"))
+    }
+}
+
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct BlockPlaced {
+    pub chunk: Entity,
+    pub position: IVec3,
+    /// face of the block which it is placed against
+    pub face: BlockFace,
+    pub block_id: BlockId,
+}
+
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct BlockBroken {
+    pub chunk: Entity,
+    pub position: IVec3,
+    pub block_id: BlockId,
+}
+
+/// run Update in AppLoadState::Next, cast a ray from the cursor,
+/// or from the center of the screen when the cursor is hidden
+pub fn update_target(
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    window: Option<Single<&Window, With<PrimaryWindow>>>,
+    chunks: Query<&Chunk>,
+    shapes: Res<VoxelShapes>,
+    mut target: ResMut<BlockTarget>,
+) {
+    let Some((camera, transform)) = cameras.iter().find(|(camera, _)| camera.is_active) else {
+        return;
+    };
+    let ray = window
+        .filter(|window| window.cursor_options.visible)
+        .and_then(|window| window.cursor_position())
+        .and_then(|cursor| camera.viewport_to_world(transform, cursor).ok())
+        .unwrap_or_else(|| Ray3d::new(transform.translation(), transform.forward()));
+
+    let view = chunks.iter().collect::<ChunkView>();
+    target.hit = raycast(
+        &view,
+        ray.origin,
+        ray.direction,
+        REACH_DISTANCE,
+        Some(&shapes),
+    );
+}
+
+/// run Update in AppLoadState::Next, break or place the block at `BlockTarget`
+pub fn edit_blocks(
+    mouse: Res<ButtonInput<MouseButton>>,
+    target: Res<BlockTarget>,
+    held: Res<HeldBlock>,
+    context: BlockEditContext,
+    mut chunks: LoadedChunksMut,
+    mut placed: EventWriter<BlockPlaced>,
+    mut broken: EventWriter<BlockBroken>,
+) {
+    let Some(hit) = &target.hit else {
+        return;
+    };
+
+    if mouse.just_pressed(MouseButton::Left) {
+        let Some((entity, mut chunk)) = chunks.get_mut(hit.position) else {
+            return;
+        };
+        if let Some(block_data) = chunk.data.remove(&hit.position) {
+            chunk.update_light(hit.position, context.definitions());
+            broken.write(BlockBroken {
+                chunk: entity,
+                position: hit.position,
+                block_id: block_data.id,
+            });
+        }
+    } else if mouse.just_pressed(MouseButton::Right) {
+        let position = hit.adjacent();
+        if context.overlaps_player(position, &held.0) {
+            return;
+        }
+        let Some((entity, mut chunk)) = chunks.get_mut(position) else {
+            return;
+        };
+        if chunk.data.contains_key(&position) {
+            return;
+        }
+
+        chunk
+            .data
+            .insert(position, BlockData { id: held.0.clone() });
+        chunk.update_light(position, context.definitions());
+        placed.write(BlockPlaced {
+            chunk: entity,
+            position,
+            face: hit.face,
+            block_id: held.0.clone(),
+        });
+    }
+}
+
+/// the loaded chunks looked up by `LoadedChunks`
+#[derive(SystemParam)]
+pub struct LoadedChunksMut<'w, 's> {
+    loaded: Res<'w, LoadedChunks>,
+    chunks: Query<'w, 's, &'static mut Chunk>,
+}
+
+impl LoadedChunksMut<'_, '_> {
+    /// the chunk containing the block at `pos`
+    pub fn get_mut(&mut self, pos: IVec3) -> Option<(Entity, Mut<'_, Chunk>)> {
+        let entity = *self.loaded.get(&chunk_position(pos))?;
+        let chunk = self.chunks.get_mut(entity).ok()?;
+        Some((entity, chunk))
+    }
+}
+
+/// the definitions to relight the chunk, and the shapes and the players
+/// which a placed block must not trap
+#[derive(SystemParam)]
+pub struct BlockEditContext<'w, 's> {
+    definitions: Option<Res<'w, BlockDefinitions>>,
+    shapes: Option<Res<'w, VoxelShapes>>,
+    players: Query<'w, 's, &'static Transform, With<Player>>,
+    settings: Option<Res<'w, PlayerSettings>>,
+}
+
+impl BlockEditContext<'_, '_> {
+    pub fn definitions(&self) -> Option<&BlockDefinitions> {
+        self.definitions.as_deref()
+    }
+
+    /// whether `block_id` placed at `pos` would overlap a player, touching is not overlapping
+    pub fn overlaps_player(&self, pos: IVec3, block_id: &BlockId) -> bool {
+        let solid = self
+            .definitions()
+            .and_then(|definitions| definitions.get(block_id))
+            .is_none_or(|definition| definition.solid);
+        if !solid {
+            return false;
+        }
+
+        let boxes = match self.shapes.as_ref().and_then(|shapes| shapes.get(block_id)) {
+            Some(shape) => shape.aabbs(pos),
+            None => VoxelShape::full().aabbs(pos),
+        };
+        let settings = self.settings.as_deref().cloned().unwrap_or_default();
+        self.players.iter().any(|transform| {
+            let player = Player::aabb(transform.translation, &settings);
+            boxes.iter().any(|aabb| overlaps(aabb, &player))
+        })
+    }
+}
+
+fn overlaps(a: &Aabb3d, b: &Aabb3d) -> bool {
+    (a.min.cmplt(b.max) & b.min.cmplt(a.max)).all()
+}
+
+/// run Update in AppLoadState::Next, rebuild the mesh of every modified chunk
+pub fn remesh_chunks(
+    chunks: Query<(&Chunk, &Mesh3d), Changed<Chunk>>,
+    meshing: ChunkMeshing,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let context = meshing.context();
+    for (chunk, mesh) in &chunks {
+        meshes.insert(&mesh.0, chunk.mesh(&context));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::ecs::event::Events;
+
+    use super::*;
+
+    fn hit(position: IVec3, face: BlockFace) -> RaycastHit {
+        RaycastHit {
+            position,
+            face,
+            distance: 1.0,
+            point: position.as_vec3(),
+            block_id: BlockId::from_static("bevy_craft:block/stone"),
+        }
+    }
+
+    #[test]
+    fn test_edit_blocks() {
+        let stone = BlockId::from_static("bevy_craft:block/stone");
+        let mut chunk = Chunk::default();
+        chunk
+            .data
+            .insert(IVec3::ZERO, BlockData { id: stone.clone() });
+        chunk
+            .data
+            .insert(IVec3::new(1, 0, 0), BlockData { id: stone.clone() });
+
+        let mut app = App::new();
+        app.add_event::<BlockPlaced>()
+            .add_event::<BlockBroken>()
+            .init_resource::<HeldBlock>()
+            .init_resource::<LoadedChunks>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .insert_resource(BlockTarget {
+                hit: Some(hit(IVec3::new(1, 0, 0), BlockFace::Up)),
+            })
+            .add_systems(Update, edit_blocks);
+        let entity = app.world_mut().spawn(chunk).id();
+        app.world_mut()
+            .resource_mut::<LoadedChunks>()
+            .insert(IVec2::ZERO, entity);
+
+        app.world_mut()
+            .resource_mut::<ButtonInput<MouseButton>>()
+            .press(MouseButton::Left);
+        app.update();
+
+        let broken = app
+            .world_mut()
+            .resource_mut::<Events<BlockBroken>>()
+            .drain()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            broken,
+            vec![BlockBroken {
+                chunk: entity,
+                position: IVec3::new(1, 0, 0),
+                block_id: stone.clone(),
+            }]
+        );
+        let chunk = app.world().get::<Chunk>(entity).unwrap();
+        assert!(!chunk.data.contains_key(&IVec3::new(1, 0, 0)));
+
+        app.world_mut().resource_mut::<BlockTarget>().hit = Some(hit(IVec3::ZERO, BlockFace::Up));
+        let mut mouse = app.world_mut().resource_mut::<ButtonInput<MouseButton>>();
+        mouse.release_all();
+        mouse.clear();
+        mouse.press(MouseButton::Right);
+        app.update();
+
+        let placed = app
+            .world_mut()
+            .resource_mut::<Events<BlockPlaced>>()
+            .drain()
+            .collect::<Vec<_>>();
+        let dirt = HeldBlock::default().0;
+        assert_eq!(
+            placed,
+            vec![BlockPlaced {
+                chunk: entity,
+                position: IVec3::Y,
+                face: BlockFace::Up,
+                block_id: dirt.clone(),
+            }]
+        );
+        let chunk = app.world().get::<Chunk>(entity).unwrap();
+        assert_eq!(chunk.data[&IVec3::Y].id, dirt);
+
+        // the chunk is still found once it is empty
+        app.world_mut()
+            .get_mut::<Chunk>(entity)
+            .unwrap()
+            .data
+            .clear();
+        app.world_mut().resource_mut::<BlockTarget>().hit = Some(hit(IVec3::NEG_Y, BlockFace::Up));
+        let mut mouse = app.world_mut().resource_mut::<ButtonInput<MouseButton>>();
+        mouse.release_all();
+        mouse.clear();
+        mouse.press(MouseButton::Right);
+        app.update();
+        let chunk = app.world().get::<Chunk>(entity).unwrap();
+        assert_eq!(chunk.data[&IVec3::ZERO].id, dirt);
+    }
+
+    #[test]
+    fn test_place_inside_player() {
+        let stone = BlockId::from_static("bevy_craft:block/stone");
+        let mut chunk = Chunk::default();
+        chunk.data.insert(IVec3::ZERO, BlockData { id: stone });
+
+        let mut app = App::new();
+        app.add_event::<BlockPlaced>()
+            .add_event::<BlockBroken>()
+            .init_resource::<HeldBlock>()
+            .init_resource::<LoadedChunks>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .insert_resource(BlockTarget {
+                hit: Some(hit(IVec3::ZERO, BlockFace::Up)),
+            })
+            .add_systems(Update, edit_blocks);
+        let entity = app.world_mut().spawn(chunk).id();
+        app.world_mut()
+            .resource_mut::<LoadedChunks>()
+            .insert(IVec2::ZERO, entity);
+        // standing on the stone, where the block would be placed
+        let player = app
+            .world_mut()
+            .spawn((Player::default(), Transform::from_xyz(0.5, 1.0, 0.5)))
+            .id();
+
+        app.world_mut()
+            .resource_mut::<ButtonInput<MouseButton>>()
+            .press(MouseButton::Right);
+        app.update();
+
+        assert!(app.world().resource::<Events<BlockPlaced>>().is_empty());
+        let chunk = app.world().get::<Chunk>(entity).unwrap();
+        assert!(!chunk.data.contains_key(&IVec3::Y));
+
+        // once the player stepped aside
+        app.world_mut()
+            .get_mut::<Transform>(player)
+            .unwrap()
+            .translation = Vec3::new(1.5, 1.0, 0.5);
+        let mut mouse = app.world_mut().resource_mut::<ButtonInput<MouseButton>>();
+        mouse.release_all();
+        mouse.clear();
+        mouse.press(MouseButton::Right);
+        app.update();
+
+        assert_eq!(app.world().resource::<Events<BlockPlaced>>().len(), 1);
+        let chunk = app.world().get::<Chunk>(entity).unwrap();
+        assert!(chunk.data.contains_key(&IVec3::Y));
+    }
+}
@@ -0,0 +1,9 @@
+pub(crate) mod edit;
+pub(crate) mod outline;
+pub(crate) mod plugin;
+
+pub mod prelude {
+    pub use super::edit::*;
+    pub use super::outline::*;
+    pub use super::plugin::*;
+}
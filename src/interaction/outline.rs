@@ -0,0 +1,96 @@
+use bevy::{
+    math::bounding::{Aabb3d, BoundingVolume},
+    prelude::*,
+};
+
+use crate::{assets::prelude::*, interaction::prelude::*};
+
+/// grow the outline a little so that it is not hidden by the faces of the block
+const OUTLINE_OFFSET: f32 = 0.002;
+
+/// the outline drawn around `BlockTarget`, toggled with F1
+#[derive(Resource, Debug)]
+pub struct SelectionOutline {
+    pub enabled: bool,
+    pub color: Color,
+}
+
+impl Default for SelectionOutline {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            color: Color::BLACK,
+        }
+    }
+}
+
+/// the boxes of `shape` for the block at `pos`, a full cube if the shape is unknown or empty
+pub fn outline_boxes(shape: Option<&VoxelShape>, pos: IVec3) -> Vec<Aabb3d> {
+    match shape {
+        Some(shape) if !shape.is_empty() => shape.aabbs(pos),
+        _ => VoxelShape::full().aabbs(pos),
+    }
+}
+
+/// run Update in AppLoadState::Next
+pub fn toggle_outline(keys: Res<ButtonInput<KeyCode>>, mut outline: ResMut<SelectionOutline>) {
+    if keys.just_pressed(KeyCode::F1) {
+        outline.enabled = !outline.enabled;
+    }
+}
+
+/// run Update in AppLoadState::Next, after `update_target`
+pub fn draw_outline(
+    mut gizmos: Gizmos,
+    outline: Res<SelectionOutline>,
+    target: Res<BlockTarget>,
+    shapes: Res<VoxelShapes>,
+) {
+    if !outline.enabled {
+        return;
+    }
+    let Some(hit) = &target.hit else {
+        return;
+    };
+
+    for aabb in outline_boxes(shapes.get(&hit.block_id), hit.position) {
+        let size = Vec3::from(aabb.max - aabb.min) + Vec3::splat(OUTLINE_OFFSET * 2.0);
+        gizmos.cuboid(
+            Transform::from_translation(aabb.center().into()).with_scale(size),
+            outline.color,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn aabb(min: Vec3, max: Vec3) -> Aabb3d {
+        Aabb3d {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+
+    #[test]
+    fn test_outline_boxes() {
+        let stairs = VoxelShape::new([
+            ShapeBox::new([0, 0, 0], [16, 8, 16]),
+            ShapeBox::new([8, 8, 0], [16, 16, 16]),
+        ]);
+
+        let boxes = outline_boxes(Some(&stairs), IVec3::new(1, 2, 3));
+        assert_eq!(
+            boxes,
+            vec![
+                aabb(Vec3::new(1.0, 2.0, 3.0), Vec3::new(2.0, 2.5, 4.0)),
+                aabb(Vec3::new(1.5, 2.5, 3.0), Vec3::new(2.0, 3.0, 4.0)),
+            ]
+        );
+
+        let cube = vec![aabb(Vec3::ZERO, Vec3::ONE)];
+        assert_eq!(outline_boxes(None, IVec3::ZERO), cube);
+        assert_eq!(outline_boxes(Some(&VoxelShape::empty()), IVec3::ZERO), cube);
+    }
+}
@@ -0,0 +1,35 @@
+use bevy::prelude::*;
+
+use crate::{assets::prelude::*, interaction::prelude::*, world::prelude::*};
+
+/// break blocks with the left click and place `HeldBlock` with the right click
+pub struct BlockInteractionPlugin;
+
+impl Plugin for BlockInteractionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BlockPlaced>()
+            .add_event::<BlockBroken>()
+            .init_resource::<BlockTarget>()
+            .init_resource::<HeldBlock>()
+            .init_resource::<LoadedChunks>()
+            .add_systems(
+                Update,
+                (update_target, edit_blocks, remesh_chunks)
+                    .chain()
+                    .run_if(in_state(AppLoadState::Next)),
+            );
+    }
+}
+
+/// draw the outline of the targeted block, requires `BlockInteractionPlugin`
+pub struct SelectionOutlinePlugin;
+
+impl Plugin for SelectionOutlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectionOutline>().add_systems(
+            Update,
+            (toggle_outline, draw_outline.after(update_target))
+                .run_if(in_state(AppLoadState::Next)),
+        );
+    }
+}
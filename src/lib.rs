@@ -1,4 +1,10 @@
 pub mod assets;
+pub mod camera;
 pub mod chunks;
+pub mod headless;
 pub mod identity;
+pub mod interaction;
+pub mod player;
 pub mod render;
+pub mod ui;
+pub mod world;
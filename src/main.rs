@@ -1,5 +1,8 @@
+use std::{path::PathBuf, sync::Arc};
+
 use bevy::{
     pbr::wireframe::{WireframeConfig, WireframePlugin},
+    platform::collections::HashMap,
     prelude::*,
     render::{
         settings::{Backends, PowerPreference, RenderCreation, WgpuSettings},
@@ -7,10 +10,128 @@ use bevy::{
     },
 };
 
-use bevy_craft::{assets::prelude::*, chunks::prelude::*, identity::prelude::*};
+use bevy_craft::{
+    assets::prelude::*, camera::prelude::*, chunks::prelude::*, headless::prelude::*,
+    identity::prelude::*, interaction::prelude::*, player::prelude::*, render::prelude::*,
+    ui::prelude::*, world::prelude::*,
+};
+
+/// world opened when `--world` is not given
+const DEFAULT_WORLD: &str = "world";
+
+/// command line flags of the main binary
+#[derive(Default)]
+struct Args {
+    /// export the demo chunk to `.obj` or `.glb` instead of opening a window
+    export: Option<PathBuf>,
+    /// export these chunks of the world instead of the demo chunk, both corners are included
+    export_chunks: Option<(IVec2, IVec2)>,
+    /// dump the atlas and the rect of each texture into a directory instead of opening a window
+    dump_atlas: Option<PathBuf>,
+    /// directory of the worlds, `saves` by default
+    saves: Option<PathBuf>,
+    /// name of the world to play, it is created if it does not exist
+    world: Option<String>,
+    /// create the world, fail if it exists
+    new_world: bool,
+    /// seed of a created world, random by default
+    seed: Option<u32>,
+    list_worlds: bool,
+    rename_world: Option<(String, String)>,
+    delete_world: Option<String>,
+}
+
+impl Args {
+    fn parse() -> Self {
+        let mut result = Args::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--export" => result.export = Some(PathBuf::from(Self::value(&arg, args.next()))),
+                "--export-chunks" => {
+                    let value = Self::value(&arg, args.next());
+                    let range = Self::chunk_range(&value)
+                        .unwrap_or_else(|| Self::exit(&format!("invalid chunk range {}", value)));
+                    result.export_chunks = Some(range);
+                }
+                "--dump-atlas" => {
+                    result.dump_atlas = Some(PathBuf::from(Self::value(&arg, args.next())))
+                }
+                "--saves" => result.saves = Some(PathBuf::from(Self::value(&arg, args.next()))),
+                "--world" => result.world = Some(Self::value(&arg, args.next())),
+                "--new-world" => result.new_world = true,
+                "--seed" => {
+                    let value = Self::value(&arg, args.next());
+                    let seed = value
+                        .parse()
+                        .unwrap_or_else(|_| Self::exit(&format!("invalid seed {}", value)));
+                    result.seed = Some(seed);
+                }
+                "--list-worlds" => result.list_worlds = true,
+                "--rename-world" => {
+                    let from = Self::value(&arg, args.next());
+                    let to = Self::value(&arg, args.next());
+                    result.rename_world = Some((from, to));
+                }
+                "--delete-world" => result.delete_world = Some(Self::value(&arg, args.next())),
+                _ => Self::exit(&format!("unknown argument {}", arg)),
+            }
+        }
+        if result.export_chunks.is_some() && result.export.is_none() {
+            Self::exit("--export-chunks requires --export");
+        }
+        result
+    }
+
+    /// `x0,z0:x1,z1`
+    fn chunk_range(value: &str) -> Option<(IVec2, IVec2)> {
+        let chunk_pos = |value: &str| {
+            let (x, z) = value.split_once(',')?;
+            Some(IVec2::new(x.trim().parse().ok()?, z.trim().parse().ok()?))
+        };
+        let (from, to) = value.split_once(':')?;
+        Some((chunk_pos(from)?, chunk_pos(to)?))
+    }
+
+    fn value(flag: &str, value: Option<String>) -> String {
+        value.unwrap_or_else(|| Self::exit(&format!("{} requires a value", flag)))
+    }
+
+    fn exit(message: &str) -> ! {
+        eprintln!("{}", message);
+        eprintln!("usage: bevy_craft [--export <path.obj|path.glb>] [--dump-atlas <dir>]");
+        eprintln!(
+            "       bevy_craft [--saves <dir>] [--world <name>] \
+             --export <path.obj|path.glb> --export-chunks <x0,z0:x1,z1>"
+        );
+        eprintln!("       bevy_craft [--saves <dir>] [--world <name>] [--new-world] [--seed <n>]");
+        eprintln!(
+            "       bevy_craft [--saves <dir>] \
+             [--list-worlds | --rename-world <name> <new name> | --delete-world <name>]"
+        );
+        std::process::exit(2);
+    }
+}
 
 fn main() {
+    let args = Args::parse();
+    let saves = args.saves.clone().map(Saves::new).unwrap_or_default();
+    if args.export.is_some() || args.dump_atlas.is_some() {
+        export(&saves, args);
+        return;
+    }
+
+    if args.list_worlds || args.rename_world.is_some() || args.delete_world.is_some() {
+        manage_worlds(&saves, args);
+        return;
+    }
+    let world = open_world(&saves, &args).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
     App::new()
+        .insert_resource(world)
         .add_plugins(DefaultPlugins.set(RenderPlugin {
             render_creation: RenderCreation::Automatic(WgpuSettings {
                 backends: Some(Backends::VULKAN),
@@ -23,101 +144,202 @@ fn main() {
             RenderDebugFlags::ALLOW_COPIES_FROM_INDIRECT_PARAMETERS,
         ))
         .add_plugins(AppAssetPlugin)
-        .add_systems(OnEnter(AppLoadState::Next), render_dirt)
-        .add_systems(Update, input_handler)
+        .add_plugins(LoadingScreenPlugin)
+        .add_plugins(BlockInteractionPlugin)
+        .add_plugins(SelectionOutlinePlugin)
+        .add_plugins(PlayerPlugin)
+        .add_plugins(DebugCameraPlugin)
+        .add_plugins(ChunkStreamingPlugin)
+        .add_plugins(WorldSavePlugin)
+        .add_systems(OnEnter(AppLoadState::Next), setup_world)
         .add_systems(Update, toggle_wireframe)
+        .add_systems(Update, dump_atlas.run_if(in_state(AppLoadState::Next)))
         .run();
 }
-#[derive(Component)]
-struct CustomUV;
 
-fn render_dirt(
-    mut commands: Commands,
-    models: Res<ModelManager>,
-    atlas: Res<AppTextureAtlas<TextureId>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
+/// load the world of `--world`, or create it with `--new-world` or if it does not exist
+fn open_world(saves: &Saves, args: &Args) -> Result<SavedWorld, SaveError> {
+    let name = args.world.as_deref().unwrap_or(DEFAULT_WORLD);
+    if !args.new_world && saves.exists(name) {
+        if args.seed.is_some() {
+            eprintln!("--seed is ignored, world {} already exists", name);
+        }
+        return saves.load(name);
+    }
+
+    let seed = args.seed.unwrap_or_else(random_seed);
+    let world = saves.create(
+        name,
+        LevelData::new(name, seed, GeneratorSettings::default()),
+    )?;
+    println!("created world {} with seed {}", name, seed);
+    Ok(world)
+}
+
+/// list, rename or delete worlds instead of opening a window
+fn manage_worlds(saves: &Saves, args: Args) {
+    let mut result = Ok(());
+    if args.list_worlds {
+        result = saves.list().map(|worlds| {
+            for (name, level) in worlds {
+                println!("{}\tseed {}\t{}", name, level.seed, level.name);
+            }
+        });
+    }
+    if let Some((from, to)) = args.rename_world {
+        result = result.and_then(|_| saves.rename(&from, &to).map(|_| ()));
+    }
+    if let Some(name) = args.delete_world {
+        result = result.and_then(|_| saves.delete(&name));
+    }
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+/// load assets headlessly, then export the demo chunk or the chunks of a world, or dump the atlas
+fn export(saves: &Saves, args: Args) {
+    let mut app = App::new();
+    app.add_plugins(HeadlessPlugins);
+    if let Err(err) = run_until_loaded(&mut app, DEFAULT_LOAD_TIMEOUT) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+
+    let world = app.world();
+    let atlas = world.resource::<AppTextureAtlas<TextureId>>();
+    let Some(image) = world.resource::<Assets<Image>>().get(&atlas.atlas()) else {
+        eprintln!("atlas image is not built");
+        std::process::exit(1);
+    };
+
+    let mut result = Ok(());
+    if let Some(path) = args.export {
+        let definitions = world.get_resource::<BlockDefinitions>();
+        let chunks = match args.export_chunks {
+            Some((from, to)) => {
+                let name = args.world.as_deref().unwrap_or(DEFAULT_WORLD);
+                let biomes = world.resource::<Biomes>();
+                saves.load(name).and_then(|saved| {
+                    let generator = saved.generator().with_biomes(Arc::new(biomes.clone()));
+                    saved.load_area(&generator, from, to, biomes, definitions)
+                })
+            }
+            None => {
+                let mut chunk = demo_chunk();
+                chunk.compute_light(definitions);
+                Ok(HashMap::from([(IVec2::ZERO, chunk)]))
+            }
+        };
+        let context = MeshContext {
+            atlas,
+            models: world.resource::<ModelManager>(),
+            shapes: world.resource::<VoxelShapes>(),
+            definitions,
+        };
+        match chunks {
+            Ok(chunks) => {
+                // in a stable order, so that the same chunks give the same file
+                let mut positions = chunks.keys().collect::<Vec<_>>();
+                positions.sort_by_key(|pos| (pos.x, pos.y));
+                let mut vertex = Vertex::default();
+                vertex.extend(
+                    positions
+                        .into_iter()
+                        .map(|pos| chunks[pos].vertex(&context)),
+                );
+                result = result.and(export_vertex(&vertex, image, &path));
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(dir) = args.dump_atlas {
+        result = result.and(export_atlas(atlas, image, &dir));
+    }
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+fn demo_chunk() -> Chunk {
     let mut chunk = Chunk::default();
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            chunk.data.insert(
+                IVec3::new(x, -1, z),
+                BlockData {
+                    id: BlockId::from_static("bevy_craft:block/dirt"),
+                },
+            );
+        }
+    }
     chunk.data.insert(
         IVec3::new(0, 0, 0),
         BlockData {
-            id: BlockId("bevy_craft:block/cherry_stairs".to_string()),
+            id: BlockId::from_static("bevy_craft:block/cherry_stairs"),
         },
     );
     chunk.data.insert(
         IVec3::new(0, 1, 0),
         BlockData {
-            id: BlockId("bevy_craft:block/cherry_stairs".to_string()),
+            id: BlockId::from_static("bevy_craft:block/cherry_stairs"),
         },
     );
     chunk.data.insert(
         IVec3::new(1, 0, 0),
         BlockData {
-            id: BlockId("bevy_craft:block/cherry_stairs".to_string()),
+            id: BlockId::from_static("bevy_craft:block/cherry_stairs"),
         },
     );
     chunk.data.insert(
         IVec3::new(0, 0, 1),
         BlockData {
-            id: BlockId("bevy_craft:block/cherry_stairs".to_string()),
+            id: BlockId::from_static("bevy_craft:block/cherry_stairs"),
         },
     );
     chunk.data.insert(
         IVec3::new(2, 0, 1),
         BlockData {
-            id: BlockId("bevy_craft:block/cherry_stairs".to_string()),
+            id: BlockId::from_static("bevy_craft:block/cherry_stairs"),
         },
     );
 
     // let chunk = Chunk::generate_with_noise(1234, 0.1, 0.1);
-    let mesh = chunk.mesh(&atlas, &models);
-
-    commands.spawn((
-        Mesh3d(meshes.add(mesh)),
-        MeshMaterial3d(materials.add(StandardMaterial {
-            base_color_texture: Some(atlas.atlas()),
-            ..default()
-        })),
-        CustomUV,
-    ));
+    chunk
+}
 
-    // Transform for the camera and lighting, looking at (0,0,0) (the position of the mesh).
-    let camera_and_light_transform =
-        Transform::from_xyz(1.8, 4.0, 1.8).looking_at(Vec3::ZERO, Vec3::Y);
+fn setup_world(
+    mut commands: Commands,
+    settings: Res<PlayerSettings>,
+    world: Res<SavedWorld>,
+    biomes: Res<Biomes>,
+) {
+    let generator = world.generator().with_biomes(Arc::new(biomes.clone()));
+    // the level was created without the biomes, whose features may cover its spawn
+    let spawn = generator.spawn(world.level.spawn.xz());
+    commands.insert_resource(WorldGenerator(Arc::new(generator)));
 
-    // Camera in 3D space.
-    commands.spawn((Camera3d::default(), camera_and_light_transform));
+    // chunks are streamed around the player
+    let player = spawn_player(
+        &mut commands,
+        spawn.as_vec3() + Vec3::new(0.5, 0.0, 0.5),
+        std::f32::consts::FRAC_PI_4,
+        &settings,
+    );
+    commands.entity(player).insert(ChunkLoader::default());
 
     // Light up the scene.
-    commands.spawn((PointLight::default(), camera_and_light_transform));
-}
-
-fn input_handler(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut query: Query<&mut Transform, With<CustomUV>>,
-    time: Res<Time>,
-) {
-    if keyboard_input.pressed(KeyCode::KeyX) {
-        for mut transform in &mut query {
-            transform.rotate_x(time.delta_secs() / 1.2);
-        }
-    }
-    if keyboard_input.pressed(KeyCode::KeyY) {
-        for mut transform in &mut query {
-            transform.rotate_y(time.delta_secs() / 1.2);
-        }
-    }
-    if keyboard_input.pressed(KeyCode::KeyZ) {
-        for mut transform in &mut query {
-            transform.rotate_z(time.delta_secs() / 1.2);
-        }
-    }
-    if keyboard_input.pressed(KeyCode::KeyR) {
-        for mut transform in &mut query {
-            transform.look_to(Vec3::NEG_Z, Vec3::Y);
-        }
-    }
+    commands.spawn((
+        DirectionalLight::default(),
+        Transform::from_xyz(1.0, 4.0, 2.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
 }
 
 fn toggle_wireframe(mut config: ResMut<WireframeConfig>, keys: Res<ButtonInput<KeyCode>>) {
@@ -125,3 +347,23 @@ fn toggle_wireframe(mut config: ResMut<WireframeConfig>, keys: Res<ButtonInput<K
         config.global = !config.global; // 按 F3 切换
     }
 }
+
+fn dump_atlas(
+    keys: Res<ButtonInput<KeyCode>>,
+    atlas: Res<AppTextureAtlas<TextureId>>,
+    images: Res<Assets<Image>>,
+) {
+    if !keys.just_pressed(KeyCode::F4) {
+        return;
+    }
+
+    let dir = PathBuf::from("atlas_dump");
+    let result = images
+        .get(&atlas.atlas())
+        .ok_or(ExportError::MissingAtlas)
+        .and_then(|image| export_atlas(&atlas, image, &dir));
+    match result {
+        Ok(()) => info!("atlas dumped into {}", dir.display()),
+        Err(err) => error!("dump atlas failed: {}", err),
+    }
+}
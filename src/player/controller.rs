@@ -0,0 +1,488 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{
+    input::mouse::AccumulatedMouseMotion,
+    math::bounding::{Aabb3d, BoundingVolume},
+    prelude::*,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+
+use crate::{assets::prelude::*, chunks::prelude::*, player::prelude::*};
+
+#[derive(Resource, Debug, Clone)]
+pub struct PlayerSettings {
+    pub walk_speed: f32,
+    pub sprint_speed: f32,
+    pub fly_speed: f32,
+    /// how fast the horizontal velocity reaches the wanted speed on the ground
+    pub acceleration: f32,
+    /// fraction of `acceleration` in the air
+    pub air_control: f32,
+    pub jump_velocity: f32,
+    pub gravity: f32,
+    pub terminal_velocity: f32,
+    /// highest block which is climbed without jumping, such as a slab or a stair
+    pub step_height: f32,
+    pub width: f32,
+    pub height: f32,
+    pub eye_height: f32,
+    /// radians per pixel of mouse motion
+    pub sensitivity: f32,
+}
+
+impl Default for PlayerSettings {
+    fn default() -> Self {
+        Self {
+            walk_speed: 4.3,
+            sprint_speed: 5.6,
+            fly_speed: 10.9,
+            acceleration: 20.0,
+            air_control: 0.2,
+            jump_velocity: 9.0,
+            gravity: 32.0,
+            terminal_velocity: 78.4,
+            step_height: 0.6,
+            width: 0.6,
+            height: 1.8,
+            eye_height: 1.62,
+            sensitivity: 0.002,
+        }
+    }
+}
+
+/// the player, `Transform::translation` is the center of its feet
+#[derive(Component, Debug, Default, Clone, PartialEq)]
+#[require(Transform, Visibility, PlayerInput)]
+pub struct Player {
+    pub velocity: Vec3,
+    pub on_ground: bool,
+    pub flying: bool,
+    /// rotation around Y, 0 looks to -Z
+    pub yaw: f32,
+    /// rotation around X, clamped to look straight up or down
+    pub pitch: f32,
+}
+
+/// the camera of the player, a child of `Player`
+#[derive(Component)]
+pub struct PlayerCamera;
+
+/// the input of the player in this tick
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct PlayerInput {
+    /// x to the right, y forward
+    pub movement: Vec2,
+    pub sprint: bool,
+    /// jump on the ground, ascend while flying
+    pub jump: bool,
+    /// descend while flying
+    pub descend: bool,
+}
+
+impl Player {
+    pub fn aabb(position: Vec3, settings: &PlayerSettings) -> Aabb3d {
+        let half = settings.width / 2.0;
+        Aabb3d {
+            min: (position - Vec3::new(half, 0.0, half)).into(),
+            max: (position + Vec3::new(half, settings.height, half)).into(),
+        }
+    }
+
+    pub fn look(&mut self, delta: Vec2, settings: &PlayerSettings) {
+        self.yaw -= delta.x * settings.sensitivity;
+        self.pitch = (self.pitch - delta.y * settings.sensitivity)
+            .clamp(-FRAC_PI_2 + 0.01, FRAC_PI_2 - 0.01);
+    }
+
+    /// horizontal velocity wanted by the input
+    fn wish_velocity(&self, input: &PlayerInput, settings: &PlayerSettings) -> Vec2 {
+        let speed = if self.flying {
+            settings.fly_speed
+        } else if input.sprint {
+            settings.sprint_speed
+        } else {
+            settings.walk_speed
+        };
+        let rotation = Quat::from_rotation_y(self.yaw);
+        let forward = (rotation * Vec3::NEG_Z).xz();
+        let right = (rotation * Vec3::X).xz();
+        (right * input.movement.x + forward * input.movement.y).normalize_or_zero() * speed
+    }
+
+    /// advance the player by one fixed tick of `dt` seconds, return the new position
+    pub fn step<W: BlockGetter>(
+        &mut self,
+        position: Vec3,
+        input: &PlayerInput,
+        settings: &PlayerSettings,
+        colliders: &BlockColliders<W>,
+        dt: f32,
+    ) -> Vec3 {
+        let wish = self.wish_velocity(input, settings);
+        let control = if self.on_ground || self.flying {
+            1.0
+        } else {
+            settings.air_control
+        };
+        let blend = (settings.acceleration * control * dt).min(1.0);
+        let horizontal = self.velocity.xz().lerp(wish, blend);
+        self.velocity.x = horizontal.x;
+        self.velocity.z = horizontal.y;
+
+        if self.flying {
+            let vertical = input.jump as i32 - input.descend as i32;
+            self.velocity.y = vertical as f32 * settings.fly_speed;
+        } else if input.jump && self.on_ground {
+            self.velocity.y = settings.jump_velocity;
+        } else {
+            self.velocity.y =
+                (self.velocity.y - settings.gravity * dt).max(-settings.terminal_velocity);
+        }
+
+        let motion = self.velocity * dt;
+        let aabb = Self::aabb(position, settings);
+        let step = Vec3::Y * settings.step_height;
+        let region = aabb
+            .merge(&aabb.translated_by(motion))
+            .merge(&aabb.translated_by(motion + step));
+        let boxes = colliders.boxes(&region);
+
+        let mut moved = move_and_collide(&boxes, &aabb, motion);
+        let landed = motion.y < 0.0 && moved.y != motion.y;
+        let blocked = moved.x != motion.x || moved.z != motion.z;
+        let mut stepped = false;
+        if (self.on_ground || landed) && blocked && !self.flying {
+            // climb up, move horizontally, then fall back down
+            let up = move_and_collide(&boxes, &aabb, step);
+            let raised = aabb.translated_by(up);
+            let across = move_and_collide(&boxes, &raised, motion.with_y(0.0));
+            let down = move_and_collide(&boxes, &raised.translated_by(across), -up);
+            if across.xz().length_squared() > moved.xz().length_squared() {
+                moved = up + across + down;
+                stepped = true;
+            }
+        }
+
+        if moved.x != motion.x {
+            self.velocity.x = 0.0;
+        }
+        if moved.z != motion.z {
+            self.velocity.z = 0.0;
+        }
+        if moved.y != motion.y && !stepped {
+            self.velocity.y = 0.0;
+        }
+        if stepped {
+            self.velocity.y = self.velocity.y.max(0.0);
+        }
+        self.on_ground = landed || stepped;
+
+        position + moved
+    }
+}
+
+/// spawn the player with its camera at `position`
+pub fn spawn_player(
+    commands: &mut Commands,
+    position: Vec3,
+    yaw: f32,
+    settings: &PlayerSettings,
+) -> Entity {
+    commands
+        .spawn((
+            Player { yaw, ..default() },
+            Transform::from_translation(position).with_rotation(Quat::from_rotation_y(yaw)),
+        ))
+        .with_child((
+            Camera3d::default(),
+            PlayerCamera,
+            Transform::from_xyz(0.0, settings.eye_height, 0.0),
+        ))
+        .id()
+}
+
+/// run OnEnter AppLoadState::Next
+pub fn grab_cursor(mut window: Single<&mut Window, With<PrimaryWindow>>) {
+    window.cursor_options.grab_mode = CursorGrabMode::Locked;
+    window.cursor_options.visible = false;
+}
+
+/// run Update in AppLoadState::Next, Escape frees or grabs the cursor
+pub fn toggle_cursor(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut window: Single<&mut Window, With<PrimaryWindow>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        let grabbed = window.cursor_options.grab_mode != CursorGrabMode::None;
+        window.cursor_options.grab_mode = if grabbed {
+            CursorGrabMode::None
+        } else {
+            CursorGrabMode::Locked
+        };
+        window.cursor_options.visible = grabbed;
+    }
+}
+
+/// run Update in AppLoadState::Next, rotate the player and its camera while the cursor is grabbed
+pub fn player_look(
+    motion: Res<AccumulatedMouseMotion>,
+    settings: Res<PlayerSettings>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    mut player: Single<(&mut Player, &mut Transform), Without<PlayerCamera>>,
+    mut camera: Single<&mut Transform, With<PlayerCamera>>,
+) {
+    if window.cursor_options.grab_mode == CursorGrabMode::None {
+        return;
+    }
+
+    let (player, transform) = &mut *player;
+    player.look(motion.delta, &settings);
+    transform.rotation = Quat::from_rotation_y(player.yaw);
+    camera.rotation = Quat::from_rotation_x(player.pitch);
+}
+
+pub fn player_camera_active(camera: Query<&Camera, With<PlayerCamera>>) -> bool {
+    camera.iter().any(|camera| camera.is_active)
+}
+
+/// run Update in AppLoadState::Next, WASD to move, Space to jump, Control to sprint,
+/// F to toggle flying, Shift to descend while flying,
+/// the player stands still while its camera is not active
+pub fn player_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    camera: Single<&Camera, With<PlayerCamera>>,
+    mut player: Single<(&mut Player, &mut PlayerInput)>,
+) {
+    let (player, input) = &mut *player;
+    if !camera.is_active {
+        **input = PlayerInput::default();
+        return;
+    }
+
+    let axis = |negative, positive| keys.pressed(positive) as i32 - keys.pressed(negative) as i32;
+    input.movement = Vec2::new(
+        axis(KeyCode::KeyA, KeyCode::KeyD) as f32,
+        axis(KeyCode::KeyS, KeyCode::KeyW) as f32,
+    );
+    input.sprint = keys.pressed(KeyCode::ControlLeft);
+    input.jump = keys.pressed(KeyCode::Space);
+    input.descend = keys.pressed(KeyCode::ShiftLeft);
+
+    if keys.just_pressed(KeyCode::KeyF) {
+        player.flying = !player.flying;
+        player.velocity.y = 0.0;
+    }
+}
+
+/// run FixedUpdate in AppLoadState::Next
+pub fn move_player(
+    time: Res<Time>,
+    settings: Res<PlayerSettings>,
+    shapes: Res<VoxelShapes>,
+    definitions: Option<Res<BlockDefinitions>>,
+    chunks: Query<&Chunk>,
+    mut players: Query<(&mut Player, &mut Transform, &PlayerInput)>,
+) {
+    let view = chunks.iter().collect::<ChunkView>();
+    let mut colliders = BlockColliders::new(&view).with_shapes(&shapes);
+    if let Some(ref definitions) = definitions {
+        colliders = colliders.with_definitions(definitions);
+    }
+
+    for (mut player, mut transform, input) in &mut players {
+        // wait for the chunk to be loaded instead of falling through it
+        if !view.contains(chunk_position(transform.translation.floor().as_ivec3())) {
+            continue;
+        }
+        transform.translation = player.step(
+            transform.translation,
+            input,
+            &settings,
+            &colliders,
+            time.delta_secs(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::identity::prelude::*;
+
+    use super::*;
+
+    const DT: f32 = 1.0 / 64.0;
+
+    /// a stone floor at y = -1, a slab at (2, 0, 0) and a stone block at (0, 0, -3)
+    fn world() -> (Chunk, VoxelShapes) {
+        let stone = BlockId::from_static("bevy_craft:block/stone");
+        let slab = BlockId::from_static("bevy_craft:block/slab");
+        let mut chunk = Chunk::default();
+        for x in -8..8 {
+            for z in -8..8 {
+                chunk
+                    .data
+                    .insert(IVec3::new(x, -1, z), BlockData { id: stone.clone() });
+            }
+        }
+        chunk
+            .data
+            .insert(IVec3::new(2, 0, 0), BlockData { id: slab.clone() });
+        chunk
+            .data
+            .insert(IVec3::new(0, 0, -3), BlockData { id: stone.clone() });
+
+        let mut shapes = VoxelShapes::default();
+        shapes.insert(
+            slab,
+            VoxelShape::new([ShapeBox::new([0, 0, 0], [16, 8, 16])]),
+        );
+        (chunk, shapes)
+    }
+
+    fn run(
+        player: &mut Player,
+        mut position: Vec3,
+        input: PlayerInput,
+        ticks: usize,
+        colliders: &BlockColliders<Chunk>,
+    ) -> Vec3 {
+        let settings = PlayerSettings::default();
+        for _ in 0..ticks {
+            position = player.step(position, &input, &settings, colliders, DT);
+        }
+        position
+    }
+
+    #[test]
+    fn test_fall_and_jump() {
+        let (chunk, shapes) = world();
+        let colliders = BlockColliders::new(&chunk).with_shapes(&shapes);
+        let mut player = Player::default();
+
+        let position = run(
+            &mut player,
+            Vec3::new(0.5, 3.0, 0.5),
+            PlayerInput::default(),
+            64,
+            &colliders,
+        );
+        assert!(player.on_ground);
+        assert!(position.y.abs() < 1e-4);
+        assert_eq!(player.velocity, Vec3::ZERO);
+
+        let jump = PlayerInput {
+            jump: true,
+            ..default()
+        };
+        let mut highest = position.y;
+        let mut current = position;
+        for _ in 0..64 {
+            current = run(&mut player, current, jump, 1, &colliders);
+            highest = highest.max(current.y);
+            if player.on_ground {
+                break;
+            }
+        }
+        assert!(highest > 1.0 && highest < 1.5);
+        assert!(player.on_ground);
+    }
+
+    #[test]
+    fn test_walk_into_wall() {
+        let (chunk, shapes) = world();
+        let colliders = BlockColliders::new(&chunk).with_shapes(&shapes);
+        let mut player = Player {
+            on_ground: true,
+            ..default()
+        };
+
+        // walk forward (-Z) into the stone block, it is too high to step on
+        let forward = PlayerInput {
+            movement: Vec2::Y,
+            ..default()
+        };
+        let position = run(
+            &mut player,
+            Vec3::new(0.5, 0.0, 0.5),
+            forward,
+            128,
+            &colliders,
+        );
+        assert!((position.z - -1.7).abs() < 1e-4);
+        assert!(position.y.abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_step_up() {
+        let (chunk, shapes) = world();
+        let colliders = BlockColliders::new(&chunk).with_shapes(&shapes);
+        // yaw of -90° looks to +X
+        let mut player = Player {
+            on_ground: true,
+            yaw: -FRAC_PI_2,
+            ..default()
+        };
+
+        let forward = PlayerInput {
+            movement: Vec2::Y,
+            ..default()
+        };
+        let position = run(
+            &mut player,
+            Vec3::new(0.5, 0.0, 0.5),
+            forward,
+            32,
+            &colliders,
+        );
+        assert!(position.x > 2.0 && position.x < 3.0);
+        assert!((position.y - 0.5).abs() < 1e-4);
+        assert!(player.on_ground);
+    }
+
+    #[test]
+    fn test_fly() {
+        let (chunk, shapes) = world();
+        let colliders = BlockColliders::new(&chunk).with_shapes(&shapes);
+        let mut player = Player {
+            flying: true,
+            ..default()
+        };
+
+        let position = run(
+            &mut player,
+            Vec3::new(0.5, 2.0, 0.5),
+            PlayerInput::default(),
+            64,
+            &colliders,
+        );
+        assert_eq!(position, Vec3::new(0.5, 2.0, 0.5));
+
+        let descend = PlayerInput {
+            descend: true,
+            ..default()
+        };
+        let position = run(&mut player, position, descend, 64, &colliders);
+        assert!(position.y.abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let (chunk, shapes) = world();
+        let colliders = BlockColliders::new(&chunk).with_shapes(&shapes);
+        let input = PlayerInput {
+            movement: Vec2::new(0.3, 1.0),
+            sprint: true,
+            jump: true,
+            ..default()
+        };
+
+        let mut first = Player::default();
+        let mut second = Player::default();
+        let start = Vec3::new(-3.2, 1.0, 4.1);
+        assert_eq!(
+            run(&mut first, start, input, 200, &colliders),
+            run(&mut second, start, input, 200, &colliders)
+        );
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,9 @@
+pub(crate) mod controller;
+pub(crate) mod physics;
+pub(crate) mod plugin;
+
+pub mod prelude {
+    pub use super::controller::*;
+    pub use super::physics::*;
+    pub use super::plugin::*;
+}
@@ -0,0 +1,189 @@
+use bevy::{
+    math::bounding::{Aabb3d, BoundingVolume, IntersectsVolume},
+    prelude::*,
+};
+
+use crate::{assets::prelude::*, chunks::prelude::*};
+
+/// tolerance of the contact tests, so that a box resting on a face is not stuck into it
+const EPSILON: f32 = 1e-5;
+
+/// the blocks which entities collide with
+pub struct BlockColliders<'a, W: BlockGetter> {
+    pub world: &'a W,
+    /// shapes of the blocks, every block is a full cube if not given
+    pub shapes: Option<&'a VoxelShapes>,
+    /// blocks which are not `solid` are ignored
+    pub definitions: Option<&'a BlockDefinitions>,
+}
+
+impl<'a, W: BlockGetter> BlockColliders<'a, W> {
+    pub fn new(world: &'a W) -> Self {
+        Self {
+            world,
+            shapes: None,
+            definitions: None,
+        }
+    }
+
+    pub fn with_shapes(mut self, shapes: &'a VoxelShapes) -> Self {
+        self.shapes = Some(shapes);
+        self
+    }
+
+    pub fn with_definitions(mut self, definitions: &'a BlockDefinitions) -> Self {
+        self.definitions = Some(definitions);
+        self
+    }
+
+    /// collision boxes of the blocks which intersect `region`
+    pub fn boxes(&self, region: &Aabb3d) -> Vec<Aabb3d> {
+        let min = Vec3::from(region.min).floor().as_ivec3();
+        let max = Vec3::from(region.max).ceil().as_ivec3();
+
+        let mut result = Vec::new();
+        for x in min.x..max.x {
+            for y in min.y..max.y {
+                for z in min.z..max.z {
+                    let pos = IVec3::new(x, y, z);
+                    let Some(block_data) = self.world.get_block(pos) else {
+                        continue;
+                    };
+                    let solid = self
+                        .definitions
+                        .and_then(|definitions| definitions.get(&block_data.id))
+                        .is_none_or(|definition| definition.solid);
+                    if !solid {
+                        continue;
+                    }
+
+                    let shape = self.shapes.and_then(|shapes| shapes.get(&block_data.id));
+                    let boxes = match shape {
+                        Some(shape) => shape.aabbs(pos),
+                        None => VoxelShape::full().aabbs(pos),
+                    };
+                    result.extend(boxes.into_iter().filter(|aabb| aabb.intersects(region)));
+                }
+            }
+        }
+        result
+    }
+}
+
+/// clip `motion` along `axis` so that `aabb` stops when it touches `other`
+fn clip(aabb: &Aabb3d, other: &Aabb3d, axis: usize, motion: f32) -> f32 {
+    let overlap = (0..3)
+        .filter(|i| *i != axis)
+        .all(|i| aabb.max[i] > other.min[i] + EPSILON && aabb.min[i] < other.max[i] - EPSILON);
+    if !overlap {
+        return motion;
+    }
+
+    if motion > 0.0 && aabb.max[axis] <= other.min[axis] + EPSILON {
+        motion.min(other.min[axis] - aabb.max[axis])
+    } else if motion < 0.0 && aabb.min[axis] >= other.max[axis] - EPSILON {
+        motion.max(other.max[axis] - aabb.min[axis])
+    } else {
+        motion
+    }
+}
+
+/// move `aabb` by `motion` among `boxes`, resolved per axis in the order Y, X, Z,
+/// returns the motion which is actually done
+pub fn move_and_collide(boxes: &[Aabb3d], aabb: &Aabb3d, motion: Vec3) -> Vec3 {
+    let mut aabb = *aabb;
+    let mut result = Vec3::ZERO;
+    for axis in [1, 0, 2] {
+        let distance = boxes.iter().fold(motion[axis], |distance, other| {
+            clip(&aabb, other, axis, distance)
+        });
+        let mut offset = Vec3::ZERO;
+        offset[axis] = distance;
+        aabb.translate_by(offset);
+        result[axis] = distance;
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::identity::prelude::*;
+
+    use super::*;
+
+    fn unit(pos: Vec3) -> Aabb3d {
+        Aabb3d {
+            min: pos.into(),
+            max: (pos + Vec3::ONE).into(),
+        }
+    }
+
+    #[test]
+    fn test_move_and_collide() {
+        let floor = [
+            unit(Vec3::new(0.0, -1.0, 0.0)),
+            unit(Vec3::new(1.0, -1.0, 0.0)),
+        ];
+        let aabb = Aabb3d {
+            min: Vec3A::new(0.2, 0.5, 0.2),
+            max: Vec3A::new(0.8, 1.5, 0.8),
+        };
+
+        // land on the floor
+        let moved = move_and_collide(&floor, &aabb, Vec3::new(0.0, -2.0, 0.0));
+        assert_eq!(moved, Vec3::new(0.0, -0.5, 0.0));
+
+        // slide along the floor
+        let moved = move_and_collide(&floor, &aabb, Vec3::new(0.5, -2.0, 0.0));
+        assert_eq!(moved, Vec3::new(0.5, -0.5, 0.0));
+
+        // stop at a wall
+        let wall = [unit(Vec3::new(1.0, 0.0, 0.0))];
+        let moved = move_and_collide(&wall, &aabb, Vec3::new(1.0, 0.0, 0.5));
+        assert!((moved.x - 0.2).abs() < 1e-6);
+        assert_eq!(moved.z, 0.5);
+    }
+
+    #[test]
+    fn test_block_colliders() {
+        let slab = BlockId::from_static("bevy_craft:block/slab");
+        let flower = BlockId::from_static("bevy_craft:block/flower");
+        let mut shapes = VoxelShapes::default();
+        shapes.insert(
+            slab.clone(),
+            VoxelShape::new([ShapeBox::new([0, 0, 0], [16, 8, 16])]),
+        );
+        let mut definitions = BlockDefinitions::default();
+        definitions.insert(
+            flower.clone(),
+            BlockDefinition {
+                solid: false,
+                ..default()
+            },
+        );
+
+        let mut chunk = Chunk::default();
+        chunk.data.insert(IVec3::ZERO, BlockData { id: slab });
+        chunk.data.insert(IVec3::X, BlockData { id: flower });
+        chunk.data.insert(
+            IVec3::Z,
+            BlockData {
+                id: BlockId::from_static("bevy_craft:block/stone"),
+            },
+        );
+
+        let colliders = BlockColliders::new(&chunk)
+            .with_shapes(&shapes)
+            .with_definitions(&definitions);
+        let boxes = colliders.boxes(&Aabb3d {
+            min: Vec3A::ZERO,
+            max: Vec3A::new(2.0, 1.0, 2.0),
+        });
+        assert_eq!(boxes.len(), 2);
+        assert!(boxes.contains(&Aabb3d {
+            min: Vec3A::ZERO,
+            max: Vec3A::new(1.0, 0.5, 1.0),
+        }));
+        assert!(boxes.contains(&unit(Vec3::Z)));
+    }
+}
@@ -0,0 +1,26 @@
+use bevy::prelude::*;
+
+use crate::{assets::prelude::*, player::prelude::*};
+
+/// first person controller, the player is spawned with `spawn_player`
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerSettings>()
+            .add_systems(OnEnter(AppLoadState::Next), grab_cursor)
+            .add_systems(
+                Update,
+                (
+                    toggle_cursor,
+                    player_look.run_if(player_camera_active),
+                    player_input,
+                )
+                    .run_if(in_state(AppLoadState::Next)),
+            )
+            .add_systems(
+                FixedUpdate,
+                move_player.run_if(in_state(AppLoadState::Next)),
+            );
+    }
+}
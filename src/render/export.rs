@@ -0,0 +1,444 @@
+use std::fmt::Write as _;
+use std::io::Cursor;
+use std::path::Path;
+use std::str::FromStr;
+
+use bevy::image::IntoDynamicImageError;
+use bevy::prelude::*;
+use derive_more::derive::{Display, Error, From};
+use image::ImageFormat;
+use serde_json::json;
+
+use crate::assets::prelude::*;
+use crate::identity::prelude::*;
+use crate::render::prelude::*;
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_VERSION: u32 = 2;
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+const GL_FLOAT: u32 = 5126;
+const GL_UNSIGNED_INT: u32 = 5125;
+const GL_NEAREST: u32 = 9728;
+const GL_ARRAY_BUFFER: u32 = 34962;
+const GL_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// `.obj` with a `.mtl` and the atlas `.png` next to it
+    #[display("obj")]
+    Obj,
+    /// binary glTF with the atlas embedded
+    #[display("glb")]
+    Glb,
+}
+
+impl FromStr for ExportFormat {
+    type Err = ExportError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "obj" => Ok(ExportFormat::Obj),
+            "glb" => Ok(ExportFormat::Glb),
+            _ => Err(ExportError::UnknownFormat(value.to_string())),
+        }
+    }
+}
+
+impl TryFrom<&Path> for ExportFormat {
+    type Error = ExportError;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .ok_or(ExportError::UnknownFormat(path.display().to_string()))?
+            .parse()
+    }
+}
+
+#[derive(Debug, Error, Display, From)]
+pub enum ExportError {
+    #[display("Failed to write export: {}", _0)]
+    Io(std::io::Error),
+    #[display("Failed to convert atlas: {}", _0)]
+    Atlas(IntoDynamicImageError),
+    #[display("Failed to encode atlas: {}", _0)]
+    Encode(image::ImageError),
+    #[display("unknown export format {{ {} }}", _0)]
+    #[from(ignore)]
+    UnknownFormat(#[error(not(source))] String),
+    #[display("nothing to export")]
+    Empty,
+    #[display("the atlas image is not loaded")]
+    MissingAtlas,
+}
+
+/// export the vertex and the atlas, the format is chosen by the extension of `path`
+pub fn export_vertex(vertex: &Vertex, atlas: &Image, path: &Path) -> Result<(), ExportError> {
+    match ExportFormat::try_from(path)? {
+        ExportFormat::Obj => export_obj(vertex, atlas, path),
+        ExportFormat::Glb => export_glb(vertex, atlas, path),
+    }
+}
+
+pub fn encode_png(atlas: &Image) -> Result<Vec<u8>, ExportError> {
+    let mut png = Cursor::new(Vec::new());
+    atlas
+        .clone()
+        .try_into_dynamic()?
+        .write_to(&mut png, ImageFormat::Png)?;
+    Ok(png.into_inner())
+}
+
+/// write `atlas.png` and `atlas.json` with the rect and uv of each texture into `dir`
+pub fn export_atlas(
+    atlas: &AppTextureAtlas<TextureId>,
+    image: &Image,
+    dir: &Path,
+) -> Result<(), ExportError> {
+    let document = json!({
+        "size": atlas.size(),
+        "textures": atlas.entries(),
+    });
+
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join("atlas.png"), encode_png(image)?)?;
+    std::fs::write(
+        dir.join("atlas.json"),
+        serde_json::to_vec_pretty(&document).map_err(std::io::Error::from)?,
+    )?;
+    Ok(())
+}
+
+/// write `name.obj`, `name.mtl` and `name.png`
+pub fn export_obj(vertex: &Vertex, atlas: &Image, path: &Path) -> Result<(), ExportError> {
+    if vertex.indices.is_empty() {
+        return Err(ExportError::Empty);
+    }
+
+    let mtl_path = path.with_extension("mtl");
+    let png_path = path.with_extension("png");
+    let file_name = |path: &Path| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+
+    let mut obj = String::new();
+    // writing into a String never fails
+    let _ = writeln!(obj, "mtllib {}", file_name(&mtl_path));
+    let _ = writeln!(obj, "o chunk");
+    for [x, y, z] in &vertex.positions {
+        let _ = writeln!(obj, "v {} {} {}", x, y, z);
+    }
+    // obj puts the origin of uv at the bottom left
+    for [u, v] in &vertex.uvs {
+        let _ = writeln!(obj, "vt {} {}", u, 1.0 - v);
+    }
+    for [x, y, z] in &vertex.normals {
+        let _ = writeln!(obj, "vn {} {} {}", x, y, z);
+    }
+    let _ = writeln!(obj, "usemtl atlas");
+    for triangle in vertex.indices.chunks_exact(3) {
+        let _ = write!(obj, "f");
+        for indice in triangle {
+            let _ = write!(obj, " {0}/{0}/{0}", indice + 1);
+        }
+        let _ = writeln!(obj);
+    }
+
+    let mtl = format!(
+        "newmtl atlas\nKa 1 1 1\nKd 1 1 1\nKs 0 0 0\nd 1\nillum 1\nmap_Kd {}\n",
+        file_name(&png_path)
+    );
+
+    std::fs::write(path, obj)?;
+    std::fs::write(mtl_path, mtl)?;
+    std::fs::write(png_path, encode_png(atlas)?)?;
+    Ok(())
+}
+
+/// write a binary glTF with the atlas embedded
+pub fn export_glb(vertex: &Vertex, atlas: &Image, path: &Path) -> Result<(), ExportError> {
+    std::fs::write(path, glb(vertex, &encode_png(atlas)?)?)?;
+    Ok(())
+}
+
+fn glb(vertex: &Vertex, png: &[u8]) -> Result<Vec<u8>, ExportError> {
+    if vertex.indices.is_empty() {
+        return Err(ExportError::Empty);
+    }
+
+    let mut bin = Vec::new();
+    let mut views = Vec::new();
+    let mut push_view = |bytes: &[u8], target: Option<u32>| {
+        let offset = bin.len();
+        bin.extend_from_slice(bytes);
+        bin.resize(bin.len().next_multiple_of(4), 0);
+
+        let mut view = json!({ "buffer": 0, "byteOffset": offset, "byteLength": bytes.len() });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+        views.push(view);
+        views.len() - 1
+    };
+
+    let floats = |values: &mut dyn Iterator<Item = f32>| {
+        values.flat_map(f32::to_le_bytes).collect::<Vec<_>>()
+    };
+    let positions = push_view(
+        &floats(&mut vertex.positions.iter().flatten().copied()),
+        Some(GL_ARRAY_BUFFER),
+    );
+    let normals = push_view(
+        &floats(&mut vertex.normals.iter().flatten().copied()),
+        Some(GL_ARRAY_BUFFER),
+    );
+    let uvs = push_view(
+        &floats(&mut vertex.uvs.iter().flatten().copied()),
+        Some(GL_ARRAY_BUFFER),
+    );
+    let indices = push_view(
+        &vertex
+            .indices
+            .iter()
+            .flat_map(|indice| indice.to_le_bytes())
+            .collect::<Vec<_>>(),
+        Some(GL_ELEMENT_ARRAY_BUFFER),
+    );
+    let image = push_view(png, None);
+
+    // POSITION accessor requires the bounds
+    let (min, max) = vertex.positions.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), position| {
+            let position = Vec3::from_array(*position);
+            (min.min(position), max.max(position))
+        },
+    );
+
+    let document = json!({
+        "asset": { "version": "2.0", "generator": "bevy_craft" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0, "name": "chunk" }],
+        "meshes": [{
+            "primitives": [{
+                "attributes": { "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2 },
+                "indices": 3,
+                "material": 0,
+            }],
+        }],
+        "materials": [{
+            "name": "atlas",
+            "pbrMetallicRoughness": {
+                "baseColorTexture": { "index": 0 },
+                "metallicFactor": 0.0,
+                "roughnessFactor": 1.0,
+            },
+            "alphaMode": "MASK",
+        }],
+        "textures": [{ "sampler": 0, "source": 0 }],
+        "samplers": [{ "magFilter": GL_NEAREST, "minFilter": GL_NEAREST }],
+        "images": [{ "bufferView": image, "mimeType": "image/png" }],
+        "accessors": [
+            {
+                "bufferView": positions,
+                "componentType": GL_FLOAT,
+                "count": vertex.positions.len(),
+                "type": "VEC3",
+                "min": min.to_array(),
+                "max": max.to_array(),
+            },
+            {
+                "bufferView": normals,
+                "componentType": GL_FLOAT,
+                "count": vertex.normals.len(),
+                "type": "VEC3",
+            },
+            {
+                "bufferView": uvs,
+                "componentType": GL_FLOAT,
+                "count": vertex.uvs.len(),
+                "type": "VEC2",
+            },
+            {
+                "bufferView": indices,
+                "componentType": GL_UNSIGNED_INT,
+                "count": vertex.indices.len(),
+                "type": "SCALAR",
+            },
+        ],
+        "bufferViews": views,
+        "buffers": [{ "byteLength": bin.len() }],
+    });
+
+    let mut json = serde_json::to_vec(&document).map_err(std::io::Error::from)?;
+    json.resize(json.len().next_multiple_of(4), b' ');
+
+    let total = 12 + 8 + json.len() + 8 + bin.len();
+    let mut glb = Vec::with_capacity(total);
+    glb.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+    glb.extend_from_slice(&GLB_VERSION.to_le_bytes());
+    glb.extend_from_slice(&(total as u32).to_le_bytes());
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(&GLB_CHUNK_JSON.to_le_bytes());
+    glb.extend_from_slice(&json);
+    glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+    glb.extend_from_slice(&GLB_CHUNK_BIN.to_le_bytes());
+    glb.extend_from_slice(&bin);
+    Ok(glb)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn quad() -> Vertex {
+        Vertex {
+            positions: vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0],
+            ],
+            normals: vec![[0.0, 0.0, 1.0]; 4],
+            uvs: vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+            lights: vec![[1.0, 0.0]; 4],
+            tints: vec![[1.0; 3]; 4],
+            indices: vec![0, 1, 2, 0, 2, 3],
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            ExportFormat::try_from(Path::new("out/chunk.GLB")).unwrap(),
+            ExportFormat::Glb
+        );
+        assert_eq!(
+            ExportFormat::try_from(Path::new("chunk.obj")).unwrap(),
+            ExportFormat::Obj
+        );
+        assert!(ExportFormat::try_from(Path::new("chunk.fbx")).is_err());
+        assert!(ExportFormat::try_from(Path::new("chunk")).is_err());
+    }
+
+    #[test]
+    fn test_glb() {
+        let glb = glb(&quad(), &[1, 2, 3]).unwrap();
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(glb[offset..offset + 4].try_into().unwrap());
+
+        assert_eq!(u32_at(0), GLB_MAGIC);
+        assert_eq!(u32_at(8) as usize, glb.len());
+        assert_eq!(u32_at(16), GLB_CHUNK_JSON);
+
+        let json_len = u32_at(12) as usize;
+        assert_eq!(json_len % 4, 0);
+        let document: serde_json::Value = serde_json::from_slice(&glb[20..20 + json_len]).unwrap();
+        assert_eq!(document["accessors"][0]["count"], 4);
+        assert_eq!(document["accessors"][0]["max"], json!([1.0, 1.0, 0.0]));
+        assert_eq!(document["accessors"][3]["count"], 6);
+
+        let bin_len = u32_at(20 + json_len) as usize;
+        assert_eq!(u32_at(24 + json_len), GLB_CHUNK_BIN);
+        assert_eq!(document["buffers"][0]["byteLength"], bin_len);
+        assert_eq!(28 + json_len + bin_len, glb.len());
+
+        assert!(matches!(
+            super::glb(&Vertex::default(), &[]),
+            Err(ExportError::Empty)
+        ));
+    }
+
+    #[test]
+    fn test_obj() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let path = dir.join("quad.obj");
+        export_vertex(&quad(), &Image::default(), &path).unwrap();
+
+        let obj = std::fs::read_to_string(&path).unwrap();
+        assert!(obj.starts_with("mtllib quad.mtl\n"));
+        assert_eq!(obj.lines().filter(|line| line.starts_with("v ")).count(), 4);
+        assert!(obj.contains("vt 0 1\n"));
+        assert!(obj.contains("f 1/1/1 2/2/2 3/3/3\n"));
+
+        let mtl = std::fs::read_to_string(dir.join("quad.mtl")).unwrap();
+        assert!(mtl.contains("map_Kd quad.png"));
+        assert!(dir.join("quad.png").exists());
+    }
+
+    #[test]
+    fn test_export_atlas() {
+        use bevy::render::{
+            render_asset::RenderAssetUsages,
+            render_resource::{Extent3d, TextureDimension, TextureFormat},
+        };
+
+        let mut images = Assets::<Image>::default();
+        let mut image = |size| {
+            images.add(Image::new_fill(
+                Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                &[255, 0, 0, 255],
+                TextureFormat::Rgba8UnormSrgb,
+                RenderAssetUsages::all(),
+            ))
+        };
+        let (dirt, stone) = (image(16), image(8));
+        let atlas = AppTextureAtlas::build(
+            &mut images,
+            [
+                (
+                    TextureId::try_from("bevy_craft:block/dirt").unwrap(),
+                    dirt.id(),
+                ),
+                (
+                    TextureId::try_from("bevy_craft:block/stone").unwrap(),
+                    stone.id(),
+                ),
+            ],
+        )
+        .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let image = images.get(&atlas.atlas()).unwrap();
+        export_atlas(&atlas, image, dir.path()).unwrap();
+        assert!(dir.path().join("atlas.png").exists());
+
+        let document: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dir.path().join("atlas.json")).unwrap()).unwrap();
+        let size = atlas.size();
+        assert_eq!(document["size"], json!([size.x, size.y]));
+        for (texture_id, expected) in [("bevy_craft:block/dirt", 16), ("bevy_craft:block/stone", 8)]
+        {
+            let entry = &document["textures"][texture_id];
+            let rect = &entry["rect"];
+            let [min_x, min_y, max_x, max_y] = [
+                &rect["min"][0],
+                &rect["min"][1],
+                &rect["max"][0],
+                &rect["max"][1],
+            ]
+            .map(|value| value.as_u64().unwrap() as u32);
+            assert_eq!((max_x - min_x, max_y - min_y), (expected, expected));
+            assert!(max_x <= size.x && max_y <= size.y);
+            assert_eq!(
+                entry["uv"]["min"][0].as_f64().unwrap(),
+                min_x as f64 / size.x as f64
+            );
+            assert_eq!(
+                entry["uv"]["max"][1].as_f64().unwrap(),
+                max_y as f64 / size.y as f64
+            );
+        }
+    }
+}
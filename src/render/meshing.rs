@@ -1,15 +1,39 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 
 use crate::{assets::prelude::*, chunks::prelude::*, identity::prelude::*, render::prelude::*};
 
+/// everything a chunk is meshed with
+#[derive(Clone, Copy)]
+pub struct MeshContext<'a> {
+    pub atlas: &'a AppTextureAtlas<TextureId>,
+    pub models: &'a ModelManager,
+    /// a face is culled by the shape of the opaque block in front of it
+    pub shapes: &'a VoxelShapes,
+    pub definitions: Option<&'a BlockDefinitions>,
+}
+
+/// the resources of `MeshContext`
+#[derive(SystemParam)]
+pub struct ChunkMeshing<'w> {
+    atlas: Res<'w, AppTextureAtlas<TextureId>>,
+    models: Res<'w, ModelManager>,
+    shapes: Res<'w, VoxelShapes>,
+    definitions: Option<Res<'w, BlockDefinitions>>,
+}
+
+impl ChunkMeshing<'_> {
+    pub fn context(&self) -> MeshContext<'_> {
+        MeshContext {
+            atlas: &self.atlas,
+            models: &self.models,
+            shapes: &self.shapes,
+            definitions: self.definitions.as_deref(),
+        }
+    }
+}
+
 impl Model {
-    pub fn vertex(
-        &self,
-        pos: IVec3,
-        atlas: &AppTextureAtlas<TextureId>,
-        chunk: &Chunk,
-        models: &ModelManager,
-    ) -> Option<Vertex> {
+    pub fn vertex(&self, pos: IVec3, chunk: &Chunk, context: &MeshContext) -> Option<Vertex> {
         let element_size = self
             .elements
             .as_ref()
@@ -22,6 +46,8 @@ impl Model {
         let mut positions = Vec::new();
         let mut normals = Vec::new();
         let mut uvs = Vec::new();
+        let mut lights = Vec::new();
+        let mut tints = Vec::new();
         let mut indices = Vec::new();
 
         if let Some(ref elements) = self.elements {
@@ -30,8 +56,8 @@ impl Model {
                 let max = element.max();
                 for (face, face_data) in &element.faces {
                     // TODO: Alpha Face?
-                    if should_cull_face(pos, *face, element, face_data, chunk, models) {
-                        bevy::log::info!("pos: {:?}, face: {:?}, cullface", pos, face);
+                    if should_cull_face(pos, *face, element, face_data, chunk, context) {
+                        bevy::log::trace!("pos: {:?}, face: {:?}, cullface", pos, face);
                         continue;
                     }
 
@@ -45,6 +71,12 @@ impl Model {
                     // normals
                     normals.extend([face.normal(); 4]);
 
+                    // lights
+                    lights.extend(face_lights(pos, *face, element, chunk, context.definitions));
+
+                    // tints
+                    tints.extend([chunk.tint(pos, face_data.tintindex); 4]);
+
                     // indices
                     indices.extend(face.indice(positions.len() as u32 - 4));
 
@@ -61,7 +93,7 @@ impl Model {
                                 .and_then(|textures| textures.texture_path(key))
                         })
                         .and_then(|texture| TextureId::try_from(texture).ok())
-                        .and_then(|texture_id| atlas.uv(texture_id))
+                        .and_then(|texture_id| context.atlas.uv(texture_id))
                         .unwrap_or(Rect::EMPTY); // NOTE: maybe need an default texture?
 
                     let [u1, v1, u2, v2] =
@@ -81,6 +113,8 @@ impl Model {
             positions,
             normals,
             uvs,
+            lights,
+            tints,
             indices,
         })
     }
@@ -92,7 +126,7 @@ fn should_cull_face(
     element: &Element,
     face_data: &ElementFace,
     chunk: &Chunk,
-    models: &ModelManager,
+    context: &MeshContext,
 ) -> bool {
     let Some(cull_face) = face_data.cullface else {
         return false;
@@ -103,21 +137,279 @@ fn should_cull_face(
         return false;
     }
 
-    let Some(opposite_faces) = chunk
-        .opposite(pos, cull_face)
-        .map(|block_data| &block_data.id)
-        .and_then(|block_id| models.get(block_id))
-        .and_then(|model| model.faces(face.opposite()))
-    else {
+    // the faces behind a block which is not opaque are seen through it
+    let neighbor = chunk.opposite(pos, cull_face);
+    if !is_opaque(neighbor, context.definitions) {
         return false;
+    }
+    neighbor
+        .and_then(|block_data| context.shapes.get(&block_data.id))
+        .is_some_and(|shape| shape.occludes(face.opposite(), element.rect(face)))
+}
+
+/// light of the four vertices of the face, in the order of `BlockFace::vertex`,
+/// a face on the block boundary is lit by the block in front of it,
+/// with `shade` each corner averages the blocks touching it, otherwise the face is flat
+pub fn face_lights(
+    pos: IVec3,
+    face: BlockFace,
+    element: &Element,
+    chunk: &Chunk,
+    definitions: Option<&BlockDefinitions>,
+) -> [[f32; 2]; 4] {
+    let base = if element.is_normal_face(face) {
+        pos + IVec3::from(face)
+    } else {
+        pos
     };
+    if !element.shade {
+        return [chunk.light(base).levels(); 4];
+    }
+
+    let (u_axis, v_axis) = tangent_axes(face);
+    let corners = corner_lights(base, u_axis, v_axis, chunk, definitions);
+    face.vertex(element.min(), element.max()).map(|vertex| {
+        // interpolate between the corners for the vertices of a partial face
+        let u = vertex[u_axis].clamp(0.0, 1.0);
+        let v = vertex[v_axis].clamp(0.0, 1.0);
+        let lerp = |a: [f32; 2], b: [f32; 2], t: f32| [0, 1].map(|i| a[i] + (b[i] - a[i]) * t);
+        lerp(
+            lerp(corners[0][0], corners[1][0], u),
+            lerp(corners[0][1], corners[1][1], u),
+            v,
+        )
+    })
+}
+
+/// the two axes along the face
+fn tangent_axes(face: BlockFace) -> (usize, usize) {
+    match face {
+        BlockFace::Down | BlockFace::Up => (0, 2),
+        BlockFace::North | BlockFace::South => (0, 1),
+        BlockFace::West | BlockFace::East => (1, 2),
+    }
+}
+
+/// average light of the corners of the face in front of `base`,
+/// indexed by the negative/positive side of `u_axis` then `v_axis`,
+/// the blocks which stop the light propagation also hide the corners
+fn corner_lights(
+    base: IVec3,
+    u_axis: usize,
+    v_axis: usize,
+    chunk: &Chunk,
+    definitions: Option<&BlockDefinitions>,
+) -> [[[f32; 2]; 2]; 2] {
+    let blocks_light = |pos: IVec3| is_opaque(chunk.data.get(&pos), definitions);
+
+    [-1, 1].map(|u| {
+        [-1, 1].map(|v| {
+            let mut u_offset = IVec3::ZERO;
+            u_offset[u_axis] = u;
+            let mut v_offset = IVec3::ZERO;
+            v_offset[v_axis] = v;
+
+            let u_side = base + u_offset;
+            let v_side = base + v_offset;
+            let corner = base + u_offset + v_offset;
+            let u_blocked = blocks_light(u_side);
+            let v_blocked = blocks_light(v_side);
+
+            let mut samples = vec![base];
+            if !u_blocked {
+                samples.push(u_side);
+            }
+            if !v_blocked {
+                samples.push(v_side);
+            }
+            // the corner is hidden when both sides are blocked
+            let corner_visible = !(u_blocked && v_blocked);
+            if corner_visible && !blocks_light(corner) {
+                samples.push(corner);
+            }
 
-    let rect = element.rect(face);
-    opposite_faces
-        .iter()
-        .filter(|face| face.is_normal_face(face.face))
-        .any(|face| {
-            let opposite_rect = face.rect(face.face);
-            opposite_rect.contains(rect.min) && opposite_rect.contains(rect.max)
+            let sum = samples.iter().fold([0.0; 2], |sum, pos| {
+                let levels = chunk.light(*pos).levels();
+                [sum[0] + levels[0], sum[1] + levels[1]]
+            });
+            sum.map(|value| value / samples.len() as f32)
         })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stone() -> BlockData {
+        BlockData {
+            id: BlockId::from_static("bevy_craft:block/stone"),
+        }
+    }
+
+    #[test]
+    fn test_cull_faces() {
+        let leaves = BlockId::from_static("bevy_craft:block/oak_leaves");
+        let slab = BlockId::from_static("bevy_craft:block/slab");
+        let mut models = ModelManager::default();
+        for block_id in [stone().id, leaves.clone(), slab.clone()] {
+            models.insert(
+                block_id.into(),
+                Model::default()
+                    .with_element(Element::new([0, 0, 0], [16, 16, 16]).with_all_faces("#all")),
+            );
+        }
+        let mut definitions = BlockDefinitions::default();
+        definitions.insert(
+            leaves.clone(),
+            BlockDefinition {
+                opaque: false,
+                ..default()
+            },
+        );
+        definitions.insert(
+            slab.clone(),
+            BlockDefinition {
+                shape: Some(VoxelShape::new([ShapeBox::new([0, 0, 0], [16, 8, 16])])),
+                ..default()
+            },
+        );
+        let shapes = VoxelShapes::new(&models, Some(&definitions));
+        let mut images = Assets::<Image>::default();
+        let image = images.add(Image::default()).id();
+        let atlas = AppTextureAtlas::build(
+            &mut images,
+            [(
+                TextureId::try_from("bevy_craft:block/stone").unwrap(),
+                image,
+            )],
+        )
+        .unwrap();
+        let context = MeshContext {
+            atlas: &atlas,
+            models: &models,
+            shapes: &shapes,
+            definitions: Some(&definitions),
+        };
+        let faces = |chunk: &Chunk| chunk.vertex(&context).positions.len() / 4;
+
+        let mut chunk = Chunk::default();
+        chunk.data.insert(IVec3::ZERO, stone());
+        chunk.data.insert(IVec3::X, stone());
+        assert_eq!(faces(&chunk), 10);
+
+        // the stone is seen through the leaves
+        chunk.data.insert(IVec3::X, BlockData { id: leaves });
+        assert_eq!(faces(&chunk), 11);
+
+        // the shape of the definition culls, not the model
+        chunk.data.insert(IVec3::X, BlockData { id: slab });
+        assert_eq!(faces(&chunk), 11);
+    }
+
+    /// a floor at y = 0 and a roof at y = 2 over x >= 1,
+    /// the sky light under the roof fades along +X
+    fn overhang() -> Chunk {
+        let mut chunk = Chunk::default();
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                chunk.data.insert(IVec3::new(x, 0, z), stone());
+                if x >= 1 {
+                    chunk.data.insert(IVec3::new(x, 2, z), stone());
+                }
+            }
+        }
+        chunk.compute_light(None);
+        chunk
+    }
+
+    fn sky(lights: [[f32; 2]; 4]) -> [f32; 4] {
+        lights.map(|[sky, _]| sky * MAX_LIGHT_LEVEL as f32)
+    }
+
+    #[test]
+    fn test_smooth_corners() {
+        let chunk = overhang();
+        let element = Element::new([0, 0, 0], [16, 16, 16]);
+        assert_eq!(chunk.light(IVec3::new(1, 1, 5)).sky(), 14);
+        assert_eq!(chunk.light(IVec3::new(2, 1, 5)).sky(), 13);
+
+        // top face vertices: (0, 0), (1, 0), (1, 1), (0, 1) on X/Z
+        let lights = face_lights(IVec3::new(1, 0, 5), BlockFace::Up, &element, &chunk, None);
+        assert_eq!(sky(lights), [14.5, 13.5, 13.5, 14.5]);
+
+        // the open side is smoothed toward the overhang
+        let lights = face_lights(IVec3::new(0, 0, 5), BlockFace::Up, &element, &chunk, None);
+        assert_eq!(sky(lights), [15.0, 14.5, 14.5, 15.0]);
+    }
+
+    #[test]
+    fn test_blocked_corner() {
+        let element = Element::new([0, 0, 0], [16, 16, 16]);
+        let mut chunk = overhang();
+        // walls on both sides of the corner, the diagonal must not leak through
+        chunk.data.insert(IVec3::new(5, 1, 4), stone());
+        chunk.data.insert(IVec3::new(4, 1, 5), stone());
+        chunk.compute_light(None);
+
+        let lights = face_lights(IVec3::new(5, 0, 5), BlockFace::Up, &element, &chunk, None);
+        let base = chunk.light(IVec3::new(5, 1, 5)).sky() as f32;
+        assert_eq!(sky(lights)[0], base);
+
+        // the light goes through glass walls, so the corner is not hidden
+        let glass = BlockId::from_static("bevy_craft:block/glass");
+        let mut definitions = BlockDefinitions::default();
+        definitions.insert(
+            glass.clone(),
+            BlockDefinition {
+                opaque: false,
+                ..default()
+            },
+        );
+        for pos in [IVec3::new(5, 1, 4), IVec3::new(4, 1, 5)] {
+            chunk.data.insert(pos, BlockData { id: glass.clone() });
+        }
+        chunk.compute_light(Some(&definitions));
+        let lights = face_lights(
+            IVec3::new(5, 0, 5),
+            BlockFace::Up,
+            &element,
+            &chunk,
+            Some(&definitions),
+        );
+        let corner = [(5, 5), (5, 4), (4, 5), (4, 4)]
+            .map(|(x, z)| chunk.light(IVec3::new(x, 1, z)).sky() as f32)
+            .iter()
+            .sum::<f32>()
+            / 4.0;
+        assert!((sky(lights)[0] - corner).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_partial_and_flat_face() {
+        let chunk = overhang();
+
+        // a slab facing west at x = 1, lit by (0, 1, 5) and the blocks around it
+        let slab = Element::new([0, 0, 0], [16, 8, 16]);
+        let lights = face_lights(IVec3::new(1, 1, 5), BlockFace::West, &slab, &chunk, None);
+        // the top vertices are halfway between the bottom and the top corners
+        let full = face_lights(
+            IVec3::new(1, 1, 5),
+            BlockFace::West,
+            &Element::new([0, 0, 0], [16, 16, 16]),
+            &chunk,
+            None,
+        );
+        assert_eq!(lights[2], full[2]);
+        assert_eq!(lights[0][0], (full[0][0] + full[3][0]) / 2.0);
+
+        let flat = face_lights(
+            IVec3::new(1, 0, 5),
+            BlockFace::Up,
+            &Element::new([0, 0, 0], [16, 16, 16]).with_shade(false),
+            &chunk,
+            None,
+        );
+        assert_eq!(sky(flat), [14.0; 4]);
+    }
 }
@@ -1,6 +1,9 @@
+pub(crate) mod export;
 pub(crate) mod meshing;
 pub(crate) mod voxel;
 
 pub mod prelude {
+    pub use super::export::*;
+    pub use super::meshing::{ChunkMeshing, MeshContext};
     pub use super::voxel::Vertex;
 }
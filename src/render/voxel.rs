@@ -1,14 +1,27 @@
 use bevy::{
     asset::RenderAssetUsages,
     prelude::*,
-    render::mesh::{Indices, PrimitiveTopology},
+    render::{
+        mesh::{Indices, MeshVertexAttribute, PrimitiveTopology},
+        render_resource::VertexFormat,
+    },
 };
 
+use crate::chunks::prelude::*;
+
+/// sky light and block light of the vertex in 0..=1
+pub const ATTRIBUTE_LIGHT: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Light", 718_204_113, VertexFormat::Float32x2);
+
 #[derive(Default, Debug)]
 pub struct Vertex {
     pub positions: Vec<[f32; 3]>,
     pub normals: Vec<[f32; 3]>,
     pub uvs: Vec<[f32; 2]>,
+    /// sky light and block light in 0..=1
+    pub lights: Vec<[f32; 2]>,
+    /// biome color multiplied with the texture, white if the face is not tinted
+    pub tints: Vec<[f32; 3]>,
     pub indices: Vec<u32>,
 }
 
@@ -24,6 +37,12 @@ impl Vertex {
         self.uvs.reserve(other.uvs.len());
         self.uvs.extend(other.uvs);
 
+        self.lights.reserve(other.lights.len());
+        self.lights.extend(other.lights);
+
+        self.tints.reserve(other.tints.len());
+        self.tints.extend(other.tints);
+
         self.indices.reserve(other.indices.len());
         self.indices.extend(
             other
@@ -44,6 +63,17 @@ impl Extend<Vertex> for Vertex {
 
 impl From<Vertex> for Mesh {
     fn from(value: Vertex) -> Self {
+        // the standard material has no light attribute, tint the vertex instead
+        let colors = value
+            .lights
+            .iter()
+            .zip(&value.tints)
+            .map(|([sky, block], [r, g, b])| {
+                let value = brightness(sky.max(*block));
+                [value * r, value * g, value * b, 1.0]
+            })
+            .collect::<Vec<_>>();
+
         Mesh::new(
             PrimitiveTopology::TriangleList,
             RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
@@ -51,6 +81,8 @@ impl From<Vertex> for Mesh {
         .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, value.positions)
         .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, value.uvs)
         .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, value.normals)
+        .with_inserted_attribute(ATTRIBUTE_LIGHT, value.lights)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
         .with_inserted_indices(Indices::U32(value.indices))
     }
 }
@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+
+use crate::assets::prelude::*;
+
+/// show `LoadProgress` until AppLoadState::Next
+pub struct LoadingScreenPlugin;
+
+#[derive(Component)]
+pub struct LoadingScreen;
+
+#[derive(Component)]
+struct LoadingText;
+
+#[derive(Component)]
+struct LoadingBar;
+
+impl Plugin for LoadingScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppLoadState::ModelLoading), spawn_loading_screen)
+            .add_systems(
+                Update,
+                update_loading_screen
+                    .run_if(resource_changed::<LoadProgress>)
+                    .run_if(not(in_state(AppLoadState::Next))),
+            )
+            .add_systems(OnEnter(AppLoadState::Next), despawn_loading_screen);
+    }
+}
+
+fn spawn_loading_screen(mut commands: Commands) {
+    commands.spawn((Camera2d, LoadingScreen));
+
+    commands
+        .spawn((
+            LoadingScreen,
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                LoadingText,
+                Text::new("Loading..."),
+                TextFont {
+                    font_size: 18.0,
+                    ..default()
+                },
+            ));
+
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(400.0),
+                        height: Val::Px(12.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        LoadingBar,
+                        Node {
+                            width: Val::Percent(0.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.3, 0.8, 0.3)),
+                    ));
+                });
+        });
+}
+
+fn update_loading_screen(
+    state: Res<State<AppLoadState>>,
+    progress: Res<LoadProgress>,
+    mut text: Single<&mut Text, With<LoadingText>>,
+    mut bar: Single<&mut Node, With<LoadingBar>>,
+) {
+    text.0 = format!(
+        "{:?}\nmodels {}\nblocks {}\nbiomes {}\ntextures {}",
+        state.get(),
+        progress.models,
+        progress.blocks,
+        progress.biomes,
+        progress.textures
+    );
+    bar.width = Val::Percent(progress.fraction() * 100.0);
+}
+
+fn despawn_loading_screen(mut commands: Commands, query: Query<Entity, With<LoadingScreen>>) {
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,5 @@
+pub(crate) mod loading;
+
+pub mod prelude {
+    pub use super::loading::*;
+}
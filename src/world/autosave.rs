@@ -0,0 +1,431 @@
+use std::{
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use bevy::{
+    platform::collections::HashMap,
+    prelude::*,
+    tasks::{block_on, futures_lite::future, IoTaskPool, Task},
+};
+
+use crate::{chunks::prelude::*, world::prelude::*};
+
+/// the chunk was edited since it was loaded or saved
+#[derive(Component, Debug, Default)]
+pub struct DirtyChunk;
+
+#[derive(Resource, Debug, Clone)]
+pub struct AutosaveSettings {
+    /// time between two saves of the dirty chunks
+    pub interval: Duration,
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// whether the world is being saved, such as to show "saving…"
+#[derive(Resource, Debug, Default, Clone)]
+pub struct SaveStatus {
+    pub saving: bool,
+    /// `Time::elapsed` when the last save finished
+    pub last_saved: Option<Duration>,
+    pub last_error: Option<String>,
+}
+
+/// snapshots of the chunks which are not written yet, keyed by chunk position,
+/// a chunk saved twice before the write is written once
+#[derive(Debug, Clone, Default)]
+pub struct PendingChunks(Arc<Mutex<HashMap<IVec2, Arc<ChunkSave>>>>);
+
+impl PendingChunks {
+    fn lock(&self) -> MutexGuard<'_, HashMap<IVec2, Arc<ChunkSave>>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn insert(&self, save: ChunkSave) {
+        self.lock().insert(save.position, Arc::new(save));
+    }
+
+    pub fn get(&self, chunk_pos: IVec2) -> Option<Arc<ChunkSave>> {
+        self.lock().get(&chunk_pos).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn snapshot(&self) -> Vec<Arc<ChunkSave>> {
+        self.lock().values().cloned().collect()
+    }
+
+    /// forget the written snapshots, unless the chunk was saved again meanwhile
+    fn written(&self, saves: &[Arc<ChunkSave>]) {
+        let mut pending = self.lock();
+        for save in saves {
+            if pending
+                .get(&save.position)
+                .is_some_and(|pending| Arc::ptr_eq(pending, save))
+            {
+                pending.remove(&save.position);
+            }
+        }
+    }
+}
+
+/// `RegionStorage` which reads the chunks waiting to be written first,
+/// so that a chunk unloaded and loaded again before the write is not stale
+#[derive(Debug, Clone)]
+pub struct BufferedStorage {
+    pub pending: PendingChunks,
+    pub storage: RegionStorage,
+}
+
+impl ChunkStorage for BufferedStorage {
+    fn load(&self, chunk_pos: IVec2) -> Option<Chunk> {
+        let Some(save) = self.pending.get(chunk_pos) else {
+            return self.storage.load(chunk_pos);
+        };
+        ChunkSave::clone(&save)
+            .into_chunk()
+            .inspect_err(|err| error!("load chunk {} failed: {}", chunk_pos, err))
+            .ok()
+    }
+}
+
+/// writes the dirty chunks of `SavedWorld` on the io task pool, one save at a time
+#[derive(Resource)]
+pub struct ChunkSaver {
+    pending: PendingChunks,
+    storage: RegionStorage,
+    timer: Timer,
+    task: Option<Task<Result<(), SaveError>>>,
+}
+
+impl ChunkSaver {
+    pub fn new(world: &SavedWorld, interval: Duration) -> Self {
+        Self {
+            pending: PendingChunks::default(),
+            storage: world.storage(),
+            timer: Timer::new(interval, TimerMode::Repeating),
+            task: None,
+        }
+    }
+
+    pub fn pending(&self) -> &PendingChunks {
+        &self.pending
+    }
+
+    /// the storage to load the chunks from while the saver runs
+    pub fn buffered_storage(&self) -> BufferedStorage {
+        BufferedStorage {
+            pending: self.pending.clone(),
+            storage: self.storage.clone(),
+        }
+    }
+
+    /// take a snapshot of every dirty chunk
+    fn snapshot(&self, commands: &mut Commands, dirty: &DirtyChunks) {
+        for (entity, chunk, chunk_pos) in dirty {
+            self.pending.insert(ChunkSave::new(chunk_pos.0, chunk));
+            commands.entity(entity).remove::<DirtyChunk>();
+        }
+    }
+
+    /// the regions are written once for all of their pending chunks
+    fn write(
+        pending: PendingChunks,
+        storage: RegionStorage,
+        world: Option<SavedWorld>,
+    ) -> Result<(), SaveError> {
+        let saves = pending.snapshot();
+        storage.write_saves(saves.iter().map(Arc::as_ref))?;
+        pending.written(&saves);
+        if let Some(world) = world {
+            world.save_level()?;
+        }
+        Ok(())
+    }
+}
+
+pub type DirtyChunks<'w, 's> =
+    Query<'w, 's, (Entity, &'static Chunk, &'static ChunkPosition), With<DirtyChunk>>;
+
+type EditedChunks<'w, 's> =
+    Query<'w, 's, (Entity, Ref<'static, Chunk>), (Changed<Chunk>, Without<DirtyChunk>)>;
+
+/// run Startup, stream the chunks of `SavedWorld` through a `ChunkSaver`
+pub fn open_saved_world(
+    mut commands: Commands,
+    world: Res<SavedWorld>,
+    settings: Res<AutosaveSettings>,
+) {
+    let saver = ChunkSaver::new(&world, settings.interval);
+    commands.insert_resource(WorldStorage(Arc::new(saver.buffered_storage())));
+    commands.insert_resource(saver);
+}
+
+/// run FixedUpdate in AppLoadState::Next
+pub fn tick_game_time(mut world: ResMut<SavedWorld>) {
+    world.level.game_time += 1;
+}
+
+/// run Update, mark the chunks edited since they were loaded,
+/// a chunk loaded and edited since the last run is dirty too
+pub fn mark_dirty_chunks(mut commands: Commands, chunks: EditedChunks) {
+    for (entity, chunk) in &chunks {
+        if chunk.last_changed() != chunk.added() {
+            commands.entity(entity).insert(DirtyChunk);
+        }
+    }
+}
+
+/// keep a dirty chunk which is unloaded until it is written
+pub fn save_unloaded_chunk(
+    trigger: Trigger<OnRemove, Chunk>,
+    chunks: Query<(&Chunk, &ChunkPosition), With<DirtyChunk>>,
+    saver: Option<Res<ChunkSaver>>,
+) {
+    let (Some(saver), Ok((chunk, chunk_pos))) = (saver, chunks.get(trigger.target())) else {
+        return;
+    };
+    saver.pending.insert(ChunkSave::new(chunk_pos.0, chunk));
+}
+
+/// run Update, write the dirty chunks every `AutosaveSettings::interval`
+pub fn autosave(
+    mut commands: Commands,
+    time: Res<Time>,
+    world: Option<Res<SavedWorld>>,
+    dirty: DirtyChunks,
+    mut saver: ResMut<ChunkSaver>,
+    mut status: ResMut<SaveStatus>,
+) {
+    if let Some(task) = &mut saver.task {
+        let Some(result) = block_on(future::poll_once(task)) else {
+            return;
+        };
+        saver.task = None;
+        status.saving = false;
+        match result {
+            Ok(()) => {
+                status.last_saved = Some(time.elapsed());
+                status.last_error = None;
+            }
+            Err(err) => {
+                error!("autosave failed: {}", err);
+                status.last_error = Some(err.to_string());
+            }
+        }
+    }
+
+    if !saver.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    saver.snapshot(&mut commands, &dirty);
+    let pending = saver.pending.clone();
+    let storage = saver.storage.clone();
+    let world = world.map(|world| world.clone());
+    saver.task =
+        Some(IoTaskPool::get().spawn(async move { ChunkSaver::write(pending, storage, world) }));
+    status.saving = true;
+}
+
+/// run Last, wait for the running save and write everything before the app exits
+pub fn flush_on_exit(
+    mut commands: Commands,
+    mut exit: EventReader<AppExit>,
+    world: Option<Res<SavedWorld>>,
+    dirty: DirtyChunks,
+    mut saver: ResMut<ChunkSaver>,
+    mut status: ResMut<SaveStatus>,
+) {
+    if exit.is_empty() {
+        return;
+    }
+    exit.clear();
+
+    if let Some(task) = saver.task.take() {
+        if let Err(err) = block_on(task) {
+            error!("autosave failed: {}", err);
+        }
+    }
+
+    saver.snapshot(&mut commands, &dirty);
+    let world = world.map(|world| world.clone());
+    status.saving = true;
+    let result = ChunkSaver::write(saver.pending.clone(), saver.storage.clone(), world);
+    status.saving = false;
+    match result {
+        Ok(()) => info!("world saved"),
+        Err(err) => {
+            error!("save on exit failed: {}", err);
+            status.last_error = Some(err.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use crate::identity::prelude::*;
+
+    use super::*;
+
+    const EDITED: IVec3 = IVec3::new(3, 100, 4);
+
+    fn app(interval: Duration) -> (App, tempfile::TempDir, SavedWorld) {
+        let dir = tempfile::tempdir().unwrap();
+        let saves = Saves::new(dir.path());
+        let world = saves
+            .create(
+                "test",
+                LevelData::new("test", 5, GeneratorSettings::default()),
+            )
+            .unwrap();
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, WorldSavePlugin))
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<LoadedChunks>()
+            .init_resource::<StreamingSettings>()
+            .insert_resource(AutosaveSettings { interval })
+            .insert_resource(WorldGenerator(Arc::new(world.generator())))
+            .insert_resource(ChunkMaterial(Handle::default()))
+            .insert_resource(world.clone())
+            .add_systems(Update, (unload_chunks, load_chunks, finish_loading).chain());
+        app.world_mut().spawn((
+            ChunkLoader {
+                radius: 0,
+                shape: ViewShape::Square,
+            },
+            GlobalTransform::default(),
+        ));
+        (app, dir, world)
+    }
+
+    fn update_until(app: &mut App, condition: impl Fn(&mut World) -> bool) {
+        let start = Instant::now();
+        while !condition(app.world_mut()) {
+            app.update();
+            assert!(start.elapsed() < Duration::from_secs(10));
+        }
+    }
+
+    fn loaded(world: &mut World) -> bool {
+        world
+            .query_filtered::<(), (With<Chunk>, With<ChunkPosition>)>()
+            .iter(world)
+            .count()
+            == 1
+    }
+
+    fn edit(app: &mut App) {
+        let mut chunk = app
+            .world_mut()
+            .query::<&mut Chunk>()
+            .single_mut(app.world_mut())
+            .unwrap();
+        chunk.data.insert(
+            EDITED,
+            BlockData {
+                id: BlockId::from_static("bevy_craft:block/dirt"),
+            },
+        );
+    }
+
+    fn move_loader(app: &mut App, position: Vec3) {
+        let mut transform = app
+            .world_mut()
+            .query_filtered::<&mut GlobalTransform, With<ChunkLoader>>()
+            .single_mut(app.world_mut())
+            .unwrap();
+        *transform = GlobalTransform::from_translation(position);
+    }
+
+    #[test]
+    fn test_autosave() {
+        let (mut app, _dir, saved) = app(Duration::ZERO);
+        update_until(&mut app, loaded);
+        // a loaded chunk is not dirty
+        app.update();
+        let dirty = app
+            .world_mut()
+            .query::<&DirtyChunk>()
+            .iter(app.world())
+            .count();
+        assert_eq!(dirty, 0);
+
+        edit(&mut app);
+        let storage = saved.storage();
+        update_until(&mut app, |_| {
+            storage
+                .load_chunk(IVec2::ZERO)
+                .unwrap()
+                .is_some_and(|chunk| chunk.data.contains_key(&EDITED))
+        });
+        let status = app.world().resource::<SaveStatus>();
+        assert!(status.last_saved.is_some());
+        assert!(status.last_error.is_none());
+    }
+
+    #[test]
+    fn test_flush_on_exit() {
+        let (mut app, _dir, world) = app(Duration::from_secs(3600));
+        update_until(&mut app, loaded);
+        edit(&mut app);
+        app.update();
+
+        // the unloaded chunk waits in memory, and is loaded from there
+        move_loader(&mut app, Vec3::new(200.0, 0.0, 0.0));
+        update_until(&mut app, |world| {
+            world
+                .resource::<ChunkSaver>()
+                .pending()
+                .get(IVec2::ZERO)
+                .is_some()
+        });
+        assert!(world.storage().load_chunk(IVec2::ZERO).unwrap().is_none());
+
+        move_loader(&mut app, Vec3::ZERO);
+        update_until(&mut app, |world| {
+            world
+                .query::<(&Chunk, &ChunkPosition)>()
+                .iter(world)
+                .any(|(chunk, pos)| pos.0 == IVec2::ZERO && chunk.data.contains_key(&EDITED))
+        });
+
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+        let chunk = world.storage().load_chunk(IVec2::ZERO).unwrap().unwrap();
+        assert!(chunk.data.contains_key(&EDITED));
+        assert!(app.world().resource::<ChunkSaver>().pending().is_empty());
+    }
+
+    #[test]
+    fn test_edit_and_unload() {
+        let (mut app, _dir, _world) = app(Duration::from_secs(3600));
+        update_until(&mut app, loaded);
+        app.update();
+
+        // edited in the frame where the chunk is unloaded
+        edit(&mut app);
+        move_loader(&mut app, Vec3::new(200.0, 0.0, 0.0));
+        app.update();
+        let saver = app.world().resource::<ChunkSaver>();
+        assert!(saver.pending().get(IVec2::ZERO).is_some());
+        let chunk = saver.buffered_storage().load(IVec2::ZERO).unwrap();
+        assert!(chunk.data.contains_key(&EDITED));
+    }
+}
@@ -0,0 +1,265 @@
+use std::{fs, path::Path};
+
+use bevy::asset::{io::Reader, AssetLoader, LoadContext, LoadState};
+use bevy::{log, platform::collections::HashMap, prelude::*};
+use derive_more::derive::{Display, Error, From};
+use serde::{Deserialize, Serialize};
+
+use crate::{assets::prelude::*, chunks::prelude::*, identity::prelude::*};
+
+pub const BIOMES_DIR: &str = "biomes";
+
+#[derive(Debug, Error, Display, From)]
+pub enum BiomeError {
+    #[display("Failed to load biome: {}", _0)]
+    Io(std::io::Error),
+    #[display("Biome Syntax Error in {}: {}", _0, _1)]
+    #[from(ignore)]
+    JsonError(#[error(not(source))] String, serde_json::Error),
+    #[display("{}", _0)]
+    Identity(IdentityError),
+}
+
+/// biome definition, loaded from `namespace/biomes/name.json`
+/// and keyed by `namespace:name`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Asset, TypePath)]
+pub struct Biome {
+    /// a column takes the biome nearest to its temperature and humidity, both in -1..=1
+    pub temperature: f64,
+    pub humidity: f64,
+    /// block id of the top block
+    pub surface: String,
+    /// block id of the blocks under the surface
+    pub subsurface: String,
+    /// rgb of the faces with tintindex 0
+    pub grass_color: [u8; 3],
+    /// rgb of the faces with tintindex 1
+    pub foliage_color: [u8; 3],
+}
+
+impl Biome {
+    pub fn tints(&self) -> BiomeTints {
+        let color = |[r, g, b]: [u8; 3]| [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0];
+        BiomeTints {
+            grass: color(self.grass_color),
+            foliage: color(self.foliage_color),
+        }
+    }
+
+    fn distance(&self, temperature: f64, humidity: f64) -> f64 {
+        (self.temperature - temperature).powi(2) + (self.humidity - humidity).powi(2)
+    }
+}
+
+/// every biome definition, the generator picks from them
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Biomes {
+    biomes: HashMap<BiomeId, Biome>,
+}
+
+impl Biomes {
+    /// read every `namespace/biomes/*.json` under the asset root without the asset server,
+    /// `AppAssetPlugin` loads them in AppLoadState::ModelLoading
+    pub fn load(root: &Path) -> Result<Self, BiomeError> {
+        let mut result = Self::default();
+        for path in discover_assets(root, BIOMES_DIR, BiomeId::EXTENSION) {
+            let (biome_id, biome) = read_biome(root, &path)?;
+            result.insert(biome_id, biome);
+        }
+        Ok(result)
+    }
+
+    pub fn insert(&mut self, biome_id: BiomeId, biome: Biome) {
+        self.biomes.insert(biome_id, biome);
+    }
+
+    pub fn get(&self, biome_id: &BiomeId) -> Option<&Biome> {
+        self.biomes.get(biome_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.biomes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.biomes.is_empty()
+    }
+
+    /// the biome nearest to the climate, ties are broken by id so the choice is stable
+    pub fn nearest(&self, temperature: f64, humidity: f64) -> Option<(&BiomeId, &Biome)> {
+        self.biomes.iter().min_by(|(a_id, a), (b_id, b)| {
+            a.distance(temperature, humidity)
+                .total_cmp(&b.distance(temperature, humidity))
+                .then_with(|| a_id.cmp(b_id))
+        })
+    }
+
+    /// the tints of the biome, the default tints if it is not defined
+    pub fn tints(&self, biome_id: &BiomeId) -> BiomeTints {
+        self.get(biome_id).map(Biome::tints).unwrap_or_default()
+    }
+}
+
+pub struct BiomeLoader;
+
+impl AssetLoader for BiomeLoader {
+    type Asset = Biome;
+    type Settings = ();
+    type Error = BiomeError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::<u8>::new();
+        reader.read_to_end(&mut bytes).await?;
+        serde_json::from_slice::<Biome>(&bytes)
+            .map_err(|err| BiomeError::JsonError(load_context.path().display().to_string(), err))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct BiomeHandles(HashMap<BiomeId, Handle<Biome>>);
+
+/// run OnEnter AppLoadState::ModelLoading
+pub fn pre_biome_load(
+    mut commands: Commands,
+    root: Res<AssetRoot>,
+    asset_server: Res<AssetServer>,
+    mut tracker: ResMut<LoadTracker>,
+) {
+    let handles = discover_assets(&root.0, BIOMES_DIR, BiomeId::EXTENSION)
+        .into_iter()
+        .filter_map(|path| match BiomeId::try_from(path.as_str()) {
+            Ok(biome_id) => {
+                tracker.biomes.track(&path);
+                Some((biome_id, asset_server.load(path)))
+            }
+            Err(err) => {
+                log::warn!("{}", err);
+                None
+            }
+        })
+        .collect();
+    commands.insert_resource(BiomeHandles(handles));
+}
+
+/// run Update in AppLoadState::ModelLoaded, wait until every biome is loaded
+pub fn collect_biomes(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    handles: Res<BiomeHandles>,
+    mut biome_assets: ResMut<Assets<Biome>>,
+) {
+    let loading = handles.0.values().any(|handle| {
+        !matches!(
+            asset_server.load_state(handle.id()),
+            LoadState::Loaded | LoadState::Failed(_)
+        )
+    });
+    if loading {
+        return;
+    }
+
+    let mut result = Biomes::default();
+    for (biome_id, handle) in &handles.0 {
+        match biome_assets.remove(handle.id()) {
+            Some(biome) => result.insert(biome_id.clone(), biome),
+            None => log::error!("biome {} load failed", biome_id),
+        }
+    }
+
+    commands.remove_resource::<BiomeHandles>();
+    commands.insert_resource(result);
+}
+
+/// check every biome under the asset root like `Biomes::load` without stopping at the first error,
+/// the number of biomes and the errors
+pub fn validate_biomes(root: &Path) -> (usize, Vec<BiomeError>) {
+    let paths = discover_assets(root, BIOMES_DIR, BiomeId::EXTENSION);
+    let errors = paths
+        .iter()
+        .filter_map(|path| read_biome(root, path).err())
+        .collect();
+    (paths.len(), errors)
+}
+
+fn read_biome(root: &Path, path: &str) -> Result<(BiomeId, Biome), BiomeError> {
+    let biome_id = BiomeId::try_from(path)?;
+    let bytes = fs::read(root.join(path))?;
+    let biome = serde_json::from_slice(&bytes)
+        .map_err(|err| BiomeError::JsonError(path.to_string(), err))?;
+    Ok((biome_id, biome))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_load_biomes() {
+        let biomes = Biomes::load(Path::new(ASSET_ROOT)).unwrap();
+        let plains = BiomeId::try_from("bevy_craft:plains").unwrap();
+        let desert = BiomeId::try_from("bevy_craft:desert").unwrap();
+        assert!(biomes.len() >= 2);
+        assert_eq!(
+            biomes.get(&desert).unwrap().surface,
+            "bevy_craft:block/sand"
+        );
+
+        let (nearest, _) = biomes.nearest(0.9, -0.6).unwrap();
+        assert_eq!(nearest, &desert);
+        let (nearest, _) = biomes.nearest(0.3, 0.0).unwrap();
+        assert_eq!(nearest, &plains);
+
+        let tints = biomes.tints(&plains);
+        assert_eq!(tints.grass, [145.0 / 255.0, 189.0 / 255.0, 89.0 / 255.0]);
+        assert_eq!(
+            biomes.tints(&BiomeId::try_from("bevy_craft:none").unwrap()),
+            BiomeTints::default()
+        );
+    }
+
+    #[test]
+    fn test_biome_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("pack/biomes")).unwrap();
+        fs::write(root.join("pack/biomes/broken.json"), "{ \"temperature\": ").unwrap();
+
+        let err = Biomes::load(root).unwrap_err();
+        assert!(
+            matches!(err, BiomeError::JsonError(ref path, _) if path == "pack/biomes/broken.json")
+        );
+        assert!(Biomes::default().nearest(0.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_validate_biomes() {
+        let (biomes, errors) = validate_biomes(Path::new(ASSET_ROOT));
+        assert!(biomes >= 2);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("pack/biomes")).unwrap();
+        fs::write(
+            root.join("pack/biomes/broken.json"),
+            "{ \"temperature\": 0.5 }",
+        )
+        .unwrap();
+        fs::write(root.join("pack/biomes/cut.json"), "{ \"temperature\": ").unwrap();
+        let (biomes, errors) = validate_biomes(root);
+        assert_eq!(biomes, 2);
+        assert_eq!(errors.len(), 2);
+        assert!(errors
+            .iter()
+            .all(|err| matches!(err, BiomeError::JsonError(..))));
+    }
+}
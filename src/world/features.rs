@@ -0,0 +1,660 @@
+use std::sync::Mutex;
+
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{chunks::prelude::*, identity::prelude::*};
+
+/// a feature may reach this many chunks away from the chunk it is placed in,
+/// blocks further away are dropped
+pub const FEATURE_REACH: i32 = 1;
+
+/// number of sources `DeferredWrites` holds at most, the least recently used is dropped
+/// and placed again if a chunk still needs it
+pub const MAX_DEFERRED_SOURCES: usize = 1024;
+
+/// multi-block decoration placed after the terrain, the block ids are `BlockId` strings
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Feature {
+    /// a trunk of `log` under a round crown of `leaves`, grows on the blocks of `on`
+    Tree {
+        log: String,
+        leaves: String,
+        min_height: i32,
+        max_height: i32,
+        on: Vec<String>,
+    },
+    /// a random walk of `ore` replacing `replace`, `size` blocks at most
+    OreVein {
+        ore: String,
+        replace: String,
+        size: u32,
+        min_y: i32,
+        max_y: i32,
+    },
+    /// a ball of `block` sunk into the surface, any surface if `on` is empty
+    Boulder {
+        block: String,
+        min_radius: i32,
+        max_radius: i32,
+        on: Vec<String>,
+    },
+}
+
+/// how many times a feature is tried in every chunk and where
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PlacedFeature {
+    pub feature: Feature,
+    /// tries per chunk
+    pub count: u32,
+    /// probability of each try, 1 if it is missing
+    #[serde(default = "PlacedFeature::always")]
+    pub chance: f64,
+    /// `BiomeId`s of the columns the feature is placed in, every column if it is empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub biomes: Vec<String>,
+}
+
+impl PlacedFeature {
+    pub fn new(feature: Feature, count: u32) -> Self {
+        Self {
+            feature,
+            count,
+            chance: 1.0,
+            biomes: Vec::new(),
+        }
+    }
+
+    pub fn with_chance(mut self, chance: f64) -> Self {
+        self.chance = chance;
+        self
+    }
+
+    pub fn with_biome(mut self, biome_id: &str) -> Self {
+        self.biomes.push(biome_id.to_string());
+        self
+    }
+
+    fn always() -> f64 {
+        1.0
+    }
+
+    /// the features of a new world
+    pub fn defaults() -> Vec<Self> {
+        let tree = Feature::Tree {
+            log: "bevy_craft:block/oak_log".to_string(),
+            leaves: "bevy_craft:block/oak_leaves".to_string(),
+            min_height: 4,
+            max_height: 6,
+            on: vec![
+                "bevy_craft:block/grass_block".to_string(),
+                "bevy_craft:block/dirt".to_string(),
+            ],
+        };
+        vec![
+            PlacedFeature::new(
+                Feature::OreVein {
+                    ore: "bevy_craft:block/coal_ore".to_string(),
+                    replace: "bevy_craft:block/stone".to_string(),
+                    size: 8,
+                    min_y: 1,
+                    max_y: 14,
+                },
+                6,
+            ),
+            PlacedFeature::new(
+                Feature::Boulder {
+                    block: "bevy_craft:block/stone".to_string(),
+                    min_radius: 1,
+                    max_radius: 2,
+                    on: vec!["bevy_craft:block/grass_block".to_string()],
+                },
+                1,
+            )
+            .with_chance(0.2),
+            PlacedFeature::new(tree.clone(), 6).with_biome("bevy_craft:forest"),
+            PlacedFeature::new(tree, 1)
+                .with_chance(0.5)
+                .with_biome("bevy_craft:plains"),
+        ]
+    }
+}
+
+/// what a placed block may overwrite
+#[derive(Debug, Clone, PartialEq)]
+pub enum Replace {
+    Air,
+    AirOr(BlockId),
+    Block(BlockId),
+}
+
+impl Replace {
+    pub fn matches(&self, current: Option<&BlockId>) -> bool {
+        match (self, current) {
+            (_, None) => matches!(self, Replace::Air | Replace::AirOr(_)),
+            (Replace::AirOr(id) | Replace::Block(id), Some(current)) => id == current,
+            (Replace::Air, Some(_)) => false,
+        }
+    }
+}
+
+/// a block of a feature, written when the chunk containing it is generated
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockWrite {
+    pub pos: IVec3,
+    pub id: BlockId,
+    pub replace: Replace,
+}
+
+/// the writes of the features of a chunk, grouped by the chunk they fall in
+pub type FeatureWrites = HashMap<IVec2, Vec<BlockWrite>>;
+
+/// the terrain a feature is placed on
+pub trait FeatureTerrain {
+    /// y and id of the highest block of the column
+    fn top(&self, x: i32, z: i32) -> (i32, BlockId);
+    fn biome(&self, x: i32, z: i32) -> Option<BiomeId>;
+}
+
+/// splitmix64, stable across platforms and versions so that worlds do not change
+#[derive(Debug, Clone)]
+pub struct FeatureRng(u64);
+
+impl FeatureRng {
+    /// a different sequence for every seed, chunk and feature
+    pub fn new(seed: u32, chunk_pos: IVec2, feature: usize) -> Self {
+        let mut rng = Self(seed as u64);
+        for value in [chunk_pos.x as u32, chunk_pos.y as u32, feature as u32] {
+            rng.0 ^= rng.next_u64() ^ value as u64;
+        }
+        rng
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// uniform in 0..1
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// uniform in min..=max
+    pub fn range(&mut self, min: i32, max: i32) -> i32 {
+        if max <= min {
+            return min;
+        }
+        let span = (max as i64 - min as i64 + 1) as u64;
+        (min as i64 + (self.next_u64() % span) as i64) as i32
+    }
+}
+
+/// collects the writes of the features of one chunk
+struct Placer {
+    chunk_pos: IVec2,
+    writes: FeatureWrites,
+}
+
+impl Placer {
+    fn set(&mut self, pos: IVec3, id: &BlockId, replace: Replace) {
+        let target = chunk_position(pos);
+        if (target - self.chunk_pos).abs().max_element() > FEATURE_REACH {
+            return;
+        }
+        self.writes.entry(target).or_default().push(BlockWrite {
+            pos,
+            id: id.clone(),
+            replace,
+        });
+    }
+}
+
+impl Feature {
+    fn place(
+        &self,
+        terrain: &dyn FeatureTerrain,
+        rng: &mut FeatureRng,
+        column: IVec2,
+        placer: &mut Placer,
+    ) {
+        match self {
+            Feature::Tree {
+                log,
+                leaves,
+                min_height,
+                max_height,
+                on,
+            } => {
+                let (y, top) = terrain.top(column.x, column.y);
+                if !on.contains(&top.to_string()) {
+                    return;
+                }
+                let (Ok(log), Ok(leaves)) = (
+                    BlockId::try_from(log.as_str()),
+                    BlockId::try_from(leaves.as_str()),
+                ) else {
+                    return;
+                };
+                let height = rng.range(*min_height, *max_height);
+                let crown = y + height;
+                // two wide layers under two narrow layers, the corners are random
+                for dy in -2..=1 {
+                    let radius: i32 = if dy < 0 { 2 } else { 1 };
+                    for dx in -radius..=radius {
+                        for dz in -radius..=radius {
+                            let corner = dx.abs() == radius && dz.abs() == radius;
+                            if corner && (dy == 1 || rng.next_f64() < 0.5) {
+                                continue;
+                            }
+                            let pos = IVec3::new(column.x + dx, crown + dy, column.y + dz);
+                            placer.set(pos, &leaves, Replace::Air);
+                        }
+                    }
+                }
+                for dy in 1..=height {
+                    let pos = IVec3::new(column.x, y + dy, column.y);
+                    placer.set(pos, &log, Replace::AirOr(leaves.clone()));
+                }
+            }
+            Feature::OreVein {
+                ore,
+                replace,
+                size,
+                min_y,
+                max_y,
+            } => {
+                let (Ok(ore), Ok(replace)) = (
+                    BlockId::try_from(ore.as_str()),
+                    BlockId::try_from(replace.as_str()),
+                ) else {
+                    return;
+                };
+                let mut pos = IVec3::new(column.x, rng.range(*min_y, *max_y), column.y);
+                for _ in 0..*size {
+                    placer.set(pos, &ore, Replace::Block(replace.clone()));
+                    let axis = rng.range(0, 2) as usize;
+                    pos[axis] += if rng.next_f64() < 0.5 { -1 } else { 1 };
+                }
+            }
+            Feature::Boulder {
+                block,
+                min_radius,
+                max_radius,
+                on,
+            } => {
+                let (y, top) = terrain.top(column.x, column.y);
+                if !on.is_empty() && !on.contains(&top.to_string()) {
+                    return;
+                }
+                let Ok(block) = BlockId::try_from(block.as_str()) else {
+                    return;
+                };
+                let radius = rng.range(*min_radius, *max_radius);
+                let center = IVec3::new(column.x, y, column.y);
+                for dx in -radius..=radius {
+                    for dy in -radius..=radius {
+                        for dz in -radius..=radius {
+                            let offset = IVec3::new(dx, dy, dz);
+                            if offset.length_squared() <= radius * radius + 1 {
+                                placer.set(center + offset, &block, Replace::Air);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// the writes of every feature placed in the chunk, in the order they apply
+pub fn place_features(
+    seed: u32,
+    features: &[PlacedFeature],
+    terrain: &dyn FeatureTerrain,
+    chunk_pos: IVec2,
+) -> FeatureWrites {
+    let mut placer = Placer {
+        chunk_pos,
+        writes: default(),
+    };
+    let origin = chunk_pos * CHUNK_SIZE;
+    for (index, placed) in features.iter().enumerate() {
+        let mut rng = FeatureRng::new(seed, chunk_pos, index);
+        for _ in 0..placed.count {
+            let column =
+                origin + IVec2::new(rng.range(0, CHUNK_SIZE - 1), rng.range(0, CHUNK_SIZE - 1));
+            // drawn for every try, so the other tries do not depend on the chance
+            let roll = rng.next_f64();
+            if roll >= placed.chance {
+                continue;
+            }
+            if !placed.biomes.is_empty() {
+                let biome = terrain.biome(column.x, column.y);
+                if !biome.is_some_and(|biome| placed.biomes.contains(&biome.to_string())) {
+                    continue;
+                }
+            }
+            placed.feature.place(terrain, &mut rng, column, &mut placer);
+        }
+    }
+    placer.writes
+}
+
+/// apply the writes whose `Replace` matches the block they overwrite
+pub fn apply_writes(chunk: &mut Chunk, writes: &[BlockWrite]) {
+    for write in writes {
+        let current = chunk.data.get(&write.pos).map(|block| &block.id);
+        if write.replace.matches(current) {
+            chunk.data.insert(
+                write.pos,
+                BlockData {
+                    id: write.id.clone(),
+                },
+            );
+        }
+    }
+}
+
+struct DeferredSource {
+    writes: FeatureWrites,
+    /// chunks which have not taken their writes yet
+    waiting: HashSet<IVec2>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct DeferredSources {
+    sources: HashMap<IVec2, DeferredSource>,
+    clock: u64,
+}
+
+/// writes of placed features held until the chunks they reach are generated,
+/// a source is dropped when every chunk it reaches has taken its writes or was loaded,
+/// at most `MAX_DEFERRED_SOURCES` are held
+#[derive(Default)]
+pub struct DeferredWrites {
+    sources: Mutex<DeferredSources>,
+}
+
+impl std::fmt::Debug for DeferredWrites {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeferredWrites")
+            .field("sources", &self.len())
+            .finish()
+    }
+}
+
+impl Clone for DeferredWrites {
+    /// the writes depend on the generator settings, which may change in a clone
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl DeferredWrites {
+    /// the writes of the features of `source` which fall in `target`,
+    /// `place` runs if `source` is not held
+    pub fn take(
+        &self,
+        source: IVec2,
+        target: IVec2,
+        place: impl FnOnce() -> FeatureWrites,
+    ) -> Vec<BlockWrite> {
+        if let Some(writes) = self.sources.lock().unwrap().take(source, target) {
+            return writes;
+        }
+
+        // placed without the lock, other chunks keep generating
+        let writes = place();
+        let mut sources = self.sources.lock().unwrap();
+        if !sources.sources.contains_key(&source) {
+            if sources.sources.len() >= MAX_DEFERRED_SOURCES {
+                sources.evict();
+            }
+            sources.sources.insert(
+                source,
+                DeferredSource {
+                    // every chunk in reach takes from the source, even without writes
+                    waiting: chunks_in_reach(source).collect(),
+                    writes,
+                    last_used: 0,
+                },
+            );
+        }
+        sources.take(source, target).unwrap_or_default()
+    }
+
+    /// `target` was loaded from storage with the writes already in it,
+    /// the sources in reach no longer wait for it
+    pub fn skip(&self, target: IVec2) {
+        let mut sources = self.sources.lock().unwrap();
+        for source in chunks_in_reach(target) {
+            sources.remove_waiting(source, target);
+        }
+    }
+
+    /// whether the writes of `source` wait for a chunk
+    pub fn contains(&self, source: IVec2) -> bool {
+        self.sources.lock().unwrap().sources.contains_key(&source)
+    }
+
+    /// number of sources held
+    pub fn len(&self) -> usize {
+        self.sources.lock().unwrap().sources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl DeferredSources {
+    fn take(&mut self, source: IVec2, target: IVec2) -> Option<Vec<BlockWrite>> {
+        self.clock += 1;
+        let held = self.sources.get_mut(&source)?;
+        held.last_used = self.clock;
+        // a chunk generated again gets the same writes
+        let result = held.writes.get(&target).cloned().unwrap_or_default();
+        self.remove_waiting(source, target);
+        Some(result)
+    }
+
+    fn remove_waiting(&mut self, source: IVec2, target: IVec2) {
+        if let Some(held) = self.sources.get_mut(&source) {
+            held.waiting.remove(&target);
+            if held.waiting.is_empty() {
+                self.sources.remove(&source);
+            }
+        }
+    }
+
+    fn evict(&mut self) {
+        let oldest = self
+            .sources
+            .iter()
+            .min_by_key(|(_, held)| held.last_used)
+            .map(|(source, _)| *source);
+        if let Some(oldest) = oldest {
+            self.sources.remove(&oldest);
+        }
+    }
+}
+
+/// the chunks a feature placed in `chunk_pos` may write to, which are also
+/// the chunks whose features may write to `chunk_pos`
+fn chunks_in_reach(chunk_pos: IVec2) -> impl Iterator<Item = IVec2> {
+    (-FEATURE_REACH..=FEATURE_REACH)
+        .flat_map(|z| (-FEATURE_REACH..=FEATURE_REACH).map(move |x| IVec2::new(x, z)))
+        .map(move |offset| chunk_pos + offset)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// grass at y 10 everywhere
+    struct FlatTerrain;
+
+    impl FeatureTerrain for FlatTerrain {
+        fn top(&self, _x: i32, _z: i32) -> (i32, BlockId) {
+            (10, BlockId::from_static("bevy_craft:block/grass_block"))
+        }
+
+        fn biome(&self, x: i32, _z: i32) -> Option<BiomeId> {
+            (x < 0).then(|| BiomeId::try_from("bevy_craft:forest").unwrap())
+        }
+    }
+
+    #[test]
+    fn test_feature_rng() {
+        let mut rng = FeatureRng::new(42, IVec2::new(-1, 3), 0);
+        let values = (0..100).map(|_| rng.range(-2, 5)).collect::<Vec<_>>();
+        assert!(values.iter().all(|value| (-2..=5).contains(value)));
+        assert!(values.contains(&-2) && values.contains(&5));
+        assert_eq!(rng.range(3, 3), 3);
+
+        let mut again = FeatureRng::new(42, IVec2::new(-1, 3), 0);
+        assert_eq!(
+            (0..100).map(|_| again.range(-2, 5)).collect::<Vec<_>>(),
+            values
+        );
+        let first = |seed, chunk_pos, feature| FeatureRng::new(seed, chunk_pos, feature).next_u64();
+        assert_ne!(first(42, IVec2::ZERO, 0), first(43, IVec2::ZERO, 0));
+        assert_ne!(first(42, IVec2::ZERO, 0), first(42, IVec2::X, 0));
+        assert_ne!(first(42, IVec2::ZERO, 0), first(42, IVec2::ZERO, 1));
+    }
+
+    #[test]
+    fn test_replace() {
+        let stone = BlockId::from_static("bevy_craft:block/stone");
+        let dirt = BlockId::from_static("bevy_craft:block/dirt");
+        assert!(Replace::Air.matches(None));
+        assert!(!Replace::Air.matches(Some(&stone)));
+        assert!(Replace::AirOr(stone.clone()).matches(None));
+        assert!(Replace::AirOr(stone.clone()).matches(Some(&stone)));
+        assert!(!Replace::AirOr(stone.clone()).matches(Some(&dirt)));
+        assert!(!Replace::Block(stone.clone()).matches(None));
+        assert!(Replace::Block(stone.clone()).matches(Some(&stone)));
+
+        let mut chunk = Chunk::default();
+        chunk
+            .data
+            .insert(IVec3::ZERO, BlockData { id: stone.clone() });
+        let write = |pos, replace| BlockWrite {
+            pos,
+            id: dirt.clone(),
+            replace,
+        };
+        apply_writes(
+            &mut chunk,
+            &[
+                write(IVec3::ZERO, Replace::Air),
+                write(IVec3::Y, Replace::Block(stone.clone())),
+                write(IVec3::X, Replace::Air),
+            ],
+        );
+        assert_eq!(chunk.data[&IVec3::ZERO].id, stone);
+        assert!(!chunk.data.contains_key(&IVec3::Y));
+        assert_eq!(chunk.data[&IVec3::X].id, dirt);
+    }
+
+    #[test]
+    fn test_place_features() {
+        let features = PlacedFeature::defaults();
+        let chunk_pos = IVec2::new(-1, 0);
+        let writes = place_features(7, &features, &FlatTerrain, chunk_pos);
+        assert_eq!(
+            writes,
+            place_features(7, &features, &FlatTerrain, chunk_pos)
+        );
+
+        // the trees at the borders reach into the neighbors, but not further
+        assert!(writes.len() > 1);
+        for (target, writes) in &writes {
+            assert!((*target - chunk_pos).abs().max_element() <= FEATURE_REACH);
+            assert!(writes
+                .iter()
+                .all(|write| chunk_position(write.pos) == *target));
+        }
+        let logs = writes
+            .values()
+            .flatten()
+            .filter(|write| write.id.to_string() == "bevy_craft:block/oak_log")
+            .collect::<Vec<_>>();
+        assert!(!logs.is_empty());
+        assert!(logs.iter().all(|write| write.pos.y > 10));
+
+        // the trees are only placed in the forest
+        let writes = place_features(7, &features, &FlatTerrain, IVec2::new(1, 0));
+        assert!(writes
+            .values()
+            .flatten()
+            .all(|write| write.id.to_string() != "bevy_craft:block/oak_log"));
+    }
+
+    #[test]
+    fn test_deferred_writes() {
+        let deferred = DeferredWrites::default();
+        let source = IVec2::ZERO;
+        let write = |x| BlockWrite {
+            pos: IVec3::new(x, 0, 0),
+            id: BlockId::from_static("bevy_craft:block/stone"),
+            replace: Replace::Air,
+        };
+        let place = || {
+            let mut writes = FeatureWrites::default();
+            writes.insert(IVec2::ZERO, vec![write(0)]);
+            writes.insert(IVec2::X, vec![write(16)]);
+            writes
+        };
+
+        assert_eq!(deferred.take(source, IVec2::ZERO, place), vec![write(0)]);
+        // held for the neighbor
+        assert_eq!(deferred.len(), 1);
+        let taken = deferred.take(source, IVec2::ZERO, || unreachable!());
+        assert_eq!(taken, vec![write(0)]);
+        assert!(deferred
+            .take(source, IVec2::Y, || unreachable!())
+            .is_empty());
+        assert_eq!(
+            deferred.take(source, IVec2::X, || unreachable!()),
+            vec![write(16)]
+        );
+        for z in -1..=1 {
+            for x in -1..=1 {
+                deferred.take(source, IVec2::new(x, z), || unreachable!());
+            }
+        }
+        assert!(deferred.is_empty());
+
+        // placed again once every neighbor took its writes
+        assert_eq!(deferred.take(source, IVec2::X, place), vec![write(16)]);
+        // the neighbors loaded from storage are not waited for
+        deferred.skip(IVec2::ZERO);
+        for z in -1..=1 {
+            deferred.skip(IVec2::new(-1, z));
+            deferred.skip(IVec2::new(1, z));
+        }
+        assert!(deferred.contains(source));
+        deferred.skip(IVec2::NEG_Y);
+        deferred.skip(IVec2::Y);
+        assert!(deferred.is_empty());
+
+        // the least recently used source is dropped
+        let sources = (0..=MAX_DEFERRED_SOURCES as i32).map(|x| IVec2::new(x * 10, 0));
+        for source in sources {
+            deferred.take(source, source, FeatureWrites::default);
+            deferred.take(IVec2::ZERO, IVec2::ZERO, place);
+        }
+        assert_eq!(deferred.len(), MAX_DEFERRED_SOURCES);
+        assert!(!deferred.contains(IVec2::new(10, 0)));
+        assert!(deferred.contains(IVec2::ZERO));
+        assert!(deferred.contains(IVec2::new(MAX_DEFERRED_SOURCES as i32 * 10, 0)));
+    }
+}
@@ -0,0 +1,607 @@
+use std::sync::Arc;
+
+use bevy::{math::DVec3, platform::collections::HashMap, prelude::*};
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+use serde::{Deserialize, Serialize};
+
+use crate::{chunks::prelude::*, identity::prelude::*, world::prelude::*};
+
+/// how far `NoiseGenerator::spawn` searches for an open column, in blocks
+pub const SPAWN_RADIUS: i32 = 32;
+/// how far the top of a spawn column may be from the height map
+pub const SPAWN_TOLERANCE: i32 = 2;
+
+/// build the chunk at a chunk position, must give the same chunk for the same position
+pub trait ChunkGenerator: Send + Sync + 'static {
+    fn generate(&self, chunk_pos: IVec2) -> Chunk;
+
+    /// the chunk was loaded from storage instead of generated
+    fn loaded(&self, _chunk_pos: IVec2) {}
+}
+
+/// chunks saved on disk, tried before the generator
+pub trait ChunkStorage: Send + Sync + 'static {
+    fn load(&self, chunk_pos: IVec2) -> Option<Chunk>;
+}
+
+#[derive(Resource, Clone)]
+pub struct WorldGenerator(pub Arc<dyn ChunkGenerator>);
+
+#[derive(Resource, Clone)]
+pub struct WorldStorage(pub Arc<dyn ChunkStorage>);
+
+/// caves carved out of the terrain, the frequencies are in 1 / blocks
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct CaveSettings {
+    pub enabled: bool,
+    /// nothing is carved at or below this y, so the world has a floor
+    pub min_y: i32,
+    /// cheese caves and air pockets stay this many blocks under the surface,
+    /// only spaghetti caves open to the surface
+    pub cover: i32,
+    /// large caverns where the noise is above the threshold
+    pub cheese_frequency: f64,
+    pub cheese_threshold: f64,
+    /// long tunnels where two noises are both near 0
+    pub spaghetti_frequency: f64,
+    pub spaghetti_width: f64,
+    /// small scattered holes where the noise is above the threshold
+    pub pocket_frequency: f64,
+    pub pocket_threshold: f64,
+}
+
+impl Default for CaveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_y: 1,
+            cover: 4,
+            cheese_frequency: 0.03,
+            cheese_threshold: 0.45,
+            spaghetti_frequency: 0.035,
+            spaghetti_width: 0.07,
+            pocket_frequency: 0.15,
+            pocket_threshold: 0.6,
+        }
+    }
+}
+
+/// terrain from a density function, a height map of fractal perlin noise
+/// shaped by 3d noise into overhangs and carved into caves
+#[derive(Debug, Clone)]
+pub struct NoiseGenerator {
+    pub seed: u32,
+    /// height of the terrain where the noise is 0
+    pub base_height: i32,
+    /// the terrain goes up and down by this many blocks
+    pub amplitude: f64,
+    pub frequency: f64,
+    /// the 3d noise moves the surface by up to this many blocks,
+    /// 0 gives a plain height map
+    pub overhang: f64,
+    pub overhang_frequency: f64,
+    pub caves: CaveSettings,
+    /// number of `subsurface` blocks under the surface
+    pub soil_depth: i32,
+    pub surface: BlockId,
+    pub subsurface: BlockId,
+    pub stone: BlockId,
+    /// the biome of a column replaces `surface` and `subsurface`, unused if empty
+    pub biomes: Arc<Biomes>,
+    /// placed after the terrain in this order
+    pub features: Vec<PlacedFeature>,
+    deferred: DeferredWrites,
+    noise: Fbm<Perlin>,
+    temperature: Fbm<Perlin>,
+    humidity: Fbm<Perlin>,
+    density: Fbm<Perlin>,
+    cheese: Fbm<Perlin>,
+    spaghetti: [Perlin; 2],
+    pockets: Perlin,
+}
+
+impl NoiseGenerator {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            seed,
+            base_height: 16,
+            amplitude: 6.0,
+            frequency: 0.02,
+            overhang: 4.0,
+            overhang_frequency: 0.05,
+            caves: default(),
+            soil_depth: 3,
+            surface: BlockId::from_static("bevy_craft:block/grass_block"),
+            subsurface: BlockId::from_static("bevy_craft:block/dirt"),
+            stone: BlockId::from_static("bevy_craft:block/stone"),
+            biomes: default(),
+            features: PlacedFeature::defaults(),
+            deferred: default(),
+            noise: Self::noise(seed, 0.02),
+            temperature: Self::climate_noise(seed.wrapping_add(1)),
+            humidity: Self::climate_noise(seed.wrapping_add(2)),
+            // the 3d noises are sampled at frequency 1 and their coordinates are scaled
+            density: Fbm::<Perlin>::new(seed.wrapping_add(3)).set_octaves(3),
+            cheese: Fbm::<Perlin>::new(seed.wrapping_add(4)).set_octaves(2),
+            spaghetti: [
+                Perlin::new(seed.wrapping_add(5)),
+                Perlin::new(seed.wrapping_add(6)),
+            ],
+            pockets: Perlin::new(seed.wrapping_add(7)),
+        }
+    }
+
+    pub fn with_biomes(mut self, biomes: Arc<Biomes>) -> Self {
+        self.biomes = biomes;
+        self
+    }
+
+    pub fn with_frequency(mut self, frequency: f64) -> Self {
+        self.frequency = frequency;
+        self.noise = Self::noise(self.seed, frequency);
+        self
+    }
+
+    fn noise(seed: u32, frequency: f64) -> Fbm<Perlin> {
+        Fbm::<Perlin>::new(seed)
+            .set_frequency(frequency)
+            .set_octaves(4)
+    }
+
+    /// biomes change over hundreds of blocks
+    fn climate_noise(seed: u32) -> Fbm<Perlin> {
+        Fbm::<Perlin>::new(seed).set_frequency(0.004).set_octaves(2)
+    }
+
+    /// temperature and humidity of the column
+    pub fn climate(&self, x: i32, z: i32) -> (f64, f64) {
+        let point = [x as f64, z as f64];
+        (self.temperature.get(point), self.humidity.get(point))
+    }
+
+    /// the biome of the column, `None` without biomes
+    pub fn biome(&self, x: i32, z: i32) -> Option<(&BiomeId, &Biome)> {
+        let (temperature, humidity) = self.climate(x, z);
+        self.biomes.nearest(temperature, humidity)
+    }
+
+    /// the surface and subsurface blocks of a biome, the generator's for invalid ids
+    fn soil(&self, biome_id: Option<&BiomeId>) -> (BlockId, BlockId) {
+        let biome = biome_id.and_then(|biome_id| self.biomes.get(biome_id));
+        match biome.map(|biome| {
+            (
+                BlockId::try_from(biome.surface.as_str()),
+                BlockId::try_from(biome.subsurface.as_str()),
+            )
+        }) {
+            Some((Ok(surface), Ok(subsurface))) => (surface, subsurface),
+            _ => (self.surface.clone(), self.subsurface.clone()),
+        }
+    }
+
+    /// y of the height map, the surface before overhangs and caves
+    pub fn height(&self, x: i32, z: i32) -> i32 {
+        let value = self.noise.get([x as f64, z as f64]);
+        self.base_height + (value * self.amplitude).round() as i32
+    }
+
+    /// y of the highest block of the column
+    pub fn surface_height(&self, x: i32, z: i32) -> i32 {
+        self.column(x, z, &self.surface, &self.subsurface)
+            .next()
+            .map(|(y, _)| y)
+            .unwrap_or_default()
+    }
+
+    /// above the open column nearest to `center`, whose top is the terrain near the height map
+    /// with nothing placed on it, so the player neither lands in a cave nor in a tree
+    pub fn spawn(&self, center: IVec2) -> IVec3 {
+        let mut tops = HashMap::<IVec2, HashMap<IVec2, i32>>::default();
+        for radius in 0..=SPAWN_RADIUS {
+            let ring = (-radius..=radius)
+                .flat_map(|dz| (-radius..=radius).map(move |dx| IVec2::new(dx, dz)))
+                .filter(|offset| offset.abs().max_element() == radius)
+                .map(|offset| center + offset);
+            for column in ring {
+                let chunk_pos = chunk_position(IVec3::new(column.x, 0, column.y));
+                let chunk_tops = tops
+                    .entry(chunk_pos)
+                    .or_insert_with(|| column_tops(&self.generate(chunk_pos)));
+                let top = self.surface_height(column.x, column.y);
+                if chunk_tops.get(&column) == Some(&top)
+                    && (top - self.height(column.x, column.y)).abs() <= SPAWN_TOLERANCE
+                {
+                    return IVec3::new(column.x, top + 1, column.y);
+                }
+            }
+        }
+        IVec3::new(
+            center.x,
+            self.surface_height(center.x, center.y) + 1,
+            center.y,
+        )
+    }
+
+    /// the terrain is solid where the density is not negative, y 0 is always solid
+    pub fn density(&self, pos: IVec3, height: i32) -> f64 {
+        if pos.y <= 0 {
+            return f64::INFINITY;
+        }
+        let mut density = (height - pos.y) as f64;
+        if self.overhang > 0.0 {
+            let point = (pos.as_dvec3() * self.overhang_frequency).to_array();
+            density += self.density.get(point) * self.overhang;
+        }
+        density
+    }
+
+    /// whether a cave removes the block, `depth` is the number of solid blocks above it
+    pub fn is_cave(&self, pos: IVec3, depth: i32) -> bool {
+        let caves = &self.caves;
+        if !caves.enabled || pos.y <= caves.min_y.max(0) {
+            return false;
+        }
+        let pos = pos.as_dvec3();
+
+        let [first, second] = &self.spaghetti;
+        let point = (pos * caves.spaghetti_frequency).to_array();
+        if first.get(point).abs() < caves.spaghetti_width
+            && second.get(point).abs() < caves.spaghetti_width
+        {
+            return true;
+        }
+
+        if depth < caves.cover {
+            return false;
+        }
+        // flattened, caverns are wider than they are high
+        let cheese = pos * DVec3::new(1.0, 2.0, 1.0) * caves.cheese_frequency;
+        self.cheese.get(cheese.to_array()) > caves.cheese_threshold
+            || self.pockets.get((pos * caves.pocket_frequency).to_array()) > caves.pocket_threshold
+    }
+
+    /// the solid blocks of the column from the top down
+    fn column<'a>(
+        &'a self,
+        x: i32,
+        z: i32,
+        surface: &'a BlockId,
+        subsurface: &'a BlockId,
+    ) -> impl Iterator<Item = (i32, &'a BlockId)> + 'a {
+        let height = self.height(x, z);
+        let top = height.max(0) + self.overhang.max(0.0).ceil() as i32;
+        // solid blocks above the current one, -1 under air
+        let mut depth = -1;
+        (0..=top).rev().filter_map(move |y| {
+            let pos = IVec3::new(x, y, z);
+            if self.density(pos, height) < 0.0 {
+                depth = -1;
+                return None;
+            }
+            depth += 1;
+            // the materials come from the terrain before the caves are carved
+            if self.is_cave(pos, depth) {
+                return None;
+            }
+            let id = match depth {
+                0 => surface,
+                depth if depth <= self.soil_depth => subsurface,
+                _ => &self.stone,
+            };
+            Some((y, id))
+        })
+    }
+}
+
+/// y of the highest block of every column of the chunk
+fn column_tops(chunk: &Chunk) -> HashMap<IVec2, i32> {
+    let mut tops = HashMap::<IVec2, i32>::default();
+    for pos in chunk.data.keys() {
+        let top = tops.entry(pos.xz()).or_insert(pos.y);
+        *top = (*top).max(pos.y);
+    }
+    tops
+}
+
+impl ChunkGenerator for NoiseGenerator {
+    fn generate(&self, chunk_pos: IVec2) -> Chunk {
+        let mut chunk = Chunk::default();
+        if !self.biomes.is_empty() {
+            let mut biomes = ChunkBiomes::new(chunk_pos, |column| {
+                self.biome(column.x, column.y)
+                    .map(|(biome_id, _)| biome_id.clone())
+                    .expect("biomes is not empty")
+            });
+            biomes.resolve(|biome_id| self.biomes.tints(biome_id));
+            chunk.biomes = Some(biomes);
+        }
+
+        let origin = chunk_pos * CHUNK_SIZE;
+        for x in origin.x..origin.x + CHUNK_SIZE {
+            for z in origin.y..origin.y + CHUNK_SIZE {
+                let (surface, subsurface) = self.soil(chunk.biome(IVec3::new(x, 0, z)));
+                for (y, id) in self.column(x, z, &surface, &subsurface) {
+                    chunk
+                        .data
+                        .insert(IVec3::new(x, y, z), BlockData { id: id.clone() });
+                }
+            }
+        }
+
+        // the features of the neighbors reach into the chunk, applied in a fixed order
+        // so the chunk does not depend on which neighbors were generated first
+        for dz in -FEATURE_REACH..=FEATURE_REACH {
+            for dx in -FEATURE_REACH..=FEATURE_REACH {
+                let source = chunk_pos + IVec2::new(dx, dz);
+                let writes = self.deferred.take(source, chunk_pos, || {
+                    place_features(self.seed, &self.features, self, source)
+                });
+                apply_writes(&mut chunk, &writes);
+            }
+        }
+        chunk
+    }
+
+    fn loaded(&self, chunk_pos: IVec2) {
+        self.deferred.skip(chunk_pos);
+    }
+}
+
+impl FeatureTerrain for NoiseGenerator {
+    fn top(&self, x: i32, z: i32) -> (i32, BlockId) {
+        let (surface, subsurface) = self.soil(self.biome(x, z).map(|(biome_id, _)| biome_id));
+        let top = self
+            .column(x, z, &surface, &subsurface)
+            .next()
+            .map(|(y, id)| (y, id.clone()));
+        top.unwrap_or((0, self.stone.clone()))
+    }
+
+    fn biome(&self, x: i32, z: i32) -> Option<BiomeId> {
+        NoiseGenerator::biome(self, x, z).map(|(biome_id, _)| biome_id.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::*;
+    use crate::assets::prelude::*;
+
+    #[test]
+    fn test_noise_generator() {
+        // a plain height map
+        let mut generator = NoiseGenerator::new(1234);
+        generator.overhang = 0.0;
+        generator.caves.enabled = false;
+        generator.features.clear();
+        let chunk_pos = IVec2::new(-2, 3);
+        let chunk = generator.generate(chunk_pos);
+        assert_eq!(chunk.position(), Some(chunk_pos));
+
+        let (x, z) = (-20, 50);
+        let height = generator.height(x, z);
+        assert_eq!(chunk.data[&IVec3::new(x, height, z)].id, generator.surface);
+        assert_eq!(
+            chunk.data[&IVec3::new(x, height - 1, z)].id,
+            generator.subsurface
+        );
+        assert_eq!(chunk.data[&IVec3::new(x, 0, z)].id, generator.stone);
+        assert!(!chunk.data.contains_key(&IVec3::new(x, height + 1, z)));
+        assert_eq!(generator.surface_height(x, z), height);
+    }
+
+    fn same_blocks(first: &Chunk, second: &Chunk) -> bool {
+        first.data.len() == second.data.len()
+            && first.data.iter().all(|(pos, block)| {
+                second
+                    .data
+                    .get(pos)
+                    .is_some_and(|other| other.id == block.id)
+            })
+    }
+
+    #[test]
+    fn test_density_generator() {
+        let mut generator = NoiseGenerator::new(42);
+        generator.features.clear();
+        let mut plain = generator.clone();
+        plain.overhang = 0.0;
+        plain.caves.enabled = false;
+
+        let mut gaps = 0;
+        for chunk_pos in [
+            IVec2::ZERO,
+            IVec2::new(1, 0),
+            IVec2::new(-1, -1),
+            IVec2::new(2, 3),
+        ] {
+            let chunk = generator.generate(chunk_pos);
+            let origin = chunk_pos * CHUNK_SIZE;
+            for x in origin.x..origin.x + CHUNK_SIZE {
+                for z in origin.y..origin.y + CHUNK_SIZE {
+                    let top = generator.surface_height(x, z);
+                    let blocks = (0..=top)
+                        .filter(|y| chunk.data.contains_key(&IVec3::new(x, *y, z)))
+                        .count() as i32;
+                    assert!(chunk.data.contains_key(&IVec3::new(x, top, z)));
+                    assert!(chunk.data.contains_key(&IVec3::new(x, 0, z)));
+                    assert!(!chunk.data.contains_key(&IVec3::new(x, top + 1, z)));
+                    gaps += top + 1 - blocks;
+                }
+            }
+
+            // without overhangs and caves every column is filled up to the height map
+            let filled = plain.generate(chunk_pos);
+            let expected = (origin.x..origin.x + CHUNK_SIZE)
+                .flat_map(|x| (origin.y..origin.y + CHUNK_SIZE).map(move |z| (x, z)))
+                .map(|(x, z)| plain.height(x, z).max(0) as usize + 1)
+                .sum::<usize>();
+            assert_eq!(filled.data.len(), expected);
+        }
+        // overhangs and caves leave air under the surface
+        assert!(gaps > 0);
+
+        // the same seed gives the same chunk
+        let chunk = NoiseGenerator::new(42).generate(IVec2::new(-3, 5));
+        assert!(same_blocks(
+            &chunk,
+            &NoiseGenerator::new(42).generate(IVec2::new(-3, 5))
+        ));
+        assert!(!same_blocks(
+            &chunk,
+            &NoiseGenerator::new(43).generate(IVec2::new(-3, 5))
+        ));
+    }
+
+    /// the highest block and the number of air blocks under it of every column,
+    /// one row per z
+    fn snapshot(generator: &NoiseGenerator, chunk_pos: IVec2) -> String {
+        let chunk = generator.generate(chunk_pos);
+        let origin = chunk_pos * CHUNK_SIZE;
+        let mut result = format!("chunk {} {}\n", chunk_pos.x, chunk_pos.y);
+        for z in origin.y..origin.y + CHUNK_SIZE {
+            let row = (origin.x..origin.x + CHUNK_SIZE)
+                .map(|x| {
+                    let top = (0..64)
+                        .rev()
+                        .find(|y| chunk.data.contains_key(&IVec3::new(x, *y, z)))
+                        .unwrap_or(-1);
+                    let air = (0..top)
+                        .filter(|y| !chunk.data.contains_key(&IVec3::new(x, *y, z)))
+                        .count();
+                    format!("{:2}:{:<2}", top, air)
+                })
+                .collect::<Vec<_>>();
+            result.push_str(row.join(" ").trim_end());
+            result.push('\n');
+        }
+        result
+    }
+
+    /// set `UPDATE_SNAPSHOTS=1` to write the snapshot again after changing the generator
+    #[test]
+    fn test_generator_snapshot() {
+        let path = "src/world/fixtures/terrain_42.txt";
+        let generator = NoiseGenerator::new(42);
+        let snapshot = [IVec2::ZERO, IVec2::new(-1, 2), IVec2::new(5, -7)]
+            .map(|chunk_pos| snapshot(&generator, chunk_pos))
+            .join("\n");
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(path, &snapshot).unwrap();
+        }
+        assert_eq!(snapshot, std::fs::read_to_string(path).unwrap());
+    }
+
+    #[test]
+    fn test_biome_generator() {
+        let biome = |temperature, surface: &str, grass_color| Biome {
+            temperature,
+            humidity: 0.0,
+            surface: surface.to_string(),
+            subsurface: surface.to_string(),
+            grass_color,
+            foliage_color: [0; 3],
+        };
+        let mut biomes = Biomes::default();
+        biomes.insert(
+            BiomeId::try_from("test:cold").unwrap(),
+            biome(-1.0, "bevy_craft:block/stone", [0, 0, 255]),
+        );
+        biomes.insert(
+            BiomeId::try_from("test:hot").unwrap(),
+            biome(1.0, "bevy_craft:block/sand", [255, 0, 0]),
+        );
+        let generator = NoiseGenerator::new(1234).with_biomes(Arc::new(biomes));
+
+        let chunk_pos = IVec2::new(3, -1);
+        let chunk = generator.generate(chunk_pos);
+        let chunk_biomes = chunk.biomes.as_ref().unwrap();
+        assert!(chunk_biomes.is_resolved());
+        let origin = chunk_pos * CHUNK_SIZE;
+        for x in origin.x..origin.x + CHUNK_SIZE {
+            for z in origin.y..origin.y + CHUNK_SIZE {
+                let (biome_id, biome) = generator.biome(x, z).unwrap();
+                let top = IVec3::new(x, generator.surface_height(x, z), z);
+                assert_eq!(chunk.biome(top), Some(biome_id));
+                assert_eq!(chunk.data[&top].id.to_string(), biome.surface);
+                assert_eq!(chunk.tint(top, Some(0)), biome.tints().grass);
+            }
+        }
+    }
+
+    #[test]
+    fn test_feature_generator() {
+        let mut generator = NoiseGenerator::new(7);
+        generator.features = vec![PlacedFeature::new(
+            Feature::Tree {
+                log: "bevy_craft:block/oak_log".to_string(),
+                leaves: "bevy_craft:block/oak_leaves".to_string(),
+                min_height: 4,
+                max_height: 6,
+                on: vec![generator.surface.to_string()],
+            },
+            12,
+        )];
+        let positions = [IVec2::ZERO, IVec2::X, IVec2::new(1, 1), IVec2::Y];
+
+        // the neighbors are generated in another order by a new generator
+        let chunks = positions.map(|chunk_pos| generator.generate(chunk_pos));
+        let again = generator.clone();
+        assert!(again.deferred.is_empty());
+        for (chunk_pos, chunk) in positions.iter().zip(&chunks).rev() {
+            assert!(same_blocks(chunk, &again.generate(*chunk_pos)));
+        }
+
+        // the trees of (0, 0) which cross into (1, 0) are written there
+        let writes = place_features(7, &generator.features, &generator, IVec2::ZERO);
+        let crossing = &writes[&IVec2::X];
+        assert!(crossing
+            .iter()
+            .any(|write| chunks[1].data.get(&write.pos).map(|block| &block.id) == Some(&write.id)));
+
+        // (0, 0) is held until every chunk in reach has taken its writes
+        assert!(generator.deferred.contains(IVec2::ZERO));
+        for x in -1..=1 {
+            for z in -1..=1 {
+                if !positions.contains(&IVec2::new(x, z)) {
+                    generator.generate(IVec2::new(x, z));
+                }
+            }
+        }
+        assert!(!generator.deferred.contains(IVec2::ZERO));
+        // the neighbors of a chunk loaded from storage do not wait for it
+        let generator = generator.clone();
+        generator.generate(IVec2::ZERO);
+        for x in -2..=2 {
+            for z in -2..=2 {
+                if IVec2::new(x, z) != IVec2::ZERO {
+                    generator.loaded(IVec2::new(x, z));
+                }
+            }
+        }
+        assert!(generator.deferred.is_empty());
+    }
+
+    #[test]
+    fn test_spawn() {
+        let biomes = Arc::new(Biomes::load(Path::new(ASSET_ROOT)).unwrap());
+        // the origin of seed 42 is carved by a cave
+        let generator = NoiseGenerator::new(42);
+        assert!(generator.surface_height(0, 0) < generator.height(0, 0) - SPAWN_TOLERANCE);
+
+        for generator in [generator, NoiseGenerator::new(7).with_biomes(biomes)] {
+            let spawn = generator.spawn(IVec2::ZERO);
+            let chunk = generator.generate(chunk_position(spawn));
+            // open to the sky, trees and boulders included
+            assert!(chunk.data.contains_key(&(spawn - IVec3::Y)));
+            assert!(chunk
+                .data
+                .keys()
+                .all(|pos| pos.xz() != spawn.xz() || pos.y < spawn.y));
+            assert!((spawn.y - 1 - generator.height(spawn.x, spawn.z)).abs() <= SPAWN_TOLERANCE);
+        }
+    }
+}
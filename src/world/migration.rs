@@ -0,0 +1,357 @@
+use std::sync::{Arc, LazyLock};
+
+use bevy::{platform::collections::HashMap, prelude::*};
+use derive_more::derive::{Display, Error};
+use serde_json::Value;
+
+/// version of the saved chunks and levels written by this build,
+/// increased with every `Migration`
+pub const DATA_VERSION: u32 = 3;
+/// version of the saves written before `data_version` existed
+pub const UNVERSIONED: u32 = 1;
+pub const DATA_VERSION_KEY: &str = "data_version";
+
+static BUILTIN: LazyLock<Arc<Migrations>> = LazyLock::new(|| Arc::new(Migrations::builtin()));
+
+#[derive(Debug, Error, Display, PartialEq, Eq)]
+pub enum MigrationError {
+    #[display("data version {} is newer than {}", _0, _1)]
+    TooNew(#[error(not(source))] u32, u32),
+    #[display("invalid data: {}", _0)]
+    Invalid(#[error(not(source))] String),
+}
+
+/// what a step applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveKind {
+    /// `level.json`
+    Level,
+    /// a `ChunkSave` in a region file
+    Chunk,
+}
+
+pub type UpgradeFn = fn(&mut Value) -> Result<(), MigrationError>;
+
+#[derive(Debug, Clone)]
+pub enum MigrationStep {
+    /// replace the old ids in the palette of every chunk
+    RemapBlocks(HashMap<String, String>),
+    /// rename the field `from` of the object at the json pointer `path`
+    RenameProperty {
+        kind: SaveKind,
+        path: String,
+        from: String,
+        to: String,
+    },
+    /// any other change of the format
+    Upgrade { kind: SaveKind, upgrade: UpgradeFn },
+}
+
+impl MigrationStep {
+    pub fn remap_blocks<'a>(ids: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        MigrationStep::RemapBlocks(
+            ids.into_iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+        )
+    }
+
+    pub fn rename_property(kind: SaveKind, path: &str, from: &str, to: &str) -> Self {
+        MigrationStep::RenameProperty {
+            kind,
+            path: path.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    fn apply(&self, kind: SaveKind, value: &mut Value) -> Result<(), MigrationError> {
+        match self {
+            MigrationStep::RemapBlocks(ids) => {
+                if kind != SaveKind::Chunk {
+                    return Ok(());
+                }
+                let Some(palette) = value.get_mut("palette").and_then(Value::as_array_mut) else {
+                    return Err(MigrationError::Invalid("chunk without palette".to_string()));
+                };
+                for id in palette {
+                    if let Some(new_id) = id.as_str().and_then(|id| ids.get(id)) {
+                        *id = Value::String(new_id.clone());
+                    }
+                }
+            }
+            MigrationStep::RenameProperty {
+                kind: step_kind,
+                path,
+                from,
+                to,
+            } => {
+                if *step_kind != kind {
+                    return Ok(());
+                }
+                // nothing to rename if the object or the field does not exist
+                let object = value.pointer_mut(path).and_then(Value::as_object_mut);
+                if let Some(object) = object {
+                    if let Some(property) = object.remove(from) {
+                        object.insert(to.clone(), property);
+                    }
+                }
+            }
+            MigrationStep::Upgrade {
+                kind: step_kind,
+                upgrade,
+            } => {
+                if *step_kind == kind {
+                    upgrade(value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the steps which upgrade data to `version`
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub version: u32,
+    pub steps: Vec<MigrationStep>,
+}
+
+/// every migration ordered by version, applied to saves when they are loaded
+#[derive(Debug, Clone, Default)]
+pub struct Migrations {
+    migrations: Vec<Migration>,
+}
+
+impl Migrations {
+    /// the migrations of the saves written by earlier builds
+    pub fn builtin() -> Self {
+        Migrations::default()
+            .with_step(
+                2,
+                MigrationStep::rename_property(
+                    SaveKind::Level,
+                    "",
+                    "format_version",
+                    DATA_VERSION_KEY,
+                ),
+            )
+            .with_step(
+                3,
+                MigrationStep::Upgrade {
+                    kind: SaveKind::Level,
+                    upgrade: pin_height_map,
+                },
+            )
+    }
+
+    /// shared `Migrations::builtin`
+    pub fn shared() -> Arc<Migrations> {
+        BUILTIN.clone()
+    }
+
+    pub fn with_step(mut self, version: u32, step: MigrationStep) -> Self {
+        self.register(version, step);
+        self
+    }
+
+    /// steps of the same version run in the order they are registered
+    pub fn register(&mut self, version: u32, step: MigrationStep) {
+        match self
+            .migrations
+            .binary_search_by_key(&version, |migration| migration.version)
+        {
+            Ok(index) => self.migrations[index].steps.push(step),
+            Err(index) => self.migrations.insert(
+                index,
+                Migration {
+                    version,
+                    steps: vec![step],
+                },
+            ),
+        }
+    }
+
+    /// the version of the data after every migration
+    pub fn latest(&self) -> u32 {
+        self.migrations
+            .last()
+            .map(|migration| migration.version)
+            .unwrap_or(UNVERSIONED)
+            .max(DATA_VERSION)
+    }
+
+    /// upgrade the data from its `data_version` to `latest`, returns the version it had
+    pub fn migrate(&self, kind: SaveKind, value: &mut Value) -> Result<u32, MigrationError> {
+        let version = data_version(value)?;
+        let latest = self.latest();
+        if version > latest {
+            return Err(MigrationError::TooNew(version, latest));
+        }
+
+        for migration in &self.migrations {
+            if migration.version <= version {
+                continue;
+            }
+            for step in &migration.steps {
+                step.apply(kind, value)?;
+            }
+            debug!("migrated {:?} to data version {}", kind, migration.version);
+        }
+
+        let Some(object) = value.as_object_mut() else {
+            return Err(MigrationError::Invalid("not an object".to_string()));
+        };
+        object.insert(DATA_VERSION_KEY.to_string(), Value::from(latest));
+        Ok(version)
+    }
+}
+
+/// levels created before overhangs, caves and features keep generating a plain height map,
+/// otherwise the new chunks would not match the saved ones
+fn pin_height_map(level: &mut Value) -> Result<(), MigrationError> {
+    let invalid = |what: &str| MigrationError::Invalid(format!("{} is not an object", what));
+    let level = level.as_object_mut().ok_or_else(|| invalid("level"))?;
+    let generator = level
+        .entry("generator")
+        .or_insert_with(|| Value::Object(default()))
+        .as_object_mut()
+        .ok_or_else(|| invalid("generator"))?;
+    generator.entry("overhang").or_insert(Value::from(0.0));
+    generator
+        .entry("caves")
+        .or_insert_with(|| Value::Object(default()))
+        .as_object_mut()
+        .ok_or_else(|| invalid("caves"))?
+        .entry("enabled")
+        .or_insert(Value::Bool(false));
+    generator
+        .entry("features")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    Ok(())
+}
+
+/// `data_version` of the data, `UNVERSIONED` if it is missing
+pub fn data_version(value: &Value) -> Result<u32, MigrationError> {
+    match value.get(DATA_VERSION_KEY) {
+        None => Ok(UNVERSIONED),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| MigrationError::Invalid(format!("data version {}", version))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_migrations() {
+        fn double_y(value: &mut Value) -> Result<(), MigrationError> {
+            let blocks = value["blocks"]
+                .as_array_mut()
+                .ok_or_else(|| MigrationError::Invalid("blocks".to_string()))?;
+            for block in blocks {
+                block[1] = Value::from(block[1].as_i64().unwrap_or_default() * 2);
+            }
+            Ok(())
+        }
+
+        // registered out of order
+        let migrations = Migrations::default()
+            .with_step(
+                4,
+                MigrationStep::Upgrade {
+                    kind: SaveKind::Chunk,
+                    upgrade: double_y,
+                },
+            )
+            .with_step(
+                3,
+                MigrationStep::remap_blocks([("old:block/a", "new:block/a")]),
+            )
+            .with_step(
+                3,
+                MigrationStep::rename_property(SaveKind::Chunk, "/extra", "old", "new"),
+            )
+            .with_step(
+                4,
+                MigrationStep::remap_blocks([("new:block/a", "newer:block/a")]),
+            );
+        assert_eq!(migrations.latest(), 4);
+
+        let mut chunk = json!({
+            "data_version": 2,
+            "position": [0, 0],
+            "palette": ["old:block/a", "old:block/b"],
+            "blocks": [[0, 1, 0, 0], [0, 2, 0, 1]],
+            "extra": { "old": true },
+        });
+        assert_eq!(migrations.migrate(SaveKind::Chunk, &mut chunk), Ok(2));
+        assert_eq!(
+            chunk,
+            json!({
+                "data_version": 4,
+                "position": [0, 0],
+                "palette": ["newer:block/a", "old:block/b"],
+                "blocks": [[0, 2, 0, 0], [0, 4, 0, 1]],
+                "extra": { "new": true },
+            })
+        );
+
+        // already migrated, nothing changes
+        let migrated = chunk.clone();
+        assert_eq!(migrations.migrate(SaveKind::Chunk, &mut chunk), Ok(4));
+        assert_eq!(chunk, migrated);
+
+        // chunk steps do not touch the level
+        let mut level = json!({ "data_version": 2, "extra": { "old": 1 } });
+        migrations.migrate(SaveKind::Level, &mut level).unwrap();
+        assert_eq!(level["extra"], json!({ "old": 1 }));
+
+        let mut future = json!({ "data_version": 5 });
+        assert_eq!(
+            migrations.migrate(SaveKind::Chunk, &mut future),
+            Err(MigrationError::TooNew(5, 4))
+        );
+    }
+
+    #[test]
+    fn test_builtin_migrations() {
+        let migrations = Migrations::builtin();
+        assert_eq!(migrations.latest(), DATA_VERSION);
+
+        let mut level = json!({ "format_version": 1, "name": "old" });
+        assert_eq!(
+            migrations.migrate(SaveKind::Level, &mut level),
+            Ok(UNVERSIONED)
+        );
+        assert_eq!(
+            level,
+            json!({
+                "data_version": DATA_VERSION,
+                "name": "old",
+                "generator": {
+                    "overhang": 0.0,
+                    "caves": { "enabled": false },
+                    "features": [],
+                },
+            })
+        );
+
+        // the settings of a level are kept
+        let mut level = json!({
+            "data_version": 2,
+            "generator": { "overhang": 2.0, "caves": { "min_y": 3 } },
+        });
+        migrations.migrate(SaveKind::Level, &mut level).unwrap();
+        assert_eq!(level["generator"]["overhang"], json!(2.0));
+        assert_eq!(
+            level["generator"]["caves"],
+            json!({ "min_y": 3, "enabled": false })
+        );
+    }
+}
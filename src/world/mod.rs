@@ -0,0 +1,21 @@
+pub(crate) mod autosave;
+pub(crate) mod biome;
+pub(crate) mod features;
+pub(crate) mod generator;
+pub(crate) mod migration;
+pub(crate) mod plugin;
+pub(crate) mod region;
+pub(crate) mod save;
+pub(crate) mod streaming;
+
+pub mod prelude {
+    pub use super::autosave::*;
+    pub use super::biome::*;
+    pub use super::features::*;
+    pub use super::generator::*;
+    pub use super::migration::*;
+    pub use super::plugin::*;
+    pub use super::region::*;
+    pub use super::save::*;
+    pub use super::streaming::*;
+}
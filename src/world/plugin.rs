@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+
+use crate::{assets::prelude::*, interaction::prelude::*, world::prelude::*};
+
+/// load, mesh and unload chunks around every `ChunkLoader`,
+/// the chunks come from `WorldStorage` if it exists, or from `WorldGenerator`
+pub struct ChunkStreamingPlugin;
+
+impl Plugin for ChunkStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StreamingSettings>()
+            .init_resource::<LoadedChunks>()
+            .add_systems(OnEnter(AppLoadState::Next), create_chunk_material)
+            .add_systems(
+                Update,
+                (
+                    unload_chunks,
+                    load_chunks,
+                    finish_loading,
+                    light_chunks,
+                    relight_borders,
+                    resolve_biomes,
+                    mesh_chunks,
+                )
+                    .chain()
+                    // a chunk meshed before `remesh_chunks` would be meshed twice
+                    .after(remesh_chunks)
+                    .run_if(resource_exists::<WorldGenerator>)
+                    .run_if(in_state(AppLoadState::Next)),
+            );
+    }
+}
+
+/// save the chunks edited in `SavedWorld` periodically and when the app exits,
+/// the plugin provides `WorldStorage` for `ChunkStreamingPlugin`
+pub struct WorldSavePlugin;
+
+impl Plugin for WorldSavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AutosaveSettings>()
+            .init_resource::<SaveStatus>()
+            .add_observer(save_unloaded_chunk)
+            .add_systems(
+                Startup,
+                open_saved_world.run_if(resource_exists::<SavedWorld>),
+            )
+            .add_systems(
+                FixedUpdate,
+                tick_game_time
+                    .run_if(resource_exists::<SavedWorld>)
+                    .run_if(in_state(AppLoadState::Next)),
+            )
+            .add_systems(
+                Update,
+                (mark_dirty_chunks, autosave)
+                    .chain()
+                    // a chunk edited and unloaded in the same frame must be dirty when it is despawned
+                    .after(edit_blocks)
+                    .before(unload_chunks)
+                    .run_if(resource_exists::<ChunkSaver>),
+            )
+            .add_systems(Last, flush_on_exit.run_if(resource_exists::<ChunkSaver>));
+    }
+}
@@ -0,0 +1,729 @@
+use std::{
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bevy::{platform::collections::HashMap, prelude::*};
+use derive_more::derive::{Display, Error, From};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::{chunks::prelude::*, identity::prelude::*, world::prelude::*};
+
+/// a region file holds REGION_SIZE * REGION_SIZE chunks
+pub const REGION_SIZE: i32 = 32;
+
+const REGION_MAGIC: &[u8; 4] = b"BCRG";
+const REGION_VERSION: u32 = 1;
+const REGION_CHUNKS: usize = (REGION_SIZE * REGION_SIZE) as usize;
+/// magic, version, then the offset and the length of every chunk
+const HEADER_LEN: usize = 8 + REGION_CHUNKS * 8;
+
+/// position of the region which contains the chunk at `chunk_pos`
+pub fn region_position(chunk_pos: IVec2) -> IVec2 {
+    chunk_pos.div_euclid(IVec2::splat(REGION_SIZE))
+}
+
+#[derive(Debug, Error, Display, From)]
+pub enum RegionError {
+    #[display("region io error: {}", _0)]
+    Io(std::io::Error),
+    #[display("invalid chunk data: {}", _0)]
+    Json(serde_json::Error),
+    #[display("failed to migrate chunk: {}", _0)]
+    Migration(MigrationError),
+    #[display("corrupted region {{ {} }}", _0)]
+    #[from(ignore)]
+    Corrupted(#[error(not(source))] String),
+}
+
+/// how the data of a chunk is compressed, stored in the first byte of the chunk
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkCompression {
+    Raw = 0,
+    #[default]
+    Zlib = 1,
+}
+
+impl TryFrom<u8> for ChunkCompression {
+    type Error = RegionError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ChunkCompression::Raw),
+            1 => Ok(ChunkCompression::Zlib),
+            _ => Err(RegionError::Corrupted(format!(
+                "unknown compression {}",
+                value
+            ))),
+        }
+    }
+}
+
+impl ChunkCompression {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>, RegionError> {
+        let mut result = vec![*self as u8];
+        match self {
+            ChunkCompression::Raw => result.extend_from_slice(data),
+            ChunkCompression::Zlib => {
+                let mut encoder = ZlibEncoder::new(result, Compression::default());
+                encoder.write_all(data)?;
+                result = encoder.finish()?;
+            }
+        }
+        Ok(result)
+    }
+
+    fn decompress(data: &[u8]) -> Result<Vec<u8>, RegionError> {
+        let Some((compression, data)) = data.split_first() else {
+            return Err(RegionError::Corrupted("empty chunk".to_string()));
+        };
+        match ChunkCompression::try_from(*compression)? {
+            ChunkCompression::Raw => Ok(data.to_vec()),
+            ChunkCompression::Zlib => {
+                let mut result = Vec::new();
+                ZlibDecoder::new(data).read_to_end(&mut result)?;
+                Ok(result)
+            }
+        }
+    }
+}
+
+/// a chunk as it is saved, blocks refer to a palette of `BlockId` strings
+/// so that the save does not depend on the order of the registry
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChunkSave {
+    pub data_version: u32,
+    pub position: IVec2,
+    pub palette: Vec<String>,
+    /// local x, y, local z and the palette index of every block
+    pub blocks: Vec<[i32; 4]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub biomes: Option<BiomesSave>,
+}
+
+/// the biome of every column, columns refer to a palette of `BiomeId` strings
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BiomesSave {
+    pub palette: Vec<String>,
+    /// palette index of every column, ordered by z then x
+    pub columns: Vec<u8>,
+}
+
+impl ChunkSave {
+    pub fn new(chunk_pos: IVec2, chunk: &Chunk) -> Self {
+        let origin = (chunk_pos * CHUNK_SIZE).extend(0).xzy();
+        let mut blocks = chunk.data.iter().collect::<Vec<_>>();
+        blocks.sort_by_key(|(pos, _)| (pos.y, pos.z, pos.x));
+
+        let mut palette = Vec::<String>::new();
+        let mut indices = HashMap::<&BlockId, i32>::default();
+        let blocks = blocks
+            .into_iter()
+            .map(|(pos, block_data)| {
+                let index = *indices.entry(&block_data.id).or_insert_with(|| {
+                    palette.push(block_data.id.to_string());
+                    palette.len() as i32 - 1
+                });
+                let local = *pos - origin;
+                [local.x, local.y, local.z, index]
+            })
+            .collect();
+
+        Self {
+            data_version: DATA_VERSION,
+            position: chunk_pos,
+            palette,
+            blocks,
+            biomes: chunk.biomes.as_ref().map(|biomes| BiomesSave {
+                palette: biomes.palette().iter().map(ToString::to_string).collect(),
+                columns: biomes.columns().to_vec(),
+            }),
+        }
+    }
+
+    pub fn into_chunk(self) -> Result<Chunk, RegionError> {
+        let origin = (self.position * CHUNK_SIZE).extend(0).xzy();
+        let mut chunk = Chunk::default();
+        if let Some(biomes) = self.biomes {
+            let palette = biomes
+                .palette
+                .iter()
+                .map(|id| BiomeId::try_from(id.as_str()))
+                .collect::<Result<_, _>>()
+                .map_err(|_| {
+                    RegionError::Corrupted(format!("biomes of chunk {}", self.position))
+                })?;
+            // the tints are resolved when the chunk is loaded
+            chunk.biomes = Some(
+                ChunkBiomes::from_palette(palette, biomes.columns).ok_or_else(|| {
+                    RegionError::Corrupted(format!("biomes of chunk {}", self.position))
+                })?,
+            );
+        }
+        for [x, y, z, index] in self.blocks {
+            if !(0..CHUNK_SIZE).contains(&x) || !(0..CHUNK_SIZE).contains(&z) {
+                return Err(RegionError::Corrupted(format!(
+                    "block ({}, {}, {}) out of chunk {}",
+                    x, y, z, self.position
+                )));
+            }
+            let Some(id) = usize::try_from(index)
+                .ok()
+                .and_then(|index| self.palette.get(index))
+            else {
+                return Err(RegionError::Corrupted(format!(
+                    "palette index {} out of {}",
+                    index,
+                    self.palette.len()
+                )));
+            };
+            let id = BlockId::try_from(id.as_str())
+                .map_err(|_| RegionError::Corrupted(format!("block id {}", id)))?;
+            chunk
+                .data
+                .insert(origin + IVec3::new(x, y, z), BlockData { id });
+        }
+        Ok(chunk)
+    }
+}
+
+/// the chunks of a region file, kept compressed
+#[derive(Debug, Clone)]
+pub struct Region {
+    chunks: Vec<Option<Vec<u8>>>,
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Self {
+            chunks: vec![None; REGION_CHUNKS],
+        }
+    }
+}
+
+impl Region {
+    fn index(chunk_pos: IVec2) -> usize {
+        let local = chunk_pos.rem_euclid(IVec2::splat(REGION_SIZE));
+        (local.y * REGION_SIZE + local.x) as usize
+    }
+
+    /// an empty region if the file does not exist
+    pub fn read(path: &Path) -> Result<Self, RegionError> {
+        match fs::read(path) {
+            Ok(bytes) => Self::from_bytes(&bytes),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, RegionError> {
+        if bytes.len() < HEADER_LEN || &bytes[0..4] != REGION_MAGIC {
+            return Err(RegionError::Corrupted("invalid header".to_string()));
+        }
+        let version = read_u32(bytes, 4);
+        if version != REGION_VERSION {
+            return Err(RegionError::Corrupted(format!(
+                "unknown region version {}",
+                version
+            )));
+        }
+
+        let mut result = Self::default();
+        for (index, chunk) in result.chunks.iter_mut().enumerate() {
+            let offset = read_u32(bytes, 8 + index * 8) as usize;
+            let len = read_u32(bytes, 12 + index * 8) as usize;
+            if len == 0 {
+                continue;
+            }
+            let Some(data) = offset
+                .checked_add(len)
+                .filter(|_| offset >= HEADER_LEN)
+                .and_then(|end| bytes.get(offset..end))
+            else {
+                return Err(RegionError::Corrupted(format!(
+                    "chunk {} at {}..{} out of the file",
+                    index,
+                    offset,
+                    offset + len
+                )));
+            };
+            *chunk = Some(data.to_vec());
+        }
+        Ok(result)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(REGION_MAGIC);
+        header.extend_from_slice(&REGION_VERSION.to_le_bytes());
+
+        let mut body = Vec::new();
+        for chunk in &self.chunks {
+            let (offset, len) = match chunk {
+                Some(data) => {
+                    let offset = HEADER_LEN + body.len();
+                    body.extend_from_slice(data);
+                    (offset as u32, data.len() as u32)
+                }
+                None => (0, 0),
+            };
+            header.extend_from_slice(&offset.to_le_bytes());
+            header.extend_from_slice(&len.to_le_bytes());
+        }
+
+        header.extend(body);
+        header
+    }
+
+    /// write into a temporary file, then rename it over `path`,
+    /// so that a crash leaves either the old or the new region
+    pub fn write(&self, path: &Path) -> Result<(), RegionError> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        let mut file = File::create(&tmp)?;
+        file.write_all(&self.to_bytes())?;
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&tmp, path)?;
+
+        // persist the rename itself
+        #[cfg(unix)]
+        if let Some(dir) = path.parent() {
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, chunk_pos: IVec2) -> bool {
+        self.chunks[Self::index(chunk_pos)].is_some()
+    }
+
+    /// the saved chunk, upgraded by `migrations` if it was saved by an earlier version
+    pub fn get(
+        &self,
+        chunk_pos: IVec2,
+        migrations: &Migrations,
+    ) -> Result<Option<ChunkSave>, RegionError> {
+        let Some(data) = &self.chunks[Self::index(chunk_pos)] else {
+            return Ok(None);
+        };
+        let mut value = serde_json::from_slice(&ChunkCompression::decompress(data)?)?;
+        migrations.migrate(SaveKind::Chunk, &mut value)?;
+        let save: ChunkSave = serde_json::from_value(value)?;
+        if save.position != chunk_pos {
+            return Err(RegionError::Corrupted(format!(
+                "chunk {} is saved at {}",
+                save.position, chunk_pos
+            )));
+        }
+        Ok(Some(save))
+    }
+
+    pub fn insert(
+        &mut self,
+        save: &ChunkSave,
+        compression: ChunkCompression,
+    ) -> Result<(), RegionError> {
+        self.insert_json(save.position, &serde_json::to_vec(save)?, compression)
+    }
+
+    fn insert_json(
+        &mut self,
+        chunk_pos: IVec2,
+        json: &[u8],
+        compression: ChunkCompression,
+    ) -> Result<(), RegionError> {
+        self.chunks[Self::index(chunk_pos)] = Some(compression.compress(json)?);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, chunk_pos: IVec2) {
+        self.chunks[Self::index(chunk_pos)] = None;
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut result = [0; 4];
+    result.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(result)
+}
+
+/// chunks saved in region files `r.<x>.<z>.region` under a directory
+#[derive(Debug, Clone)]
+pub struct RegionStorage {
+    dir: PathBuf,
+    pub compression: ChunkCompression,
+    migrations: Arc<Migrations>,
+}
+
+impl RegionStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            compression: ChunkCompression::default(),
+            migrations: Migrations::shared(),
+        }
+    }
+
+    pub fn with_migrations(mut self, migrations: Arc<Migrations>) -> Self {
+        self.migrations = migrations;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn region_path(&self, region_pos: IVec2) -> PathBuf {
+        self.dir
+            .join(format!("r.{}.{}.region", region_pos.x, region_pos.y))
+    }
+
+    pub fn load_chunk(&self, chunk_pos: IVec2) -> Result<Option<Chunk>, RegionError> {
+        let region = Region::read(&self.region_path(region_position(chunk_pos)))?;
+        region
+            .get(chunk_pos, &self.migrations)?
+            .map(ChunkSave::into_chunk)
+            .transpose()
+    }
+
+    pub fn save_chunk(&self, chunk_pos: IVec2, chunk: &Chunk) -> Result<(), RegionError> {
+        self.save_chunks([(chunk_pos, chunk)])
+    }
+
+    pub fn save_chunks<'a>(
+        &self,
+        chunks: impl IntoIterator<Item = (IVec2, &'a Chunk)>,
+    ) -> Result<(), RegionError> {
+        let saves = chunks
+            .into_iter()
+            .map(|(chunk_pos, chunk)| ChunkSave::new(chunk_pos, chunk))
+            .collect::<Vec<_>>();
+        self.write_saves(&saves)
+    }
+
+    /// every region file is rewritten once, however many of its chunks are saved,
+    /// the saves are stamped with the version `migrations` upgrades to,
+    /// so they are not migrated again when they are loaded
+    pub fn write_saves<'a>(
+        &self,
+        saves: impl IntoIterator<Item = &'a ChunkSave>,
+    ) -> Result<(), RegionError> {
+        let mut regions = HashMap::<IVec2, Vec<&ChunkSave>>::default();
+        for save in saves {
+            regions
+                .entry(region_position(save.position))
+                .or_default()
+                .push(save);
+        }
+        if regions.is_empty() {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        for (region_pos, saves) in regions {
+            let path = self.region_path(region_pos);
+            let mut region = Region::read(&path)?;
+            for save in saves {
+                let mut value = serde_json::to_value(save)?;
+                value[DATA_VERSION_KEY] = serde_json::Value::from(self.migrations.latest());
+                region.insert_json(
+                    save.position,
+                    &serde_json::to_vec(&value)?,
+                    self.compression,
+                )?;
+            }
+            region.write(&path)?;
+        }
+        Ok(())
+    }
+}
+
+impl ChunkStorage for RegionStorage {
+    fn load(&self, chunk_pos: IVec2) -> Option<Chunk> {
+        self.load_chunk(chunk_pos).unwrap_or_else(|err| {
+            error!("load chunk {} failed: {}", chunk_pos, err);
+            None
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assets::prelude::ASSET_ROOT;
+
+    fn assert_same(first: &Chunk, second: &Chunk) {
+        assert_eq!(first.data.len(), second.data.len());
+        assert!(first
+            .data
+            .iter()
+            .all(|(pos, block)| second.data[pos].id == block.id));
+    }
+
+    #[test]
+    fn test_region_position() {
+        assert_eq!(region_position(IVec2::new(0, 31)), IVec2::new(0, 0));
+        assert_eq!(region_position(IVec2::new(-1, 32)), IVec2::new(-1, 1));
+        assert_eq!(Region::index(IVec2::new(-1, 32)), 31);
+    }
+
+    #[test]
+    fn test_chunk_save() {
+        let generator = NoiseGenerator::new(42);
+        let chunk_pos = IVec2::new(-3, 7);
+        let chunk = generator.generate(chunk_pos);
+
+        let save = ChunkSave::new(chunk_pos, &chunk);
+        assert_eq!(save.blocks.len(), chunk.data.len());
+        let ids = chunk
+            .data
+            .values()
+            .map(|block| &block.id)
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(save.palette.len(), ids.len());
+        assert!(save.palette.contains(&generator.stone.to_string()));
+
+        let loaded = save.clone().into_chunk().unwrap();
+        assert_same(&chunk, &loaded);
+
+        let mut broken = save;
+        broken.blocks[0][3] = broken.palette.len() as i32;
+        assert!(matches!(
+            broken.into_chunk(),
+            Err(RegionError::Corrupted(_))
+        ));
+        assert!(loaded.biomes.is_none());
+
+        let biomes = Biomes::load(Path::new(ASSET_ROOT)).unwrap();
+        let generator = NoiseGenerator::new(42).with_biomes(Arc::new(biomes));
+        let chunk = generator.generate(chunk_pos);
+        let save = ChunkSave::new(chunk_pos, &chunk);
+        let json = serde_json::to_value(&save).unwrap();
+        let loaded: ChunkSave = serde_json::from_value(json).unwrap();
+        let loaded = loaded.into_chunk().unwrap();
+        assert_same(&chunk, &loaded);
+        let (expected, biomes) = (chunk.biomes.unwrap(), loaded.biomes.unwrap());
+        assert_eq!(biomes.palette(), expected.palette());
+        assert_eq!(biomes.columns(), expected.columns());
+        assert!(!biomes.is_resolved());
+
+        let mut broken = save;
+        broken.biomes.as_mut().unwrap().columns.pop();
+        assert!(matches!(
+            broken.into_chunk(),
+            Err(RegionError::Corrupted(_))
+        ));
+    }
+
+    #[test]
+    fn test_region_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = RegionStorage::new(dir.path().join("region"));
+        let generator = NoiseGenerator::new(7);
+
+        // two regions, one of them with two chunks
+        let positions = [IVec2::new(0, 0), IVec2::new(31, 5), IVec2::new(-1, 0)];
+        let chunks = positions.map(|pos| generator.generate(pos));
+        storage
+            .save_chunks(positions.iter().copied().zip(chunks.iter()))
+            .unwrap();
+        assert!(storage.region_path(IVec2::new(0, 0)).exists());
+        assert!(storage.region_path(IVec2::new(-1, 0)).exists());
+
+        for (pos, chunk) in positions.iter().zip(chunks.iter()) {
+            assert_same(chunk, &storage.load_chunk(*pos).unwrap().unwrap());
+        }
+        assert!(storage.load_chunk(IVec2::new(1, 0)).unwrap().is_none());
+        assert!(storage.load_chunk(IVec2::new(100, 0)).unwrap().is_none());
+
+        // overwrite a chunk, the other chunks of the region are kept
+        let mut edited = generator.generate(IVec2::ZERO);
+        edited.data.clear();
+        edited.data.insert(
+            IVec3::new(1, 2, 3),
+            BlockData {
+                id: BlockId::from_static("bevy_craft:block/dirt"),
+            },
+        );
+        storage.save_chunk(IVec2::ZERO, &edited).unwrap();
+        assert_same(&edited, &storage.load_chunk(IVec2::ZERO).unwrap().unwrap());
+        assert_same(
+            &chunks[1],
+            &storage.load_chunk(positions[1]).unwrap().unwrap(),
+        );
+
+        // no temporary file is left behind
+        let files = fs::read_dir(storage.dir()).unwrap().count();
+        assert_eq!(files, 2);
+    }
+
+    #[test]
+    fn test_chunk_fixtures() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = RegionStorage::new(dir.path());
+        let chunk_pos = IVec2::new(-1, 2);
+        let fixtures = [
+            include_str!("fixtures/chunk_v1.json"),
+            include_str!("fixtures/chunk_v2.json"),
+        ];
+
+        for fixture in fixtures {
+            let mut region = Region::default();
+            region
+                .insert_json(chunk_pos, fixture.as_bytes(), ChunkCompression::Zlib)
+                .unwrap();
+            region
+                .write(&storage.region_path(region_position(chunk_pos)))
+                .unwrap();
+
+            let save = region.get(chunk_pos, &Migrations::builtin()).unwrap();
+            assert_eq!(save.unwrap().data_version, DATA_VERSION);
+
+            let chunk = storage.load_chunk(chunk_pos).unwrap().unwrap();
+            assert_eq!(chunk.data.len(), 3);
+            assert_eq!(chunk.position(), Some(chunk_pos));
+            assert_eq!(
+                chunk.data[&IVec3::new(-16, 1, 32)].id,
+                BlockId::from_static("bevy_craft:block/dirt")
+            );
+            assert_eq!(
+                chunk.data[&IVec3::new(-1, 0, 47)].id,
+                BlockId::from_static("bevy_craft:block/stone")
+            );
+        }
+
+        // a block renamed after the chunk was saved
+        let storage = storage.with_migrations(Arc::new(Migrations::builtin().with_step(
+            DATA_VERSION + 1,
+            MigrationStep::remap_blocks([("bevy_craft:block/dirt", "bevy_craft:block/mud")]),
+        )));
+        let chunk = storage.load_chunk(chunk_pos).unwrap().unwrap();
+        assert_eq!(
+            chunk.data[&IVec3::new(-16, 1, 32)].id,
+            BlockId::from_static("bevy_craft:block/mud")
+        );
+
+        // saved by a newer version
+        let mut region = Region::default();
+        region
+            .insert_json(
+                chunk_pos,
+                br#"{ "data_version": 99, "position": [-1, 2], "palette": [], "blocks": [] }"#,
+                ChunkCompression::Raw,
+            )
+            .unwrap();
+        assert!(matches!(
+            region.get(chunk_pos, &Migrations::builtin()),
+            Err(RegionError::Migration(MigrationError::TooNew(
+                99,
+                DATA_VERSION
+            )))
+        ));
+    }
+
+    #[test]
+    fn test_migrated_round_trip() {
+        fn raise_blocks(value: &mut serde_json::Value) -> Result<(), MigrationError> {
+            let blocks = value["blocks"]
+                .as_array_mut()
+                .ok_or_else(|| MigrationError::Invalid("blocks".to_string()))?;
+            for block in blocks {
+                block[1] = serde_json::Value::from(block[1].as_i64().unwrap_or_default() + 1);
+            }
+            Ok(())
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let migrations = Migrations::builtin().with_step(
+            DATA_VERSION + 1,
+            MigrationStep::Upgrade {
+                kind: SaveKind::Chunk,
+                upgrade: raise_blocks,
+            },
+        );
+        let storage = RegionStorage::new(dir.path()).with_migrations(Arc::new(migrations));
+        let chunk_pos = IVec2::new(-1, 2);
+        let mut region = Region::default();
+        region
+            .insert_json(
+                chunk_pos,
+                include_bytes!("fixtures/chunk_v2.json"),
+                ChunkCompression::Zlib,
+            )
+            .unwrap();
+        region
+            .write(&storage.region_path(region_position(chunk_pos)))
+            .unwrap();
+
+        let dirt = BlockId::from_static("bevy_craft:block/dirt");
+        let chunk = storage.load_chunk(chunk_pos).unwrap().unwrap();
+        assert_eq!(chunk.data[&IVec3::new(-16, 2, 32)].id, dirt);
+
+        // saved at the latest version, the upgrade does not run again
+        storage.save_chunk(chunk_pos, &chunk).unwrap();
+        let region = Region::read(&storage.region_path(region_position(chunk_pos))).unwrap();
+        let data = region.chunks[Region::index(chunk_pos)].as_ref().unwrap();
+        let value = serde_json::from_slice(&ChunkCompression::decompress(data).unwrap()).unwrap();
+        assert_eq!(data_version(&value), Ok(DATA_VERSION + 1));
+        let loaded = storage.load_chunk(chunk_pos).unwrap().unwrap();
+        assert_same(&chunk, &loaded);
+        assert_eq!(loaded.data[&IVec3::new(-16, 2, 32)].id, dirt);
+    }
+
+    #[test]
+    fn test_uncompressed_region() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = RegionStorage::new(dir.path());
+        storage.compression = ChunkCompression::Raw;
+
+        let chunk = NoiseGenerator::new(7).generate(IVec2::new(2, 2));
+        storage.save_chunk(IVec2::new(2, 2), &chunk).unwrap();
+        assert_same(
+            &chunk,
+            &storage.load_chunk(IVec2::new(2, 2)).unwrap().unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_corrupted_region() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = RegionStorage::new(dir.path());
+        let chunk = NoiseGenerator::new(7).generate(IVec2::ZERO);
+        storage.save_chunk(IVec2::ZERO, &chunk).unwrap();
+
+        // a crash while writing leaves the temporary file, the region is untouched
+        let path = storage.region_path(IVec2::ZERO);
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, b"garbage").unwrap();
+        assert!(storage.load_chunk(IVec2::ZERO).unwrap().is_some());
+
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 10]).unwrap();
+        assert!(matches!(
+            storage.load_chunk(IVec2::ZERO),
+            Err(RegionError::Corrupted(_))
+        ));
+        assert!(storage.load(IVec2::ZERO).is_none());
+
+        fs::write(&path, b"not a region").unwrap();
+        assert!(matches!(
+            storage.load_chunk(IVec2::ZERO),
+            Err(RegionError::Corrupted(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_ids() {
+        let chunk = NoiseGenerator::new(7).generate(IVec2::ZERO);
+        let mut save = ChunkSave::new(IVec2::ZERO, &chunk);
+        save.palette[0] = "bevy_craft:models/block/stone.json".to_string();
+        assert!(matches!(save.into_chunk(), Err(RegionError::Corrupted(_))));
+    }
+}
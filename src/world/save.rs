@@ -0,0 +1,552 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{platform::collections::HashMap, prelude::*};
+use derive_more::derive::{Display, Error, From};
+use serde::{Deserialize, Serialize};
+
+use crate::{assets::prelude::*, chunks::prelude::*, identity::prelude::*, world::prelude::*};
+
+pub const DEFAULT_SAVES_DIR: &str = "saves";
+pub const LEVEL_FILE: &str = "level.json";
+pub const REGION_DIR: &str = "region";
+
+#[derive(Debug, Error, Display, From)]
+pub enum SaveError {
+    #[display("save io error: {}", _0)]
+    Io(std::io::Error),
+    #[display("invalid level: {}", _0)]
+    Json(serde_json::Error),
+    #[display("failed to migrate level: {}", _0)]
+    Migration(MigrationError),
+    #[display("failed to save chunks: {}", _0)]
+    Region(RegionError),
+    #[display("world {{ {} }} not found", _0)]
+    #[from(ignore)]
+    NotFound(#[error(not(source))] String),
+    #[display("world {{ {} }} already exists", _0)]
+    #[from(ignore)]
+    AlreadyExists(#[error(not(source))] String),
+    #[display("invalid world name {{ {} }}", _0)]
+    #[from(ignore)]
+    InvalidName(#[error(not(source))] String),
+}
+
+/// settings of `NoiseGenerator`, missing fields are the defaults
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct GeneratorSettings {
+    pub base_height: i32,
+    pub amplitude: f64,
+    pub frequency: f64,
+    pub overhang: f64,
+    pub overhang_frequency: f64,
+    pub caves: CaveSettings,
+    pub features: Vec<PlacedFeature>,
+    pub soil_depth: i32,
+    pub surface: String,
+    pub subsurface: String,
+    pub stone: String,
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        let generator = NoiseGenerator::new(0);
+        Self {
+            base_height: generator.base_height,
+            amplitude: generator.amplitude,
+            frequency: generator.frequency,
+            overhang: generator.overhang,
+            overhang_frequency: generator.overhang_frequency,
+            caves: generator.caves,
+            features: generator.features,
+            soil_depth: generator.soil_depth,
+            surface: generator.surface.to_string(),
+            subsurface: generator.subsurface.to_string(),
+            stone: generator.stone.to_string(),
+        }
+    }
+}
+
+impl GeneratorSettings {
+    pub fn generator(&self, seed: u32) -> NoiseGenerator {
+        let mut generator = NoiseGenerator::new(seed).with_frequency(self.frequency);
+        generator.base_height = self.base_height;
+        generator.amplitude = self.amplitude;
+        generator.overhang = self.overhang;
+        generator.overhang_frequency = self.overhang_frequency;
+        generator.caves = self.caves.clone();
+        generator.features = self.features.clone();
+        generator.soil_depth = self.soil_depth;
+        // invalid ids keep the default blocks
+        for (id, block) in [
+            (&self.surface, &mut generator.surface),
+            (&self.subsurface, &mut generator.subsurface),
+            (&self.stone, &mut generator.stone),
+        ] {
+            if let Ok(id) = BlockId::try_from(id.as_str()) {
+                *block = id;
+            }
+        }
+        generator
+    }
+}
+
+/// content of `level.json`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LevelData {
+    pub data_version: u32,
+    /// display name, the directory of the world may differ
+    pub name: String,
+    pub seed: u32,
+    #[serde(default)]
+    pub generator: GeneratorSettings,
+    /// where the player appears
+    pub spawn: IVec3,
+    /// fixed ticks since the world was created
+    #[serde(default)]
+    pub game_time: u64,
+    /// namespaces of the resource packs the world was played with
+    #[serde(default)]
+    pub resource_packs: Vec<String>,
+}
+
+impl LevelData {
+    /// spawn on the open terrain nearest to the origin
+    pub fn new(name: impl Into<String>, seed: u32, generator: GeneratorSettings) -> Self {
+        let spawn = generator.generator(seed).spawn(IVec2::ZERO);
+        Self {
+            data_version: DATA_VERSION,
+            name: name.into(),
+            seed,
+            generator,
+            spawn,
+            game_time: 0,
+            resource_packs: vec!["bevy_craft".to_string()],
+        }
+    }
+}
+
+/// a seed from the current time
+pub fn random_seed() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u32)
+        .unwrap_or_default()
+}
+
+/// a world on disk, `level.json` and the region files in its directory
+#[derive(Resource, Debug, Clone)]
+pub struct SavedWorld {
+    pub dir: PathBuf,
+    pub level: LevelData,
+    migrations: Arc<Migrations>,
+}
+
+impl SavedWorld {
+    pub fn storage(&self) -> RegionStorage {
+        RegionStorage::new(self.dir.join(REGION_DIR)).with_migrations(self.migrations.clone())
+    }
+
+    pub fn generator(&self) -> NoiseGenerator {
+        self.level.generator.generator(self.level.seed)
+    }
+
+    /// the chunks from `min` to `max` inclusive, loaded from the region files or generated,
+    /// their tints resolved and their light spread across their borders until it settles
+    pub fn load_area(
+        &self,
+        generator: &dyn ChunkGenerator,
+        min: IVec2,
+        max: IVec2,
+        biomes: &Biomes,
+        definitions: Option<&BlockDefinitions>,
+    ) -> Result<HashMap<IVec2, Chunk>, SaveError> {
+        let storage = self.storage();
+        let mut chunks = HashMap::default();
+        for x in min.x.min(max.x)..=min.x.max(max.x) {
+            for z in min.y.min(max.y)..=min.y.max(max.y) {
+                let chunk_pos = IVec2::new(x, z);
+                let mut chunk = match storage.load_chunk(chunk_pos)? {
+                    Some(chunk) => chunk,
+                    None => generator.generate(chunk_pos),
+                };
+                if let Some(chunk_biomes) = chunk.biomes.as_mut() {
+                    if !chunk_biomes.is_resolved() {
+                        chunk_biomes.resolve(|biome_id| biomes.tints(biome_id));
+                    }
+                }
+                chunk.compute_light(definitions);
+                chunks.insert(chunk_pos, chunk);
+            }
+        }
+
+        // like `relight_borders`, a chunk whose border changed is relit
+        loop {
+            let relit = chunks
+                .iter()
+                .filter_map(|(chunk_pos, chunk)| {
+                    let border = BorderLight::new(*chunk_pos, |pos| chunks.get(&pos));
+                    let changed = chunk
+                        .light
+                        .as_ref()
+                        .is_some_and(|light| light.border() != &border);
+                    changed.then_some((*chunk_pos, border))
+                })
+                .collect::<Vec<_>>();
+            if relit.is_empty() {
+                return Ok(chunks);
+            }
+            for (chunk_pos, border) in relit {
+                if let Some(chunk) = chunks.get_mut(&chunk_pos) {
+                    chunk.compute_light_with(definitions, border);
+                }
+            }
+        }
+    }
+
+    /// write `level.json` through a temporary file, like the region files
+    pub fn save_level(&self) -> Result<(), SaveError> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(LEVEL_FILE);
+        let tmp = self.dir.join(format!("{}.tmp", LEVEL_FILE));
+        let mut file = File::create(&tmp)?;
+        // stamped like the chunks, with the version `migrations` upgrades to
+        let level = LevelData {
+            data_version: self.migrations.latest(),
+            ..self.level.clone()
+        };
+        file.write_all(&serde_json::to_vec_pretty(&level)?)?;
+        file.sync_all()?;
+        drop(file);
+        fs::rename(tmp, path)?;
+
+        // persist the rename itself
+        #[cfg(unix)]
+        File::open(&self.dir)?.sync_all()?;
+        Ok(())
+    }
+
+    /// read `level.json`, upgraded by `migrations` if it was saved by an earlier version
+    fn read(dir: PathBuf, migrations: Arc<Migrations>) -> Result<Self, SaveError> {
+        let mut value = serde_json::from_slice(&fs::read(dir.join(LEVEL_FILE))?)?;
+        migrations.migrate(SaveKind::Level, &mut value)?;
+        Ok(Self {
+            dir,
+            level: serde_json::from_value(value)?,
+            migrations,
+        })
+    }
+}
+
+/// the directory which holds a directory per world
+#[derive(Debug, Clone)]
+pub struct Saves {
+    dir: PathBuf,
+    migrations: Arc<Migrations>,
+}
+
+impl Default for Saves {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAVES_DIR)
+    }
+}
+
+impl Saves {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            migrations: Migrations::shared(),
+        }
+    }
+
+    pub fn with_migrations(mut self, migrations: Arc<Migrations>) -> Self {
+        self.migrations = migrations;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.world_dir(name)
+            .is_ok_and(|dir| dir.join(LEVEL_FILE).is_file())
+    }
+
+    /// the directory of the world `name`, which must be a single path component
+    pub fn world_dir(&self, name: &str) -> Result<PathBuf, SaveError> {
+        let invalid = name.is_empty()
+            || name == "."
+            || name == ".."
+            || name.contains(['/', '\\', ':'])
+            || name.trim() != name;
+        if invalid {
+            return Err(SaveError::InvalidName(name.to_string()));
+        }
+        Ok(self.dir.join(name))
+    }
+
+    pub fn create(&self, name: &str, level: LevelData) -> Result<SavedWorld, SaveError> {
+        let dir = self.world_dir(name)?;
+        if dir.exists() {
+            return Err(SaveError::AlreadyExists(name.to_string()));
+        }
+
+        let world = SavedWorld {
+            dir,
+            level,
+            migrations: self.migrations.clone(),
+        };
+        world.save_level()?;
+        Ok(world)
+    }
+
+    pub fn load(&self, name: &str) -> Result<SavedWorld, SaveError> {
+        if !self.exists(name) {
+            return Err(SaveError::NotFound(name.to_string()));
+        }
+        SavedWorld::read(self.world_dir(name)?, self.migrations.clone())
+    }
+
+    /// the directory name and the level of every world, sorted by name,
+    /// directories without a readable `level.json` are skipped
+    pub fn list(&self) -> Result<Vec<(String, LevelData)>, SaveError> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut result = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            match SavedWorld::read(entry.path(), self.migrations.clone()) {
+                Ok(world) => result.push((name, world.level)),
+                Err(err) => {
+                    if entry.path().is_dir() {
+                        warn!("skip world {}: {}", name, err);
+                    }
+                }
+            }
+        }
+        result.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(result)
+    }
+
+    /// move the world into the directory `to` and use `to` as its display name
+    pub fn rename(&self, from: &str, to: &str) -> Result<SavedWorld, SaveError> {
+        let mut world = self.load(from)?;
+        let dir = self.world_dir(to)?;
+        if dir.exists() {
+            return Err(SaveError::AlreadyExists(to.to_string()));
+        }
+
+        fs::rename(&world.dir, &dir)?;
+        world.dir = dir;
+        world.level.name = to.to_string();
+        world.save_level()?;
+        Ok(world)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), SaveError> {
+        if !self.exists(name) {
+            return Err(SaveError::NotFound(name.to_string()));
+        }
+        fs::remove_dir_all(self.world_dir(name)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_level_data() {
+        let level = LevelData::new("test", 42, GeneratorSettings::default());
+        let generator = level.generator.generator(level.seed);
+        assert_eq!(level.spawn, generator.spawn(IVec2::ZERO));
+        assert_eq!(level.data_version, DATA_VERSION);
+
+        let json = serde_json::to_string(&level).unwrap();
+        assert_eq!(serde_json::from_str::<LevelData>(&json).unwrap(), level);
+
+        // optional fields fall back to the defaults
+        let level = serde_json::from_str::<LevelData>(
+            r#"{
+                "data_version": 2,
+                "name": "old",
+                "seed": 7,
+                "generator": { "amplitude": 20.0 },
+                "spawn": [0, 30, 0]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(level.generator.amplitude, 20.0);
+        assert_eq!(level.generator.stone, "bevy_craft:block/stone");
+        assert_eq!(level.game_time, 0);
+        assert!(level.resource_packs.is_empty());
+    }
+
+    #[test]
+    fn test_level_fixtures() {
+        let dir = tempfile::tempdir().unwrap();
+        let saves = Saves::new(dir.path());
+        let fixtures = [
+            ("v1", include_str!("fixtures/level_v1.json")),
+            ("v2", include_str!("fixtures/level_v2.json")),
+        ];
+
+        for (name, fixture) in fixtures {
+            fs::create_dir_all(dir.path().join(name)).unwrap();
+            fs::write(dir.path().join(name).join(LEVEL_FILE), fixture).unwrap();
+
+            let world = saves.load(name).unwrap();
+            assert_eq!(world.level.data_version, DATA_VERSION);
+            assert_eq!(world.level.name, "Old World");
+            assert_eq!(world.level.seed, 1234);
+            assert_eq!(world.level.generator.base_height, 20);
+            assert_eq!(world.level.spawn, IVec3::new(0, 21, 0));
+            assert_eq!(world.level.game_time, 1200);
+            assert_eq!(world.level.resource_packs, ["bevy_craft"]);
+            // generated like before the density generator
+            assert_eq!(world.level.generator.overhang, 0.0);
+            assert!(!world.level.generator.caves.enabled);
+            assert!(world.level.generator.features.is_empty());
+            assert_eq!(
+                world.level.generator.caves.min_y,
+                CaveSettings::default().min_y
+            );
+
+            // the migrated level is written in the current format
+            world.save_level().unwrap();
+            let json = fs::read_to_string(world.dir.join(LEVEL_FILE)).unwrap();
+            assert!(!json.contains("format_version"));
+            assert_eq!(saves.load(name).unwrap().level, world.level);
+        }
+
+        // the current format keeps its generator
+        fs::create_dir_all(dir.path().join("v3")).unwrap();
+        fs::write(
+            dir.path().join("v3").join(LEVEL_FILE),
+            include_str!("fixtures/level_v3.json"),
+        )
+        .unwrap();
+        let generator = saves.load("v3").unwrap().level.generator;
+        assert_eq!(generator.overhang, 4.0);
+        assert!(generator.caves.enabled);
+        assert_eq!(generator.caves.cheese_threshold, 0.5);
+        assert_eq!(generator.features.len(), 1);
+    }
+
+    #[test]
+    fn test_saves() {
+        let dir = tempfile::tempdir().unwrap();
+        let saves = Saves::new(dir.path().join("saves"));
+        assert!(saves.list().unwrap().is_empty());
+
+        let level = LevelData::new("first", 1, GeneratorSettings::default());
+        let world = saves.create("first", level.clone()).unwrap();
+        assert!(world.dir.join(LEVEL_FILE).is_file());
+        assert!(matches!(
+            saves.create("first", level.clone()),
+            Err(SaveError::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            saves.create("../escape", level.clone()),
+            Err(SaveError::InvalidName(_))
+        ));
+
+        saves
+            .create(
+                "second",
+                LevelData::new("second", 2, GeneratorSettings::default()),
+            )
+            .unwrap();
+        // not a world
+        fs::create_dir_all(saves.dir().join("junk")).unwrap();
+        let names = saves
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["first", "second"]);
+
+        // chunks are kept when the world is renamed
+        let chunk = world.generator().generate(IVec2::ZERO);
+        world.storage().save_chunk(IVec2::ZERO, &chunk).unwrap();
+        let renamed = saves.rename("first", "third").unwrap();
+        assert_eq!(renamed.level.name, "third");
+        assert!(!saves.exists("first"));
+        assert_eq!(saves.load("third").unwrap().level, renamed.level);
+        assert!(renamed.storage().load_chunk(IVec2::ZERO).unwrap().is_some());
+        assert!(matches!(
+            saves.rename("second", "third"),
+            Err(SaveError::AlreadyExists(_))
+        ));
+
+        saves.delete("third").unwrap();
+        assert!(matches!(saves.load("third"), Err(SaveError::NotFound(_))));
+        assert!(matches!(saves.delete("third"), Err(SaveError::NotFound(_))));
+        assert_eq!(saves.list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_load_area() {
+        let dir = tempfile::tempdir().unwrap();
+        let saves = Saves::new(dir.path());
+        let world = saves
+            .create(
+                "area",
+                LevelData::new("area", 3, GeneratorSettings::default()),
+            )
+            .unwrap();
+        let generator = world.generator();
+
+        // the saved chunk is used instead of the generated one
+        let mut edited = generator.generate(IVec2::ZERO);
+        edited.data.clear();
+        edited.data.insert(
+            IVec3::ZERO,
+            BlockData {
+                id: BlockId::from_static("bevy_craft:block/stone"),
+            },
+        );
+        world.storage().save_chunk(IVec2::ZERO, &edited).unwrap();
+
+        let chunks = world
+            .load_area(
+                &generator,
+                IVec2::new(1, 1),
+                IVec2::ZERO,
+                &Biomes::default(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[&IVec2::ZERO].data.len(), 1);
+        assert_eq!(
+            chunks[&IVec2::X].data.len(),
+            generator.generate(IVec2::X).data.len()
+        );
+        for (chunk_pos, chunk) in &chunks {
+            let light = chunk.light.as_ref().unwrap();
+            // the light of the neighbors is spread into the chunk
+            assert_eq!(
+                light.border(),
+                &BorderLight::new(*chunk_pos, |pos| chunks.get(&pos))
+            );
+            assert!(!light.border().is_empty());
+        }
+    }
+}
@@ -0,0 +1,558 @@
+use std::ops::{Deref, DerefMut};
+
+use bevy::{
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+};
+
+use crate::{
+    assets::prelude::*, chunks::prelude::*, identity::prelude::*, render::prelude::*,
+    world::prelude::*,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ViewShape {
+    Square,
+    #[default]
+    Circle,
+}
+
+impl ViewShape {
+    /// whether the chunk `offset` away from the center is in view
+    pub fn contains(&self, offset: IVec2, radius: i32) -> bool {
+        match self {
+            ViewShape::Square => offset.x.abs() <= radius && offset.y.abs() <= radius,
+            ViewShape::Circle => offset.length_squared() <= radius * radius,
+        }
+    }
+}
+
+/// keep the chunks around the entity loaded
+#[derive(Component, Debug, Clone)]
+pub struct ChunkLoader {
+    /// view distance in chunks
+    pub radius: i32,
+    pub shape: ViewShape,
+}
+
+impl Default for ChunkLoader {
+    fn default() -> Self {
+        Self {
+            radius: 4,
+            shape: ViewShape::default(),
+        }
+    }
+}
+
+impl ChunkLoader {
+    /// chunk positions in view around `center`, the nearest first
+    pub fn chunks(&self, center: IVec2) -> Vec<IVec2> {
+        let mut result = Vec::new();
+        for x in -self.radius..=self.radius {
+            for z in -self.radius..=self.radius {
+                let offset = IVec2::new(x, z);
+                if self.shape.contains(offset, self.radius) {
+                    result.push(center + offset);
+                }
+            }
+        }
+        result.sort_by_key(|pos| (*pos - center).length_squared());
+        result
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct StreamingSettings {
+    /// chunks which start loading per frame
+    pub loads_per_frame: usize,
+    /// chunks which are meshed per frame
+    pub meshes_per_frame: usize,
+}
+
+impl Default for StreamingSettings {
+    fn default() -> Self {
+        Self {
+            loads_per_frame: 8,
+            meshes_per_frame: 2,
+        }
+    }
+}
+
+/// the entity of every loading or loaded chunk, keyed by its `ChunkPosition`
+#[derive(Resource, Default)]
+pub struct LoadedChunks {
+    chunks: HashMap<IVec2, Entity>,
+}
+
+impl Deref for LoadedChunks {
+    type Target = HashMap<IVec2, Entity>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.chunks
+    }
+}
+
+impl DerefMut for LoadedChunks {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.chunks
+    }
+}
+
+/// the chunk position of a streamed chunk, known before the chunk is loaded
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkPosition(pub IVec2);
+
+/// the chunk is loaded from the storage or generated in the background
+#[derive(Component)]
+pub struct LoadingChunk {
+    task: Task<Chunk>,
+}
+
+/// the chunk is loaded but not meshed yet
+#[derive(Component)]
+pub struct NeedsMesh;
+
+/// the chunk was relit by `relight_borders`, its neighbors copy its light next
+#[derive(Component)]
+pub struct RelitChunk;
+
+type ChunkSources<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static ChunkPosition,
+        Ref<'static, Chunk>,
+        Has<RelitChunk>,
+    ),
+>;
+
+/// material shared by the meshes of every chunk
+#[derive(Resource)]
+pub struct ChunkMaterial(pub Handle<StandardMaterial>);
+
+fn loader_center(transform: &GlobalTransform) -> IVec2 {
+    chunk_position(transform.translation().floor().as_ivec3())
+}
+
+fn views(loaders: &Query<(&ChunkLoader, &GlobalTransform)>) -> Vec<Vec<IVec2>> {
+    loaders
+        .iter()
+        .map(|(loader, transform)| loader.chunks(loader_center(transform)))
+        .collect()
+}
+
+/// despawn the chunks out of view of every `ChunkLoader`
+pub fn unload_chunks(
+    mut commands: Commands,
+    loaders: Query<(&ChunkLoader, &GlobalTransform)>,
+    meshes_3d: Query<&Mesh3d>,
+    mut loaded: ResMut<LoadedChunks>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let wanted = views(&loaders)
+        .into_iter()
+        .flatten()
+        .collect::<HashSet<_>>();
+    loaded.retain(|pos, entity| {
+        if wanted.contains(pos) {
+            return true;
+        }
+        if let Ok(mesh) = meshes_3d.get(*entity) {
+            meshes.remove(&mesh.0);
+        }
+        commands.entity(*entity).despawn();
+        false
+    });
+}
+
+/// start loading the chunks in view of a `ChunkLoader`
+pub fn load_chunks(
+    mut commands: Commands,
+    settings: Res<StreamingSettings>,
+    generator: Res<WorldGenerator>,
+    storage: Option<Res<WorldStorage>>,
+    material: Res<ChunkMaterial>,
+    loaders: Query<(&ChunkLoader, &GlobalTransform)>,
+    mut loaded: ResMut<LoadedChunks>,
+) {
+    // interleave the views so that every loader gets its nearest chunks first
+    let views = views(&loaders);
+    let longest = views.iter().map(Vec::len).max().unwrap_or_default();
+    let missing = (0..longest)
+        .flat_map(|i| views.iter().filter_map(move |view| view.get(i)))
+        .filter(|pos| !loaded.contains_key(*pos))
+        .copied()
+        .collect::<Vec<_>>();
+
+    let pool = AsyncComputeTaskPool::get();
+    for pos in missing.into_iter().take(settings.loads_per_frame) {
+        if loaded.contains_key(&pos) {
+            continue;
+        }
+
+        let generator = generator.0.clone();
+        let storage = storage.as_ref().map(|storage| storage.0.clone());
+        let task = pool.spawn(async move {
+            match storage.and_then(|storage| storage.load(pos)) {
+                Some(chunk) => {
+                    generator.loaded(pos);
+                    chunk
+                }
+                None => generator.generate(pos),
+            }
+        });
+        let entity = commands
+            .spawn((
+                ChunkPosition(pos),
+                LoadingChunk { task },
+                MeshMaterial3d(material.0.clone()),
+                Transform::default(),
+                Visibility::default(),
+            ))
+            .id();
+        loaded.insert(pos, entity);
+    }
+}
+
+pub fn finish_loading(mut commands: Commands, mut loading: Query<(Entity, &mut LoadingChunk)>) {
+    for (entity, mut loading) in &mut loading {
+        if let Some(chunk) = block_on(future::poll_once(&mut loading.task)) {
+            commands
+                .entity(entity)
+                .remove::<LoadingChunk>()
+                .insert((chunk, NeedsMesh));
+        }
+    }
+}
+
+/// run OnEnter AppLoadState::Next
+pub fn create_chunk_material(
+    mut commands: Commands,
+    atlas: Res<AppTextureAtlas<TextureId>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(ChunkMaterial(materials.add(StandardMaterial {
+        base_color_texture: Some(atlas.atlas()),
+        ..default()
+    })));
+}
+
+/// compute the light of the loaded chunks, the light of their lit neighbors spreads into them
+pub fn light_chunks(
+    definitions: Option<Res<BlockDefinitions>>,
+    loaded: Res<LoadedChunks>,
+    pending: Query<(Entity, &ChunkPosition), With<NeedsMesh>>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    for (entity, chunk_pos) in &pending {
+        if !chunks.get(entity).is_ok_and(|chunk| chunk.light.is_none()) {
+            continue;
+        }
+        let border = BorderLight::new(chunk_pos.0, |pos| {
+            loaded.get(&pos).and_then(|entity| chunks.get(*entity).ok())
+        });
+        if let Ok(mut chunk) = chunks.get_mut(entity) {
+            // not an edit, must not trigger `remesh_chunks`
+            chunk
+                .bypass_change_detection()
+                .compute_light_with(definitions.as_deref(), border);
+        }
+    }
+}
+
+/// copy the light of the loaded, edited and relit chunks into the border of their neighbors,
+/// a neighbor whose border changes is relit and remeshed, which is repeated until the light settles
+pub fn relight_borders(
+    mut commands: Commands,
+    definitions: Option<Res<BlockDefinitions>>,
+    loaded: Res<LoadedChunks>,
+    mut chunks: ParamSet<(ChunkSources, Query<&mut Chunk>)>,
+) {
+    let sources = chunks.p0();
+    let mut targets = HashSet::new();
+    for (entity, chunk_pos, chunk, relit) in &sources {
+        if relit {
+            commands.entity(entity).remove::<RelitChunk>();
+        }
+        if relit || chunk.is_changed() {
+            for z in -1..=1 {
+                for x in -1..=1 {
+                    let target = chunk_pos.0 + IVec2::new(x, z);
+                    if target != chunk_pos.0 {
+                        targets.insert(target);
+                    }
+                }
+            }
+        }
+    }
+
+    let lit = |chunk_pos: &IVec2| {
+        let entity = *loaded.get(chunk_pos)?;
+        let chunk = sources.get(entity).ok()?.2.into_inner();
+        chunk.light.is_some().then_some((entity, chunk))
+    };
+    let relit = targets
+        .iter()
+        .filter_map(|target| {
+            let (entity, chunk) = lit(target)?;
+            let border = BorderLight::new(*target, |pos| lit(&pos).map(|(_, chunk)| chunk));
+            let changed = chunk
+                .light
+                .as_ref()
+                .is_some_and(|light| light.border() != &border);
+            changed.then_some((entity, border))
+        })
+        .collect::<Vec<_>>();
+
+    let mut chunks = chunks.p1();
+    for (entity, border) in relit {
+        if let Ok(mut chunk) = chunks.get_mut(entity) {
+            // not an edit, the chunk is remeshed through `NeedsMesh`
+            chunk
+                .bypass_change_detection()
+                .compute_light_with(definitions.as_deref(), border);
+            commands.entity(entity).insert((RelitChunk, NeedsMesh));
+        }
+    }
+}
+
+/// look up the tints of the biomes of the chunks loaded from storage
+pub fn resolve_biomes(
+    biomes: Option<Res<Biomes>>,
+    mut pending: Query<&mut Chunk, With<NeedsMesh>>,
+) {
+    let default_biomes = Biomes::default();
+    let biomes = biomes.as_deref().unwrap_or(&default_biomes);
+    for mut chunk in &mut pending {
+        if chunk
+            .biomes
+            .as_ref()
+            .is_some_and(|biomes| !biomes.is_resolved())
+        {
+            // not an edit, must not trigger `remesh_chunks`
+            if let Some(chunk_biomes) = chunk.bypass_change_detection().biomes.as_mut() {
+                chunk_biomes.resolve(|biome_id| biomes.tints(biome_id));
+            }
+        }
+    }
+}
+
+/// mesh the loaded chunks, the nearest to a `ChunkLoader` first
+pub fn mesh_chunks(
+    mut commands: Commands,
+    settings: Res<StreamingSettings>,
+    meshing: ChunkMeshing,
+    loaders: Query<&GlobalTransform, With<ChunkLoader>>,
+    pending: Query<(Entity, &Chunk, &ChunkPosition), With<NeedsMesh>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let centers = loaders.iter().map(loader_center).collect::<Vec<_>>();
+    let distance = |pos: &ChunkPosition| {
+        centers
+            .iter()
+            .map(|center| (pos.0 - *center).length_squared())
+            .min()
+            .unwrap_or(i32::MAX)
+    };
+
+    let mut pending = pending.iter().collect::<Vec<_>>();
+    pending.sort_by_key(|(_, _, pos)| distance(pos));
+
+    let context = meshing.context();
+    for (entity, chunk, _) in pending.into_iter().take(settings.meshes_per_frame) {
+        let mesh = chunk.mesh(&context);
+        commands
+            .entity(entity)
+            .remove::<NeedsMesh>()
+            .insert(Mesh3d(meshes.add(mesh)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+
+    /// a single block in the corner of every chunk
+    struct FlatGenerator;
+
+    impl ChunkGenerator for FlatGenerator {
+        fn generate(&self, chunk_pos: IVec2) -> Chunk {
+            let mut chunk = Chunk::default();
+            chunk.data.insert(
+                (chunk_pos * CHUNK_SIZE).extend(0).xzy(),
+                BlockData {
+                    id: BlockId::from_static("bevy_craft:block/stone"),
+                },
+            );
+            chunk
+        }
+    }
+
+    #[test]
+    fn test_view_shape() {
+        let square = ChunkLoader {
+            radius: 2,
+            shape: ViewShape::Square,
+        };
+        assert_eq!(square.chunks(IVec2::ZERO).len(), 25);
+
+        let circle = ChunkLoader {
+            radius: 2,
+            shape: ViewShape::Circle,
+        };
+        let chunks = circle.chunks(IVec2::new(10, -3));
+        assert_eq!(chunks.len(), 13);
+        assert_eq!(chunks[0], IVec2::new(10, -3));
+        assert!(!chunks.contains(&IVec2::new(12, -1)));
+    }
+
+    fn load_all(app: &mut App) {
+        let start = Instant::now();
+        loop {
+            app.update();
+            let world = app.world_mut();
+            let loading = world
+                .query_filtered::<(), With<LoadingChunk>>()
+                .iter(world)
+                .count();
+            if loading == 0 {
+                return;
+            }
+            assert!(start.elapsed() < Duration::from_secs(10));
+        }
+    }
+
+    #[test]
+    fn test_streaming() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<LoadedChunks>()
+            .insert_resource(StreamingSettings {
+                loads_per_frame: 100,
+                meshes_per_frame: 0,
+            })
+            .insert_resource(WorldGenerator(Arc::new(FlatGenerator)))
+            .insert_resource(ChunkMaterial(Handle::default()))
+            .add_systems(
+                Update,
+                (unload_chunks, load_chunks, finish_loading, light_chunks).chain(),
+            );
+
+        let loader = app
+            .world_mut()
+            .spawn((
+                ChunkLoader {
+                    radius: 1,
+                    shape: ViewShape::Square,
+                },
+                GlobalTransform::from_translation(Vec3::new(8.0, 0.0, 8.0)),
+            ))
+            .id();
+        load_all(&mut app);
+
+        let loaded = app.world().resource::<LoadedChunks>();
+        assert_eq!(loaded.len(), 9);
+        let entity = loaded[&IVec2::new(-1, 1)];
+        app.update();
+        let chunk = app.world().get::<Chunk>(entity).unwrap();
+        assert_eq!(chunk.position(), Some(IVec2::new(-1, 1)));
+        assert!(chunk.light.is_some());
+
+        // move two chunks along +X, the column at x = -1 and x = 0 is unloaded
+        app.world_mut()
+            .entity_mut(loader)
+            .insert(GlobalTransform::from_translation(Vec3::new(40.0, 0.0, 8.0)));
+        load_all(&mut app);
+
+        let loaded = app.world().resource::<LoadedChunks>();
+        assert_eq!(loaded.len(), 9);
+        assert!(!loaded.contains_key(&IVec2::new(0, 0)));
+        assert!(loaded.contains_key(&IVec2::new(3, 1)));
+        assert!(app.world().get_entity(entity).is_err());
+    }
+
+    #[test]
+    fn test_relight_borders() {
+        let stone = BlockId::from_static("bevy_craft:block/stone");
+        let torch = BlockId::from_static("bevy_craft:block/torch");
+        let mut definitions = BlockDefinitions::default();
+        definitions.insert(
+            torch.clone(),
+            BlockDefinition {
+                opaque: false,
+                light_emission: 14,
+                ..default()
+            },
+        );
+
+        // a dark layer at y = 1 between a floor and a roof
+        let slab = |chunk_pos: IVec2| {
+            let mut chunk = Chunk::default();
+            for x in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    let pos = (chunk_pos * CHUNK_SIZE + IVec2::new(x, z)).extend(0).xzy();
+                    for y in [0, 2] {
+                        chunk
+                            .data
+                            .insert(pos.with_y(y), BlockData { id: stone.clone() });
+                    }
+                }
+            }
+            chunk
+        };
+        let torch_pos = IVec3::new(15, 1, 5);
+        let mut lit = slab(IVec2::ZERO);
+        lit.data.insert(torch_pos, BlockData { id: torch });
+
+        let mut app = App::new();
+        app.init_resource::<LoadedChunks>()
+            .insert_resource(definitions)
+            .add_systems(Update, (light_chunks, relight_borders).chain());
+        let mut spawn = |chunk: Chunk, chunk_pos: IVec2| {
+            let entity = app
+                .world_mut()
+                .spawn((chunk, ChunkPosition(chunk_pos), NeedsMesh))
+                .id();
+            app.world_mut()
+                .resource_mut::<LoadedChunks>()
+                .insert(chunk_pos, entity);
+            entity
+        };
+        let first = spawn(lit, IVec2::ZERO);
+        let second = spawn(slab(IVec2::X), IVec2::X);
+        let settle = |app: &mut App| {
+            for _ in 0..=MAX_LIGHT_LEVEL {
+                app.update();
+            }
+            let world = app.world_mut();
+            assert_eq!(world.query::<&RelitChunk>().iter(world).count(), 0);
+        };
+        settle(&mut app);
+
+        // the torch lights the neighbor, which lights the border of the torch chunk
+        let light = |app: &App, entity: Entity, pos: IVec3| {
+            app.world().get::<Chunk>(entity).unwrap().light(pos)
+        };
+        assert_eq!(light(&app, second, IVec3::new(16, 1, 5)), Light::new(0, 13));
+        assert_eq!(light(&app, first, IVec3::new(16, 1, 5)), Light::new(0, 13));
+
+        // the edit relights the neighbor until the light is gone
+        let world = app.world_mut();
+        let definitions = world.remove_resource::<BlockDefinitions>();
+        let mut chunk = world.get_mut::<Chunk>(first).unwrap();
+        chunk.data.remove(&torch_pos);
+        chunk.update_light(torch_pos, definitions.as_ref());
+        world.insert_resource(definitions.unwrap());
+        settle(&mut app);
+        assert_eq!(light(&app, first, torch_pos), Light::DARK);
+        assert_eq!(light(&app, second, IVec3::new(16, 1, 5)), Light::DARK);
+    }
+}